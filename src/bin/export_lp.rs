@@ -23,7 +23,6 @@
 use ddd::parser;
 use ddd::problem::{DelayCostThresholds, DelayCostType, DelayMeasurementType, Problem};
 
-use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::{BufWriter, Write};
@@ -41,7 +40,8 @@ fn main() {
         );
         eprintln!("\nCost types: finsteps123, finsteps12345, finsteps139,");
         eprintln!("            finsteps1_3min, finsteps1_5min,");
-        eprintln!("            infsteps60, infsteps180, infsteps360, cont");
+        eprintln!("            infsteps60, infsteps180, infsteps360, cont,");
+        eprintln!("            custom:<spec> (e.g. custom:step@0=1;step@180=2;linear@600=1)");
         std::process::exit(1);
     }
 
@@ -54,8 +54,8 @@ fn main() {
         ".".to_string()
     };
 
-    let cost_type = parse_cost_type(cost_str).unwrap_or_else(|| {
-        eprintln!("Unknown cost type: {}", cost_str);
+    let cost_type = parse_cost_type(cost_str).unwrap_or_else(|e| {
+        eprintln!("{}", e);
         std::process::exit(1);
     });
 
@@ -76,7 +76,11 @@ fn main() {
         false,
         None,
         |_| {},
-    );
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
     let problem = &named.problem;
     let n_visits: usize = problem.trains.iter().map(|t| t.visits.len()).sum();
     println!(
@@ -155,19 +159,11 @@ struct ExportStats {
     n_intervals: usize,
 }
 
-fn parse_cost_type(s: &str) -> Option<DelayCostType> {
-    match s.to_lowercase().as_str() {
-        "finsteps1_3min" => Some(DelayCostType::FiniteSteps1_3Min),
-        "finsteps1_5min" => Some(DelayCostType::FiniteSteps1_5Min),
-        "finsteps123" => Some(DelayCostType::FiniteSteps123),
-        "finsteps12345" => Some(DelayCostType::FiniteSteps12345),
-        "finsteps139" => Some(DelayCostType::FiniteSteps139),
-        "infsteps60" => Some(DelayCostType::InfiniteSteps60),
-        "infsteps180" => Some(DelayCostType::InfiniteSteps180),
-        "infsteps360" => Some(DelayCostType::InfiniteSteps360),
-        "infsteps123" => Some(DelayCostType::InfiniteSteps180),
-        "cont" => Some(DelayCostType::Continuous),
-        _ => None,
+fn parse_cost_type(s: &str) -> Result<DelayCostType, String> {
+    // txt instances have no per-visit penalties.
+    match DelayCostType::from_name(s)? {
+        DelayCostType::PiecewiseLinear => Err("txt instances have no piecewise penalties".into()),
+        cost_type => Ok(cost_type),
     }
 }
 
@@ -229,7 +225,7 @@ fn write_bigm_lp(
                             let diff = cost as i32 - prev_cost as i32;
                             assert!(diff > 0);
                             let name = format!("thr_{}_{}_{}", ti, vi, threshold);
-                            obj_terms.push(format!("{} {}", diff, name));
+                            obj_terms.push(format!("{} {}", diff * train.weight as i32, name));
                             cost_var_count += 1;
                         }
                     }
@@ -243,23 +239,49 @@ fn write_bigm_lp(
                 for (vi, visit) in train.visits.iter().enumerate() {
                     if visit.aimed.is_some() {
                         let name = format!("steps_{}_{}", ti, vi);
-                        obj_terms.push(format!("1 {}", name));
+                        obj_terms.push(format!("{} {}", train.weight, name));
                         cost_var_count += 1;
                     }
                 }
             }
         }
+        // Rejected by `parse_cost_type`.
+        DelayCostType::PiecewiseLinear => unreachable!(),
         DelayCostType::Continuous => {
             for (ti, train) in problem.trains.iter().enumerate() {
                 for (vi, visit) in train.visits.iter().enumerate() {
                     if visit.aimed.is_some() {
                         let name = format!("delay_{}_{}", ti, vi);
-                        obj_terms.push(format!("1 {}", name));
+                        obj_terms.push(format!("{} {}", train.weight, name));
                         cost_var_count += 1;
                     }
                 }
             }
         }
+        DelayCostType::Custom(f) => {
+            for (ti, train) in problem.trains.iter().enumerate() {
+                for (vi, visit) in train.visits.iter().enumerate() {
+                    if visit.aimed.is_some() {
+                        for thr_idx in (0..f.steps.len()).rev() {
+                            let prev_cost = f.steps.get(thr_idx + 1).map(|x| x.1).unwrap_or(0);
+                            let (threshold, cost) = f.steps[thr_idx];
+                            let diff = cost as i32 - prev_cost as i32;
+                            assert!(diff > 0);
+                            obj_terms.push(format!("{} thr_{}_{}_{}", diff * train.weight as i32, ti, vi, threshold));
+                            cost_var_count += 1;
+                        }
+                        if let Some(repeat) = f.repeat {
+                            obj_terms.push(format!("{} rep_{}_{}", repeat.increment * train.weight, ti, vi));
+                            cost_var_count += 1;
+                        }
+                        for (k, segment) in f.linear.iter().enumerate() {
+                            obj_terms.push(format!("{} lin_{}_{}_{}", segment.rate * train.weight, ti, vi, k));
+                            cost_var_count += 1;
+                        }
+                    }
+                }
+            }
+        }
     }
 
     // Write objective: split into multiple lines if too long
@@ -399,6 +421,8 @@ fn write_bigm_lp(
                 }
             }
         }
+        // Rejected by `parse_cost_type`.
+        DelayCostType::PiecewiseLinear => unreachable!(),
         DelayCostType::Continuous => {
             for (ti, train) in problem.trains.iter().enumerate() {
                 for (vi, visit) in train.visits.iter().enumerate() {
@@ -416,6 +440,57 @@ fn write_bigm_lp(
                 }
             }
         }
+        DelayCostType::Custom(f) => {
+            for (ti, train) in problem.trains.iter().enumerate() {
+                for (vi, visit) in train.visits.iter().enumerate() {
+                    if let Some(aimed) = visit.aimed {
+                        for (threshold, _cost) in f.steps.iter() {
+                            writeln!(
+                                w,
+                                " c{}: t_{}_{} - {} thr_{}_{}_{} <= {}",
+                                c_idx,
+                                ti, vi,
+                                BIG_M,
+                                ti, vi, threshold,
+                                threshold + aimed,
+                            )
+                            .unwrap();
+                            c_idx += 1;
+                            n_constraints += 1;
+                        }
+                        if let Some(repeat) = f.repeat {
+                            // interval * rep >= t - aimed - start
+                            writeln!(
+                                w,
+                                " c{}: t_{}_{} - {} rep_{}_{} <= {}",
+                                c_idx,
+                                ti, vi,
+                                repeat.interval,
+                                ti, vi,
+                                aimed + repeat.start,
+                            )
+                            .unwrap();
+                            c_idx += 1;
+                            n_constraints += 1;
+                        }
+                        for (k, segment) in f.linear.iter().enumerate() {
+                            // lin >= t - aimed - from
+                            writeln!(
+                                w,
+                                " c{}: t_{}_{} - lin_{}_{}_{} <= {}",
+                                c_idx,
+                                ti, vi,
+                                ti, vi, k,
+                                aimed + segment.from,
+                            )
+                            .unwrap();
+                            c_idx += 1;
+                            n_constraints += 1;
+                        }
+                    }
+                }
+            }
+        }
     }
 
     // ───── Lazy Constraints section (CPLEX LP) ─────
@@ -437,7 +512,10 @@ fn write_bigm_lp(
             writeln!(
                 w,
                 " {} <= t_{}_{} <= {}",
-                visit.earliest, ti, vi, BIG_M
+                visit.earliest,
+                ti,
+                vi,
+                visit.latest.unwrap_or(BIG_M)
             )
             .unwrap();
             n_vars += 1;
@@ -455,6 +533,18 @@ fn write_bigm_lp(
             }
         }
     }
+    if let DelayCostType::Custom(f) = cost_type {
+        for (ti, train) in problem.trains.iter().enumerate() {
+            for (vi, visit) in train.visits.iter().enumerate() {
+                if visit.aimed.is_some() {
+                    for k in 0..f.linear.len() {
+                        writeln!(w, " 0 <= lin_{}_{}_{} <= {}", ti, vi, k, BIG_M).unwrap();
+                        n_vars += 1;
+                    }
+                }
+            }
+        }
+    }
 
     // ───── Binary vars ─────
     writeln!(w, "Binary").unwrap();
@@ -498,6 +588,18 @@ fn write_bigm_lp(
             }
         }
     }
+    if let DelayCostType::Custom(f) = cost_type {
+        for (ti, train) in problem.trains.iter().enumerate() {
+            for (vi, visit) in train.visits.iter().enumerate() {
+                if visit.aimed.is_some() {
+                    for (threshold, _cost) in &f.steps {
+                        writeln!(w, " thr_{}_{}_{}", ti, vi, threshold).unwrap();
+                        n_vars += 1;
+                    }
+                }
+            }
+        }
+    }
 
     // ───── Integer vars (for InfiniteSteps) ─────
     if matches!(
@@ -516,6 +618,19 @@ fn write_bigm_lp(
             }
         }
     }
+    if let DelayCostType::Custom(f) = cost_type {
+        if f.repeat.is_some() {
+            writeln!(w, "General").unwrap();
+            for (ti, train) in problem.trains.iter().enumerate() {
+                for (vi, visit) in train.visits.iter().enumerate() {
+                    if visit.aimed.is_some() {
+                        writeln!(w, " rep_{}_{}", ti, vi).unwrap();
+                        n_vars += 1;
+                    }
+                }
+            }
+        }
+    }
 
     writeln!(w, "End").unwrap();
     let _ = cost_var_count;
@@ -567,7 +682,10 @@ fn write_ti_lp(
                 .map(|visit| {
                     let mut slots = Vec::new();
                     let mut t = round(visit.earliest);
-                    while t < visit.earliest + big_m {
+                    let end = visit.latest.map_or(visit.earliest + big_m, |latest| {
+                        (latest + 1).min(visit.earliest + big_m)
+                    });
+                    while t < end {
                         slots.push(t);
                         t += interval;
                     }
@@ -577,15 +695,6 @@ fn write_ti_lp(
         })
         .collect();
 
-    // Conflicting resources map (1:1 Rust lines 33-39)
-    let mut conflicting: HashMap<usize, Vec<usize>> = HashMap::new();
-    for (a, b) in problem.conflicts.iter() {
-        conflicting.entry(*a).or_default().push(*b);
-        if *a != *b {
-            conflicting.entry(*b).or_default().push(*a);
-        }
-    }
-
     let mut resource_visits: Vec<Vec<(usize, usize)>> = Vec::new();
     for (ti, train) in problem.trains.iter().enumerate() {
         for (vi, visit) in train.visits.iter().enumerate() {
@@ -670,36 +779,35 @@ fn write_ti_lp(
     for (t1_idx, train) in problem.trains.iter().enumerate() {
         eprintln!("   c3 t{}", t1_idx);
         for (v1_idx, visit) in train.visits.iter().enumerate() {
-            if let Some(conf_res) = conflicting.get(&visit.resource_id) {
-                for &other_res in conf_res.iter() {
-                    if other_res >= resource_visits.len() {
+            let conf_res = problem.conflicts.neighbours(visit.resource_id);
+            for &other_res in conf_res.iter() {
+                if other_res >= resource_visits.len() {
+                    continue;
+                }
+                for &(t2_idx, v2_idx) in resource_visits[other_res].iter() {
+                    if t2_idx == t1_idx {
                         continue;
                     }
-                    for &(t2_idx, v2_idx) in resource_visits[other_res].iter() {
-                        if t2_idx == t1_idx {
-                            continue;
-                        }
-                        for &t1_in in &time_disc[t1_idx][v1_idx] {
-                            for &t2_in in &time_disc[t2_idx][v2_idx] {
-                                let t1_out = t1_in + visit.travel_time;
-                                let t2_out = t2_in
-                                    + problem.trains[t2_idx].visits[v2_idx]
-                                        .travel_time;
-                                let separation = (t2_in - t1_out).max(t1_in - t2_out);
-                                let has_separation = separation >= 0;
-                                if !has_separation {
-                                    writeln!(
-                                        w,
-                                        " c{}: x_{}_{}_{} + x_{}_{}_{} <= 1",
-                                        c_idx,
-                                        t1_idx, v1_idx, t1_in,
-                                        t2_idx, v2_idx, t2_in,
-                                    )
-                                    .unwrap();
-                                    c_idx += 1;
-                                    n_constraints += 1;
-                                    n_resource += 1;
-                                }
+                    for &t1_in in &time_disc[t1_idx][v1_idx] {
+                        for &t2_in in &time_disc[t2_idx][v2_idx] {
+                            let t1_out = t1_in + visit.travel_time;
+                            let t2_out = t2_in
+                                + problem.trains[t2_idx].visits[v2_idx]
+                                    .travel_time;
+                            let separation = (t2_in - t1_out).max(t1_in - t2_out);
+                            let has_separation = separation >= 0;
+                            if !has_separation {
+                                writeln!(
+                                    w,
+                                    " c{}: x_{}_{}_{} + x_{}_{}_{} <= 1",
+                                    c_idx,
+                                    t1_idx, v1_idx, t1_in,
+                                    t2_idx, v2_idx, t2_in,
+                                )
+                                .unwrap();
+                                c_idx += 1;
+                                n_constraints += 1;
+                                n_resource += 1;
                             }
                         }
                     }
@@ -738,19 +846,14 @@ fn write_ti_lp(
 
 /// Visit pairs giữa 2 tàu khác nhau dùng conflicting resources.
 fn visit_conflicts(problem: &Problem) -> Vec<((usize, usize), (usize, usize))> {
-    use std::collections::HashSet;
     let mut conflicts = Vec::new();
-    let resource_conflicts: HashSet<(usize, usize)> =
-        problem.conflicts.iter().copied().collect();
     for train_idx1 in 0..problem.trains.len() {
         for train_idx2 in (train_idx1 + 1)..problem.trains.len() {
             for visit_idx1 in 0..problem.trains[train_idx1].visits.len() {
                 for visit_idx2 in 0..problem.trains[train_idx2].visits.len() {
                     let r1 = problem.trains[train_idx1].visits[visit_idx1].resource_id;
                     let r2 = problem.trains[train_idx2].visits[visit_idx2].resource_id;
-                    let is_conflict1 = resource_conflicts.contains(&(r1, r2));
-                    let is_conflict2 = resource_conflicts.contains(&(r2, r1));
-                    if is_conflict1 || is_conflict2 {
+                    if problem.conflicts.contains(r1, r2) {
                         conflicts.push(((train_idx1, visit_idx1), (train_idx2, visit_idx2)));
                     }
                 }
@@ -34,7 +34,8 @@ fn main() {
         );
         eprintln!("\nCost types: finsteps123, finsteps12345, finsteps139,");
         eprintln!("            finsteps1_3min, finsteps1_5min,");
-        eprintln!("            infsteps60, infsteps180, infsteps360, cont");
+        eprintln!("            infsteps60, infsteps180, infsteps360, cont,");
+        eprintln!("            custom:<spec> (e.g. custom:step@0=1;step@180=2;linear@600=1)");
         std::process::exit(1);
     }
 
@@ -46,8 +47,8 @@ fn main() {
         ".".to_string()
     };
 
-    let cost_type = parse_cost_type(cost_str).unwrap_or_else(|| {
-        eprintln!("Unknown cost type: {}", cost_str);
+    let cost_type = parse_cost_type(cost_str).unwrap_or_else(|e| {
+        eprintln!("{}", e);
         std::process::exit(1);
     });
 
@@ -58,7 +59,11 @@ fn main() {
         false,
         None,
         |_| {},
-    );
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
     let problem = &named.problem;
     let n_visits: usize = problem.trains.iter().map(|t| t.visits.len()).sum();
     println!(
@@ -86,19 +91,11 @@ fn main() {
     println!("  -> {}", dat_path);
 }
 
-fn parse_cost_type(s: &str) -> Option<DelayCostType> {
-    match s.to_lowercase().as_str() {
-        "finsteps1_3min" => Some(DelayCostType::FiniteSteps1_3Min),
-        "finsteps1_5min" => Some(DelayCostType::FiniteSteps1_5Min),
-        "finsteps123" => Some(DelayCostType::FiniteSteps123),
-        "finsteps12345" => Some(DelayCostType::FiniteSteps12345),
-        "finsteps139" => Some(DelayCostType::FiniteSteps139),
-        "infsteps60" => Some(DelayCostType::InfiniteSteps60),
-        "infsteps180" => Some(DelayCostType::InfiniteSteps180),
-        "infsteps360" => Some(DelayCostType::InfiniteSteps360),
-        "infsteps123" => Some(DelayCostType::InfiniteSteps180),
-        "cont" => Some(DelayCostType::Continuous),
-        _ => None,
+fn parse_cost_type(s: &str) -> Result<DelayCostType, String> {
+    // txt instances have no per-visit penalties.
+    match DelayCostType::from_name(s)? {
+        DelayCostType::PiecewiseLinear => Err("txt instances have no piecewise penalties".into()),
+        cost_type => Ok(cost_type),
     }
 }
 
@@ -144,8 +141,10 @@ fn write_opl_mod(path: &str, cost_type: DelayCostType) {
     writeln!(w, "range Visits = 1..nVisits;").unwrap();
     writeln!(w, "int travelTime[Visits] = ...;").unwrap();
     writeln!(w, "int earliest[Visits] = ...;").unwrap();
+    writeln!(w, "int latest[Visits] = ...;  // hard deadline on the start time").unwrap();
     writeln!(w, "int aimed[Visits] = ...;  // aimed time (có thể âm), 0 nếu không tính cost").unwrap();
     writeln!(w, "{{int}} VisitsWithAimed = ...;  // set visits có aimed (cost contribution)").unwrap();
+    writeln!(w, "int weight[Visits] = ...;  // priority weight of the visit's train").unwrap();
     writeln!(w).unwrap();
 
     writeln!(w, "// Precedence within train").unwrap();
@@ -179,6 +178,8 @@ fn write_opl_mod(path: &str, cost_type: DelayCostType) {
 
     // Cost variable per visit (Option C - propagation tốt hơn)
     let cost_upper = match cost_type {
+        // Rejected by `parse_cost_type`.
+        DelayCostType::PiecewiseLinear => unreachable!(),
         DelayCostType::Continuous => HORIZON,
         DelayCostType::InfiniteSteps60 => HORIZON / 60 + 1,
         DelayCostType::InfiniteSteps180 => HORIZON / 180 + 1,
@@ -188,6 +189,10 @@ fn write_opl_mod(path: &str, cost_type: DelayCostType) {
         DelayCostType::FiniteSteps123 => 3,
         DelayCostType::FiniteSteps12345 => 5,
         DelayCostType::FiniteSteps139 => 9,
+        DelayCostType::Custom(f) => f
+            .max_cost()
+            .map(|c| c as i32)
+            .unwrap_or_else(|| f.eval(HORIZON) as i32),
     };
     writeln!(w, "// Cost per visit (max cost = {})", cost_upper).unwrap();
     writeln!(
@@ -197,10 +202,22 @@ fn write_opl_mod(path: &str, cost_type: DelayCostType) {
     )
     .unwrap();
     writeln!(w).unwrap();
+    if let DelayCostType::Custom(f) = cost_type {
+        if let Some(repeat) = f.repeat {
+            writeln!(w, "// Started intervals of the repeating staircase").unwrap();
+            writeln!(
+                w,
+                "dvar int rep[i in VisitsWithAimed] in 0..{};",
+                HORIZON / repeat.interval + 1
+            )
+            .unwrap();
+            writeln!(w).unwrap();
+        }
+    }
 
     // ───── Objective (Option C: sum of cost vars - đơn giản, propagate tốt) ─────
-    writeln!(w, "// Objective: minimize sum of cost vars").unwrap();
-    writeln!(w, "minimize sum(i in VisitsWithAimed) cost[i];").unwrap();
+    writeln!(w, "// Objective: minimize weighted sum of cost vars").unwrap();
+    writeln!(w, "minimize sum(i in VisitsWithAimed) weight[i] * cost[i];").unwrap();
     writeln!(w).unwrap();
 
     // ───── Constraints ─────
@@ -214,6 +231,8 @@ fn write_opl_mod(path: &str, cost_type: DelayCostType) {
     // Cost binding constraints (Option C)
     writeln!(w, "  // Cost binding (linear/implication theo cost type)").unwrap();
     match cost_type {
+        // Rejected by `parse_cost_type`.
+        DelayCostType::PiecewiseLinear => unreachable!(),
         DelayCostType::Continuous => {
             // cost[i] >= delay[i]; minimizing → cost = delay
             writeln!(w, "  forall(i in VisitsWithAimed)").unwrap();
@@ -257,9 +276,41 @@ fn write_opl_mod(path: &str, cost_type: DelayCostType) {
                 .unwrap();
             }
         }
+        DelayCostType::Custom(f) => {
+            // cost[i] >= sum of the pieces; minimizing makes it tight.
+            let mut terms: Vec<String> = Vec::new();
+            for thr_idx in 0..f.steps.len() {
+                let (threshold, cost_val) = f.steps[thr_idx];
+                let prev_cost = f.steps.get(thr_idx + 1).map(|x| x.1).unwrap_or(0);
+                terms.push(format!("{} * (delay[i] > {})", cost_val - prev_cost, threshold));
+            }
+            if let Some(repeat) = f.repeat {
+                writeln!(w, "  forall(i in VisitsWithAimed)").unwrap();
+                writeln!(
+                    w,
+                    "    rep[i] * {} >= delay[i] - {};",
+                    repeat.interval, repeat.start
+                )
+                .unwrap();
+                terms.push(format!("{} * rep[i]", repeat.increment));
+            }
+            for segment in f.linear.iter() {
+                terms.push(format!(
+                    "{} * maxl(0, delay[i] - {})",
+                    segment.rate, segment.from
+                ));
+            }
+            writeln!(w, "  forall(i in VisitsWithAimed)").unwrap();
+            writeln!(w, "    cost[i] >= {};", terms.join(" + ")).unwrap();
+        }
     }
     writeln!(w).unwrap();
 
+    writeln!(w, "  // Deadlines").unwrap();
+    writeln!(w, "  forall(i in Visits)").unwrap();
+    writeln!(w, "    startOf(visit[i]) <= latest[i];").unwrap();
+    writeln!(w).unwrap();
+
     writeln!(w, "  // Precedence within train").unwrap();
     writeln!(w, "  forall(<i, j> in PrecArcs)").unwrap();
     writeln!(w, "    endBeforeStart(visit[i], visit[j]);").unwrap();
@@ -337,6 +388,21 @@ fn write_opl_dat(path: &str, problem: &Problem) {
     }
     writeln!(w, "];").unwrap();
 
+    // latest (HORIZON cho visits không có deadline)
+    write!(w, "latest = [").unwrap();
+    for (i, &(ti, vi)) in flat_visits.iter().enumerate() {
+        let l = problem.trains[ti].visits[vi].latest.unwrap_or(HORIZON);
+        if i > 0 {
+            write!(w, ", ").unwrap();
+        }
+        if i % 20 == 0 && i > 0 {
+            writeln!(w).unwrap();
+            write!(w, "  ").unwrap();
+        }
+        write!(w, "{}", l).unwrap();
+    }
+    writeln!(w, "];").unwrap();
+
     // aimed (giá trị thực, kể cả âm; 0 cho visits không có aimed - sẽ không dùng)
     write!(w, "aimed = [").unwrap();
     let mut aimed_visit_indices: Vec<usize> = Vec::new();
@@ -358,6 +424,20 @@ fn write_opl_dat(path: &str, problem: &Problem) {
         write!(w, "{}", a).unwrap();
     }
     writeln!(w, "];").unwrap();
+
+    // weight (priority weight of the train, repeated per visit)
+    write!(w, "weight = [").unwrap();
+    for (i, &(ti, _vi)) in flat_visits.iter().enumerate() {
+        if i > 0 {
+            write!(w, ", ").unwrap();
+        }
+        if i % 20 == 0 && i > 0 {
+            writeln!(w).unwrap();
+            write!(w, "  ").unwrap();
+        }
+        write!(w, "{}", problem.trains[ti].weight).unwrap();
+    }
+    writeln!(w, "];").unwrap();
     writeln!(w).unwrap();
 
     // Set of visit indices có aimed (cost contribution)
@@ -400,15 +480,12 @@ fn write_opl_dat(path: &str, problem: &Problem) {
         }
     }
 
-    let conflict_set: std::collections::HashSet<(usize, usize)> =
-        problem.conflicts.iter().copied().collect();
-
     // Filter: only resources with (r, r) in conflicts (exclusive)
     let mut resource_groups: Vec<Vec<usize>> = Vec::new();
     let mut resource_ids: Vec<&usize> = resource_visits.keys().collect();
     resource_ids.sort();
     for &res_id in &resource_ids {
-        if !conflict_set.contains(&(*res_id, *res_id)) {
+        if !problem.conflicts.contains(*res_id, *res_id) {
             continue;
         }
         let visits = &resource_visits[res_id];
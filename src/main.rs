@@ -2,7 +2,10 @@ use std::{any::Any, cell::RefCell, collections::HashSet, fmt::Write, time::Insta
 
 use ddd::{
     maxsatsolver, parser,
-    problem::{self, DelayCostThresholds, DelayCostType, NamedProblem, Visit},
+    problem::{
+        self, BufferKind, BufferObjective, CostBound, DelayCostThresholds, DelayCostType,
+        NamedProblem, Visit,
+    },
     solvers::{
         ddd::{
             self as ddd_solvers, maxsat_ladder, maxsat_ladder_abstract, maxsat_ladder_sc,
@@ -13,6 +16,7 @@ use ddd::{
             counting_solver,
             greedy::{self, default_heuristic},
             heuristic,
+            scenarios::RobustObjective,
         },
         SolverError,
     },
@@ -49,15 +53,124 @@ struct Opt {
     #[structopt(long)]
     verify_instances: bool,
 
+    /// Delay cost type (e.g. `finsteps123`), `piecewise` for the per-visit
+    /// penalties stored in XML instances, or `custom:<spec>` for a
+    /// user-defined piecewise function (see `DelayCostFunction::parse`).
     #[structopt(long)]
     objective: Option<String>,
 
     #[structopt(long)]
     other_objective: Option<String>,
 
+    /// Minimise `--other-objective` among the schedules that are optimal for
+    /// `--objective` (see `solvers::util::lexicographic`).
+    #[structopt(long)]
+    lexicographic: bool,
+
+    /// Which delays count for XML and txt instances: `earliest`, `arrivals`,
+    /// `departures` or `final` (default).
+    #[structopt(long)]
+    measurement: Option<String>,
+
+    /// Side file choosing the delay measurement per train or station (see
+    /// `parser::read_measurement_policy`). `--measurement` is the default.
+    #[structopt(long)]
+    measurement_policy: Option<String>,
+
+    /// Side file with per-train priority weights, one `<train name> <weight>`
+    /// per line. Trains not listed keep the weight from the instance (default 1).
+    #[structopt(long)]
+    train_weights: Option<String>,
+
+    /// Side file with the trains that may be cancelled, one
+    /// `<train name> <cancellation cost>` per line.
+    #[structopt(long)]
+    cancellation_costs: Option<String>,
+
+    /// Side file with minimum headways between trains on conflicting
+    /// resources (see `parser::read_headways`).
+    #[structopt(long)]
+    headways: Option<String>,
+
+    /// Side file with passenger connections between trains (see
+    /// `parser::read_connections`).
+    #[structopt(long)]
+    connections: Option<String>,
+
+    /// Side file with rolling-stock turnarounds between trains (see
+    /// `parser::read_turnarounds`).
+    #[structopt(long)]
+    turnarounds: Option<String>,
+
+    /// Side file with the order in which trains must pass resources (see
+    /// `parser::read_orders`).
+    #[structopt(long)]
+    orders: Option<String>,
+
+    /// Side file with time windows in which resources are blocked (see
+    /// `parser::read_blocked_intervals`).
+    #[structopt(long)]
+    blocked: Option<String>,
+
+    /// Tighten inconsistent earliest times along each train's route before
+    /// solving (see `Problem::normalize`).
+    #[structopt(long)]
+    repair_instances: bool,
+
     #[structopt(long)]
     json_output: Option<String>,
 
+    /// Solve groups of trains that never use conflicting resources
+    /// separately, and merge the schedules.
+    #[structopt(long)]
+    decompose: bool,
+
+    /// Number of components to solve in parallel (implies `--decompose`).
+    #[structopt(long, default_value = "1")]
+    decompose_threads: usize,
+
+    /// Solve the instance in time windows of this length with each of the
+    /// solvers, committing the schedule window by window (see
+    /// `solvers::util::rolling_horizon`).
+    #[structopt(long)]
+    rolling_horizon: Option<i32>,
+
+    /// Overlap of consecutive `--rolling-horizon` windows.
+    #[structopt(long, default_value = "0")]
+    rolling_horizon_overlap: i32,
+
+    /// Side file with delay scenarios (see `parser::read_scenarios`). One
+    /// train ordering is found for all of them with `maxsat_ladder_sc` (see
+    /// `solvers::util::scenarios`).
+    #[structopt(long)]
+    scenarios: Option<String>,
+
+    /// What to minimise over the `--scenarios`: "expected" or "worst".
+    #[structopt(long, default_value = "expected")]
+    robust_objective: String,
+
+    /// Among the schedules with the optimal delay cost, maximise the
+    /// buffers between occupations of conflicting resources: "min" for the
+    /// smallest buffer, "total" for their sum (see `problem::BufferKind`).
+    #[structopt(long)]
+    buffer_objective: Option<String>,
+
+    /// Buffers count up to this many seconds, in `--buffer-objective` and
+    /// in the reported buffer statistics.
+    #[structopt(long, default_value = "60")]
+    buffer_cap: i32,
+
+    /// Instance files in the JSON format (see `parser::JsonInstance`). The
+    /// objective stored in the first instance that has one is used unless
+    /// `--objective` is given.
+    #[structopt(long)]
+    json_instance: Vec<String>,
+
+    /// Directory to write every solution to, in the JSON format (see
+    /// `parser::JsonSolution`).
+    #[structopt(long)]
+    solution_output: Option<PathBuf>,
+
     /// Default true (lazy).
     #[structopt(long)]
     satddd_use_precedence_graph: Option<bool>,
@@ -110,29 +223,15 @@ struct Opt {
     satddd_objective_encoding: Option<String>,
 }
 
-fn parse_delay_cost_type(value: &str) -> Option<DelayCostType> {
-    let key = value.to_ascii_lowercase();
-    match key.as_str() {
-        "finsteps1_5min" => Some(DelayCostType::FiniteSteps1_5Min),
-        "finsteps1_3min" => Some(DelayCostType::FiniteSteps1_3Min),
-        "finsteps123" => Some(DelayCostType::FiniteSteps123),
-        "finsteps12345" => Some(DelayCostType::FiniteSteps12345),
-        "finsteps139" => Some(DelayCostType::FiniteSteps139),
-        "infsteps60" => Some(DelayCostType::InfiniteSteps60),
-        "infsteps180" => Some(DelayCostType::InfiniteSteps180),
-        "infsteps360" => Some(DelayCostType::InfiniteSteps360),
-        // Alias: "infsteps123" means extending finsteps123 (1/2/3 at 180s steps) without cap.
-        "infsteps123" => Some(DelayCostType::InfiniteSteps180),
-        "cont" => Some(DelayCostType::Continuous),
-        _ => None,
-    }
+fn parse_delay_cost_type(value: &str) -> Result<DelayCostType, String> {
+    DelayCostType::from_name(value)
 }
 
 fn parse_delay_cost_type_or_panic(value: &str) -> DelayCostType {
-    parse_delay_cost_type(value).unwrap_or_else(|| {
+    parse_delay_cost_type(value).unwrap_or_else(|e| {
         panic!(
-            "Unknown objective type '{}'. Supported: finsteps1_5min, finsteps1_3min, finsteps123, finsteps12345, finsteps139, infsteps60, infsteps180, infsteps360, infsteps123, cont",
-            value
+            "{}. Supported: finsteps1_5min, finsteps1_3min, finsteps123, finsteps12345, finsteps139, infsteps60, infsteps180, infsteps360, infsteps123, cont, piecewise, custom:<spec>",
+            e
         )
     })
 }
@@ -182,7 +281,10 @@ fn is_likely_oom_panic(payload: &(dyn Any + Send)) -> bool {
         || message.contains("memory allocation")
 }
 
-pub fn xml_instances(mut x: impl FnMut(String, NamedProblem)) {
+pub fn xml_instances(
+    measurement: &problem::MeasurementPolicy,
+    mut x: impl FnMut(String, NamedProblem),
+) {
     let a_instances = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
 
     // let a_instances = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
@@ -198,15 +300,15 @@ pub fn xml_instances(mut x: impl FnMut(String, NamedProblem)) {
         let filename = format!("instances/Instance{}.xml", instance_id);
         println!("Reading {}", filename);
         #[allow(unused)]
-        let problem = parser::read_xml_file(
-            &filename,
-            problem::DelayMeasurementType::FinalStationArrival,
-        );
+        let problem = parser::read_xml_file(&filename, measurement.clone());
         x(format!("xml {}", instance_id), problem);
     }
 }
 
-pub fn txt_instances(mut x: impl FnMut(String, NamedProblem)) {
+pub fn txt_instances(
+    measurement: &problem::MeasurementPolicy,
+    mut x: impl FnMut(String, NamedProblem),
+) {
     // let a_instances = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
     // let b_instances = [11, 12, 13, 14, 15, 16, 17, 18, 19, 20];
     // #[allow(unused)]
@@ -226,14 +328,19 @@ pub fn txt_instances(mut x: impl FnMut(String, NamedProblem)) {
         for (infrastructure, number) in instances {
             let filename = format!("{}/Instance{}{}.txt", dir, infrastructure, number);
             println!("Reading {}", filename);
-            #[allow(unused)]
-            let (problem, _) = parser::read_txt_file(
+            let problem = match parser::read_txt_file(
                 &filename,
-                problem::DelayMeasurementType::FinalStationArrival,
+                measurement.clone(),
                 false,
                 None,
                 |_| {},
-            );
+            ) {
+                Ok((problem, _)) => problem,
+                Err(e) => {
+                    println!("Skipping {}: {}", filename, e);
+                    continue;
+                }
+            };
             x(
                 format!("{}{}{}", shortname, infrastructure, number),
                 problem,
@@ -259,14 +366,19 @@ pub fn verify_instances(mut x: impl FnMut(String, NamedProblem, Vec<Vec<i32>>) -
         {
             let filename = format!("InstanceResults/{}Sol{}.txt", solvertype, instance_id);
             println!("Reading {}", filename);
-            #[allow(unused)]
-            let (problem, solution) = parser::read_txt_file(
+            let (problem, solution) = match parser::read_txt_file(
                 &filename,
                 problem::DelayMeasurementType::FinalStationArrival,
                 true,
                 None,
                 |_| {},
-            );
+            ) {
+                Ok(result) => result,
+                Err(e) => {
+                    println!("Skipping {}: {}", filename, e);
+                    continue;
+                }
+            };
 
             let new_solution = x(
                 format!("{} {}", solvertype, instance_id),
@@ -328,6 +440,129 @@ enum SolverType {
     MaxSatDddPairwiseCustomRc2NoProp,
 }
 
+impl SolverType {
+    /// The first feature of `problem` that the solver cannot handle, if any.
+    fn unsupported_feature(&self, problem: &problem::Problem) -> Option<&'static str> {
+        let milp_or_sc = matches!(
+            self,
+            SolverType::BigMEager
+                | SolverType::BigMLazy
+                | SolverType::MipHull
+                | SolverType::MaxSatDddLadderSc
+        );
+        let sat_ddd = matches!(
+            self,
+            SolverType::SatDdd
+                | SolverType::SatDddInc
+                | SolverType::SatDddSc
+                | SolverType::SatDddScTotalizer
+                | SolverType::SatDddScInc
+                | SolverType::SatDddScAddClauses
+        );
+        // The ladder MaxSAT solvers and the fresh-solver `puresat` variant.
+        let ladder_or_puresat = matches!(
+            self,
+            SolverType::MaxSatDddLadderRC2
+                | SolverType::MaxSatDddLadderRC2Abstract
+                | SolverType::MaxSatDddLadderIpamir
+                | SolverType::MaxSatDddCadical
+                | SolverType::SatDddScFreshAddClauses
+        );
+        // The greedy heuristics and the legacy solvers never look at the
+        // headways or the deadlines. `MipTi` ends its time grid at the
+        // latest bound, but also separates conflicting visits without
+        // headways.
+        let ignores_latest = matches!(
+            self,
+            SolverType::Greedy
+                | SolverType::GreedyFast
+                | SolverType::GreedyFStr
+                | SolverType::MaxSatTi
+                | SolverType::MipDdd
+                | SolverType::MaxSatDddExternal
+                | SolverType::MaxSatDddIpamir
+                | SolverType::MaxSatDddIncremental
+                | SolverType::MaxSatDddIncrementalNoProp
+                | SolverType::MaxSatDddPairwiseCustomRc2
+                | SolverType::MaxSatDddPairwiseCustomRc2NoProp
+        );
+        if !problem.headways.is_empty() && (ignores_latest || matches!(self, SolverType::MipTi)) {
+            Some("headways")
+        } else if problem
+            .trains
+            .iter()
+            .any(|t| t.visits.iter().any(|v| v.latest.is_some()))
+            && ignores_latest
+        {
+            Some("latest bounds")
+        } else if !problem.capacities.is_empty() && !(milp_or_sc || sat_ddd || ladder_or_puresat) {
+            Some("cumulative resources")
+        } else if !problem.connections.is_empty() && !(milp_or_sc || sat_ddd) {
+            Some("connections")
+        } else if !problem.turnarounds.is_empty() && !(milp_or_sc || sat_ddd) {
+            Some("turnarounds")
+        } else if problem.connections.iter().any(|c| c.penalty.is_some()) && !milp_or_sc {
+            Some("soft connections")
+        } else if problem.trains.iter().any(|t| t.cancellation_cost.is_some())
+            && !matches!(
+                self,
+                SolverType::BigMEager | SolverType::BigMLazy | SolverType::MaxSatDddLadderSc
+            )
+        {
+            Some("cancellable trains")
+        } else if !problem.blocked.is_empty() && !(milp_or_sc || sat_ddd || ladder_or_puresat) {
+            Some("blocked intervals")
+        } else if !problem.orders.is_empty() && !(milp_or_sc || sat_ddd) {
+            Some("fixed orders")
+        } else if problem
+            .trains
+            .iter()
+            .any(|t| t.visits.iter().any(|v| v.max_travel_time.is_some()))
+            && !(milp_or_sc || sat_ddd)
+        {
+            Some("maximum travel times")
+        } else if problem.trains.iter().any(|t| t.running_time_cost.is_some())
+            // The DDD solvers take `max_travel_time` as a hard bound only.
+            && !matches!(
+                self,
+                SolverType::BigMEager | SolverType::BigMLazy | SolverType::MipHull
+            )
+        {
+            Some("running-time costs")
+        } else if problem
+            .trains
+            .iter()
+            .any(|t| t.visits.iter().any(|v| v.no_early_departure))
+            && !(milp_or_sc || sat_ddd)
+        {
+            Some("no-early-departure visits")
+        } else if problem.trains.iter().any(|t| t.earliness_cost.is_some())
+            && !(milp_or_sc || sat_ddd)
+        {
+            Some("earliness costs")
+        } else {
+            None
+        }
+    }
+
+    /// Whether the solver can keep a [`CostBound`] on another cost type, as
+    /// needed for `--lexicographic`.
+    fn supports_cost_bound(&self) -> bool {
+        matches!(
+            self,
+            SolverType::BigMEager
+                | SolverType::BigMLazy
+                | SolverType::MaxSatDddLadderSc
+                | SolverType::SatDdd
+                | SolverType::SatDddInc
+                | SolverType::SatDddSc
+                | SolverType::SatDddScTotalizer
+                | SolverType::SatDddScInc
+                | SolverType::SatDddScAddClauses
+        )
+    }
+}
+
 const TIMEOUT: f64 = 120.0;
 
 fn mk_env() -> grb::Env {
@@ -392,11 +627,18 @@ fn main() {
         .collect::<Vec<_>>();
     println!("Using solvers {:?}", solvers);
 
-    let delay_cost_type = opt
+    let json_instances = opt
+        .json_instance
+        .iter()
+        .map(|f| parser::read_json(f).unwrap_or_else(|e| panic!("{}", e)))
+        .collect::<Vec<_>>();
+
+    let objective_name = opt
         .objective
-        .as_deref()
-        .map(parse_delay_cost_type_or_panic)
-        .unwrap_or(DelayCostType::FiniteSteps123);
+        .clone()
+        .or_else(|| json_instances.iter().find_map(|i| i.objective.clone()))
+        .unwrap_or_else(|| "finsteps123".to_string());
+    let delay_cost_type = parse_delay_cost_type_or_panic(&objective_name);
     println!("Using delay cost type {:?}", delay_cost_type);
 
     let other_delay_cost_type = opt
@@ -404,6 +646,31 @@ fn main() {
         .as_deref()
         .map(parse_delay_cost_type_or_panic);
 
+    let default_measurement = opt
+        .measurement
+        .as_deref()
+        .map(|m| {
+            problem::DelayMeasurementType::from_name(m)
+                .unwrap_or_else(|| panic!("unknown delay measurement '{}'", m))
+        })
+        .unwrap_or(problem::DelayMeasurementType::FinalStationArrival);
+    let measurement_policy = match opt.measurement_policy.as_deref() {
+        Some(f) => parser::read_measurement_policy(f, default_measurement),
+        None => default_measurement.into(),
+    };
+    println!("Using delay measurement {:?}", measurement_policy);
+
+    let lexicographic_secondary = opt
+        .lexicographic
+        .then(|| other_delay_cost_type.expect("--lexicographic needs --other-objective"));
+
+    let buffer_objective = opt.buffer_objective.as_deref().map(|name| BufferObjective {
+        kind: BufferKind::from_name(name)
+            .unwrap_or_else(|| panic!("unknown buffer objective '{}'", name)),
+        cap: opt.buffer_cap,
+    });
+    let buffer_cap = opt.buffer_cap;
+
     // Default config = Option B: precedence + touched-clique AMO + SC AMO,
     // with eager-chain-expansion and full interval-graph clique cover OFF.
     // Empirically best on this benchmark; can be overridden via the
@@ -419,6 +686,8 @@ fn main() {
         use_touched_clique_amo: opt
             .maxsat_ladder_sc_use_touched_clique_amo
             .unwrap_or(true),
+        buffer_objective,
+        primary_bound: None,
         seed_sc_from_earliest: opt
             .maxsat_ladder_sc_seed_from_earliest
             .unwrap_or(false),
@@ -451,6 +720,7 @@ fn main() {
             .satddd_seed_resource_conflicts
             .unwrap_or(false),
         use_sc_amo: opt.satddd_use_sc_amo.unwrap_or(true),
+        primary_bound: None,
     };
     println!("SatDdd settings {:?}", satddd_settings);
 
@@ -491,12 +761,6 @@ fn main() {
     } else {
         None
     };
-    let get_env = || {
-        env.as_ref().expect(
-            "Gurobi environment unavailable; configure a Gurobi license or choose a non-Gurobi solver.",
-        )
-    };
-
     let matches_instance_filter = |name: &str| {
         opt.instance_name_filter
             .as_deref()
@@ -510,13 +774,107 @@ fn main() {
             .unwrap_or(true)
     };
 
+    let train_weights = opt
+        .train_weights
+        .as_deref()
+        .map(parser::read_train_weights);
+    let cancellation_costs = opt
+        .cancellation_costs
+        .as_deref()
+        .map(parser::read_cancellation_costs);
+
+    let headways_file = opt.headways.clone();
+    let connections_file = opt.connections.clone();
+    let turnarounds_file = opt.turnarounds.clone();
+    let orders_file = opt.orders.clone();
+    let blocked_file = opt.blocked.clone();
+    let repair_instances = opt.repair_instances;
+    let solution_output = opt.solution_output.clone();
+    let decompose = opt.decompose;
+    let decompose_threads = opt.decompose_threads;
+    let rolling_horizon = opt.rolling_horizon;
+    let rolling_horizon_overlap = opt.rolling_horizon_overlap;
+    let scenarios_file = opt.scenarios.clone();
+    let robust_objective = RobustObjective::from_name(&opt.robust_objective)
+        .unwrap_or_else(|| panic!("unknown robust objective '{}'", opt.robust_objective));
+    if let Some(window) = rolling_horizon {
+        assert!(
+            0 <= rolling_horizon_overlap && rolling_horizon_overlap < window,
+            "--rolling-horizon-overlap must be shorter than --rolling-horizon"
+        );
+    }
+
     let mut problems: Vec<serde_json::Value> = Default::default();
 
-    let mut solve_it = |name: String, p: NamedProblem| -> Result<Vec<Vec<i32>>, SolverError> {
+    let mut solve_it = |name: String, mut p: NamedProblem| -> Result<Vec<Vec<i32>>, SolverError> {
         if solvers.is_empty() {
             panic!("no solver specified");
         }
 
+        if let Some(weights) = train_weights.as_ref() {
+            let unknown = p.apply_train_weights(weights);
+            if !unknown.is_empty() && unknown.len() == weights.len() {
+                println!("{}: none of the train weights match a train name", name);
+            }
+        }
+
+        if let Some(costs) = cancellation_costs.as_ref() {
+            let unknown = p.apply_cancellation_costs(costs);
+            if !unknown.is_empty() && unknown.len() == costs.len() {
+                println!("{}: none of the cancellation costs match a train name", name);
+            }
+        }
+
+        if let Some(headways_file) = headways_file.as_deref() {
+            p.problem.headways = parser::read_headways(headways_file, &p.resource_names);
+        }
+
+        if let Some(connections_file) = connections_file.as_deref() {
+            p.problem.connections = parser::read_connections(connections_file, &p);
+        }
+
+        if let Some(turnarounds_file) = turnarounds_file.as_deref() {
+            p.problem.turnarounds = parser::read_turnarounds(turnarounds_file, &p);
+        }
+
+        if let Some(orders_file) = orders_file.as_deref() {
+            p.problem.orders = parser::read_orders(orders_file, &p);
+        }
+
+        if let Some(blocked_file) = blocked_file.as_deref() {
+            p.problem.blocked = parser::read_blocked_intervals(blocked_file, &p.resource_names);
+        }
+
+        let scenarios = scenarios_file
+            .as_deref()
+            .map(|f| parser::read_scenarios(f, &p))
+            .transpose();
+        let scenarios = match scenarios {
+            Ok(scenarios) => scenarios,
+            Err(e) => {
+                println!("{}: {}", name, e);
+                return Err(SolverError::NoSolution);
+            }
+        };
+
+        let mut diagnostics = p.validate();
+        if repair_instances
+            && diagnostics
+                .iter()
+                .any(|d| matches!(d.issue, problem::Issue::InconsistentEarliest { .. }))
+        {
+            let n_changed = p.problem.normalize();
+            println!("{}: tightened {} earliest times", name, n_changed);
+            diagnostics = p.validate();
+        }
+        for diagnostic in diagnostics.iter() {
+            println!("{}: {}", name, diagnostic);
+        }
+        if diagnostics.iter().any(|d| !d.issue.is_warning()) {
+            println!("{}: skipping invalid instance", name);
+            return Err(SolverError::NoSolution);
+        }
+
         let problemstats = print_problem_stats(&p.problem);
 
         let mut solves: Vec<serde_json::Value> = Default::default();
@@ -528,7 +886,46 @@ fn main() {
             println!("Starting solver {:?}", solver);
             let mut solve_data = serde_json::Map::new();
 
-            solution =
+            let solve_component = |p: &NamedProblem,
+                                   delay_cost_type: DelayCostType,
+                                   primary_bound: Option<CostBound>,
+                                   env: Option<&grb::Env>,
+                                   solve_data: &mut serde_json::Map<String, serde_json::Value>|
+             -> Result<Vec<Vec<i32>>, SolverError> {
+                if let Some(feature) = solver.unsupported_feature(&p.problem) {
+                    println!("{:?} does not support {}", solver, feature);
+                    return Err(SolverError::NoSolution);
+                }
+                if lexicographic_secondary.is_some() && !solver.supports_cost_bound() {
+                    println!("{:?} does not support lexicographic optimisation", solver);
+                    return Err(SolverError::NoSolution);
+                }
+                let maxsat_ladder_sc_settings = maxsat_ladder_sc::MaxSatDddLadderScSettings {
+                    primary_bound,
+                    ..maxsat_ladder_sc_settings
+                };
+                let satddd_settings = ddd_solvers::incremental_sat::SatDddSettings {
+                    primary_bound,
+                    ..satddd_settings
+                };
+                if let Some(objective) = buffer_objective {
+                    let supported = matches!(
+                        solver,
+                        SolverType::BigMEager | SolverType::BigMLazy | SolverType::MaxSatDddLadderSc
+                    );
+                    if !supported {
+                        println!(
+                            "{:?} does not support the {:?} buffer objective",
+                            solver, objective.kind
+                        );
+                        return Err(SolverError::NoSolution);
+                    }
+                }
+                let get_env = || {
+                    env.expect(
+                        "Gurobi environment unavailable; configure a Gurobi license or choose a non-Gurobi solver.",
+                    )
+                };
                 match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| match solver {
                     SolverType::Cutting => unimplemented!(),
                     // ddd::solvers::cutting::solve_cutting(
@@ -560,7 +957,7 @@ fn main() {
                         None,
                     )
                     .and_then(|e| e.ok_or(SolverError::NoSolution)),
-                    SolverType::BigMEager => bigm::solve_bigm(
+                    SolverType::BigMEager => bigm::solve_bigm_with_objectives(
                         get_env(),
                         &mk_env,
                         &p.problem,
@@ -569,6 +966,8 @@ fn main() {
                         TIMEOUT,
                         &p.train_names,
                         &p.resource_names,
+                        primary_bound,
+                        buffer_objective,
                         |k, v| {
                             solve_data.insert(k, v);
                         },
@@ -586,7 +985,7 @@ fn main() {
                             solve_data.insert(k, v);
                         },
                     ),
-                    SolverType::BigMLazy => bigm::solve_bigm(
+                    SolverType::BigMLazy => bigm::solve_bigm_with_objectives(
                         get_env(),
                         &mk_env,
                         &p.problem,
@@ -595,6 +994,8 @@ fn main() {
                         TIMEOUT,
                         &p.train_names,
                         &p.resource_names,
+                        primary_bound,
+                        buffer_objective,
                         |k, v| {
                             solve_data.insert(k, v);
                         },
@@ -1026,10 +1427,126 @@ fn main() {
                             std::panic::resume_unwind(payload);
                         }
                     }
-                };
+                }
+            };
+            let solve_objectives = |p: &NamedProblem,
+                                    env: Option<&grb::Env>,
+                                    solve_data: &mut serde_json::Map<String, serde_json::Value>|
+             -> Result<Vec<Vec<i32>>, SolverError> {
+                match lexicographic_secondary {
+                    Some(secondary) => ddd::solvers::util::lexicographic::solve_lexicographic(
+                        &p.problem,
+                        delay_cost_type,
+                        secondary,
+                        |c, bound, phase_data| solve_component(p, c, bound, env, phase_data),
+                        |k, v| {
+                            solve_data.insert(k, v);
+                        },
+                    ),
+                    None => solve_component(p, delay_cost_type, None, env, solve_data),
+                }
+            };
+            solution = if let Some(scenarios) = scenarios.as_ref() {
+                // Only the ladder SC solver shares order literals between
+                // the scenario copies. The copies are solved as one
+                // instance, so the options that split or re-solve it do not
+                // apply.
+                let conflicting_option = [
+                    (rolling_horizon.is_some(), "--rolling-horizon"),
+                    (decompose || decompose_threads > 1, "--decompose"),
+                    (lexicographic_secondary.is_some(), "--lexicographic"),
+                    (buffer_objective.is_some(), "--buffer-objective"),
+                ]
+                .into_iter()
+                .find_map(|(is_set, option)| is_set.then_some(option));
+                if !matches!(solver, SolverType::MaxSatDddLadderSc) {
+                    println!("{:?} does not support delay scenarios", solver);
+                    Err(SolverError::NoSolution)
+                } else if let Some(feature) = solver.unsupported_feature(&p.problem) {
+                    println!("{:?} does not support {}", solver, feature);
+                    Err(SolverError::NoSolution)
+                } else if let Some(option) = conflicting_option {
+                    println!("Delay scenarios cannot be combined with {}", option);
+                    Err(SolverError::NoSolution)
+                } else {
+                    ddd::solvers::util::scenarios::solve_robust(
+                        &p.problem,
+                        scenarios,
+                        robust_objective,
+                        delay_cost_type,
+                        |expanded, expansion, phase_data| {
+                            counting_solver::reset_counts();
+                            maxsat_ladder_sc::solve_scenarios(
+                                &mk_env,
+                                counting_solver::CountingSolver::new(
+                                    satcoder::solvers::minisat::Solver::new(),
+                                ),
+                                expanded,
+                                TIMEOUT,
+                                delay_cost_type,
+                                expansion,
+                                |k, v| {
+                                    phase_data.insert(k, v);
+                                },
+                            )
+                            .map(|(v, _)| v)
+                        },
+                        |k, v| {
+                            solve_data.insert(k, v);
+                        },
+                    )
+                }
+            } else if let Some(window) = rolling_horizon {
+                ddd::solvers::util::rolling_horizon::solve_rolling_horizon(
+                    &p,
+                    delay_cost_type,
+                    window,
+                    rolling_horizon_overlap,
+                    |w, window_data| solve_objectives(w, env.as_ref(), window_data),
+                    |k, v| {
+                        solve_data.insert(k, v);
+                    },
+                )
+            } else if decompose_threads > 1 {
+                // Each thread needs its own Gurobi environment.
+                ddd::solvers::util::decomposition::solve_decomposed_parallel(
+                    &p,
+                    delay_cost_type,
+                    decompose_threads,
+                    |c, component_data| {
+                        let env = needs_gurobi.then(mk_env);
+                        solve_objectives(c, env.as_ref(), component_data)
+                    },
+                    |k, v| {
+                        solve_data.insert(k, v);
+                    },
+                )
+            } else if decompose {
+                ddd::solvers::util::decomposition::solve_decomposed(
+                    &p,
+                    delay_cost_type,
+                    |c, component_data| solve_objectives(c, env.as_ref(), component_data),
+                    |k, v| {
+                        solve_data.insert(k, v);
+                    },
+                )
+            } else {
+                solve_objectives(&p, env.as_ref(), &mut solve_data)
+            };
             hprof::end_frame();
             let sol_time = solve_wall_start.elapsed().as_secs_f64() * 1000.0;
             let solver_name = format!("{:?}", solver);
+            // A schedule that breaks a constraint is reported as a failure
+            // instead of aborting the remaining instances.
+            let mut cost = None;
+            if let Ok(s) = solution.as_ref() {
+                cost = p.problem.verify_solution(s, delay_cost_type);
+                if cost.is_none() {
+                    println!("{:?} returned an infeasible schedule", solver);
+                    solve_data.insert("infeasible_solution".to_string(), true.into());
+                    solution = Err(SolverError::NoSolution);
+                }
+            }
             solve_data.insert("solver_name".to_string(), solver_name.clone().into());
             solve_data.insert(
                 "delay_cost_type".to_string(),
@@ -1052,11 +1569,17 @@ fn main() {
             solve_data.insert("sol_time".to_string(), sol_time.into());
 
             let solve_stats = if let Ok(solution) = solution.as_ref() {
-                let cost = p
-                    .problem
-                    .verify_solution(solution, delay_cost_type)
-                    .unwrap();
+                if let Some(dir) = solution_output.as_ref() {
+                    let json = parser::JsonSolution::new(&p, solution, delay_cost_type, &objective_name);
+                    let filename = dir.join(format!("{}_{}.json", name.replace(' ', "_"), solver_name));
+                    if let Err(e) = parser::write_json_solution(&filename.to_string_lossy(), &json) {
+                        println!("Could not write solution: {}", e);
+                    }
+                }
 
+                let cost = cost.unwrap();
+                // Feasibility does not depend on the cost type, so this
+                // cannot fail once the schedule has been verified above.
                 let other_cost =
                     other_delay_cost_type.map(|c| p.problem.verify_solution(solution, c).unwrap());
 
@@ -1064,6 +1587,10 @@ fn main() {
                 if let Some(other_cost) = other_cost {
                     solve_data.insert("other_cost".to_string(), other_cost.into());
                 }
+                solve_data.insert(
+                    "buffers".to_string(),
+                    serde_json::to_value(p.problem.buffer_stats(solution, buffer_cap)).unwrap(),
+                );
 
                 hprof::profiler().print_timing();
                 writeln!(
@@ -1132,19 +1659,25 @@ fn main() {
     };
 
     if opt.xml_instances {
-        xml_instances(|name, p| {
+        xml_instances(&measurement_policy, |name, p| {
             if matches_instance_filter(&name) {
                 let _ = solve_it(name, p);
             }
         });
     }
     if opt.txt_instances {
-        txt_instances(|name, p| {
+        txt_instances(&measurement_policy, |name, p| {
             if matches_instance_filter(&name) {
                 let _ = solve_it(name, p);
             }
         });
     }
+    for instance in json_instances {
+        let name = instance.problem.problem.name.clone();
+        if matches_instance_filter(&name) {
+            let _ = solve_it(name, instance.problem);
+        }
+    }
     if opt.verify_instances {
         verify_instances(|name, p, solution| {
             if !matches_instance_filter(&name) {
@@ -1180,7 +1713,7 @@ fn print_problem_stats(problem: &problem::Problem) -> ProblemStats {
         .map(|t| {
             t.visits
                 .iter()
-                .filter(|v| problem.conflicts.contains(&(v.resource_id, v.resource_id)))
+                .filter(|v| problem.conflicts.contains(v.resource_id, v.resource_id))
                 .count()
         })
         .sum::<usize>() as f32
@@ -1196,7 +1729,7 @@ fn print_problem_stats(problem: &problem::Problem) -> ProblemStats {
                     resource_id: r2, ..
                 } in problem.trains[t2].visits.iter()
                 {
-                    if problem.conflicts.contains(&(*r1, *r2)) {
+                    if problem.conflicts.contains(*r1, *r2) {
                         conflicting_visit_pairs += 1;
                     }
                 }
@@ -1227,14 +1760,1287 @@ fn print_problem_stats(problem: &problem::Problem) -> ProblemStats {
 
 #[cfg(test)]
 mod tests {
-    use ddd::problem::{DelayCostType, NamedProblem};
+    use ddd::problem::{BufferObjective, DelayCostType, NamedProblem, Problem};
+
+    /// Names the trains of a test problem `T0`, `T1`, ... and its eight
+    /// resources `R0` to `R7`.
+    fn named_problem(problem: Problem) -> NamedProblem {
+        NamedProblem {
+            train_names: (0..problem.trains.len()).map(|i| format!("T{}", i)).collect(),
+            resource_names: (0..8).map(|i| format!("R{}", i)).collect(),
+            problem,
+        }
+    }
+
+    /// Solves `problem` with the cost ladder DDD solver and returns the
+    /// schedule together with its verified cost.
+    fn solve_ladder_sc(problem: &Problem, delay_cost_type: DelayCostType) -> (Vec<Vec<i32>>, i32) {
+        let solution = ddd::solvers::ddd::maxsat_ladder_sc::solve(
+            crate::mk_env,
+            satcoder::solvers::minisat::Solver::new(),
+            problem,
+            30.0,
+            delay_cost_type,
+            |_, _| {},
+        )
+        .unwrap()
+        .0;
+        let cost = problem.verify_solution(&solution, delay_cost_type).unwrap();
+        (solution, cost)
+    }
+
+    /// Solves `problem` with the Big-M MILP and returns the schedule.
+    fn solve_bigm(problem: &Problem, delay_cost_type: DelayCostType, lazy: bool) -> Vec<Vec<i32>> {
+        solve_bigm_with_objectives(problem, delay_cost_type, lazy, None)
+    }
+
+    /// Like [`solve_bigm`], with an optional buffer objective after the
+    /// delay cost.
+    fn solve_bigm_with_objectives(
+        problem: &Problem,
+        delay_cost_type: DelayCostType,
+        lazy: bool,
+        buffer_objective: Option<BufferObjective>,
+    ) -> Vec<Vec<i32>> {
+        let mut env = grb::Env::new("").unwrap();
+        env.set(grb::param::OutputFlag, 0).unwrap();
+        let names = |n: usize| (0..n).map(|i| i.to_string()).collect::<Vec<_>>();
+        ddd::solvers::milp::bigm::solve_bigm_with_objectives(
+            &env,
+            crate::mk_env,
+            problem,
+            delay_cost_type,
+            lazy,
+            30.0,
+            &names(problem.trains.len()),
+            &names(8),
+            None,
+            buffer_objective,
+            |_, _| {},
+        )
+        .unwrap()
+    }
 
     #[test]
     fn objective_alias_infsteps123() {
         assert!(matches!(
             super::parse_delay_cost_type("infsteps123"),
-            Some(DelayCostType::InfiniteSteps180)
+            Ok(DelayCostType::InfiniteSteps180)
+        ));
+    }
+
+    #[test]
+    fn objective_custom_matches_builtin() {
+        let custom = super::parse_delay_cost_type("custom:step@0=1;step@180=2;step@360=3").unwrap();
+        let repeating = super::parse_delay_cost_type("custom:repeat@0/180=1").unwrap();
+        let problem = crate::problem::problem1();
+        let train = &problem.trains[0];
+        for t in [-10, 0, 1, 180, 181, 360, 361, 1000] {
+            assert_eq!(
+                train.visit_delay_cost(custom, 0, t),
+                train.visit_delay_cost(DelayCostType::FiniteSteps123, 0, t)
+            );
+            assert_eq!(
+                train.visit_delay_cost(repeating, 0, t),
+                train.visit_delay_cost(DelayCostType::InfiniteSteps180, 0, t)
+            );
+        }
+        assert!(super::parse_delay_cost_type("custom:step@0=2;step@180=1").is_err());
+        assert!(super::parse_delay_cost_type("finsteps7").is_err());
+
+        let toml_fn = std::env::temp_dir().join("ddd_custom_cost.toml");
+        std::fs::write(&toml_fn, "steps = [[0, 1], [180, 2], [360, 3]]\n").unwrap();
+        let from_toml =
+            super::parse_delay_cost_type(&format!("custom:{}", toml_fn.to_str().unwrap()))
+                .unwrap();
+        match (custom, from_toml) {
+            (DelayCostType::Custom(a), DelayCostType::Custom(b)) => assert!(std::ptr::eq(a, b)),
+            _ => panic!("expected custom cost types"),
+        }
+    }
+
+    #[test]
+    fn latest_bounds() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let mut problem = crate::problem::problem1_with_stations();
+        let solution = ddd::solvers::ddd::shared::greedy::greedy_schedule(&problem);
+        assert!(problem.verify_solution(&solution, delay_cost_type).is_some());
+
+        let t = solution[0][1];
+        problem.trains[0].visits[1].latest = Some(t);
+        assert!(problem.verify_solution(&solution, delay_cost_type).is_some());
+        problem.trains[0].visits[1].latest = Some(t - 1);
+        assert!(problem.verify_solution(&solution, delay_cost_type).is_none());
+
+        problem.trains[0].visits[1].latest = Some(problem.trains[0].visits[0].earliest - 1);
+        assert!(!problem.latest_bounds_reachable());
+        let result = ddd::solvers::ddd::maxsat_ladder::solve(
+            &crate::mk_env,
+            satcoder::solvers::minisat::Solver::new(),
+            &problem,
+            30.0,
+            delay_cost_type,
+            |_, _| {},
+        );
+        assert!(matches!(result, Err(ddd::solvers::SolverError::NoSolution)));
+    }
+
+    #[test]
+    fn unsupported_latest_bounds() {
+        use super::SolverType;
+        let mut problem = crate::problem::problem1_with_stations();
+        assert_eq!(SolverType::GreedyFast.unsupported_feature(&problem), None);
+        problem.trains[0].visits[1].latest = Some(100);
+        for solver in [SolverType::Greedy, SolverType::MaxSatTi, SolverType::MipDdd] {
+            assert_eq!(solver.unsupported_feature(&problem), Some("latest bounds"));
+        }
+        for solver in [SolverType::MipTi, SolverType::BigMLazy, SolverType::MaxSatDddLadderSc] {
+            assert_eq!(solver.unsupported_feature(&problem), None);
+        }
+    }
+
+    #[test]
+    fn unsupported_headways() {
+        use super::SolverType;
+        let mut problem = crate::problem::problem1_with_stations();
+        problem.headways.default = crate::problem::Headway {
+            same_direction: 10,
+            opposite_direction: 10,
+        };
+        let ignoring = [
+            SolverType::GreedyFast,
+            SolverType::MipTi,
+            SolverType::MaxSatDddIncremental,
+        ];
+        for solver in ignoring {
+            assert_eq!(solver.unsupported_feature(&problem), Some("headways"));
+        }
+        for solver in [SolverType::BigMEager, SolverType::MipHull, SolverType::SatDddSc] {
+            assert_eq!(solver.unsupported_feature(&problem), None);
+        }
+    }
+
+    #[test]
+    fn unsupported_running_time_costs() {
+        use super::SolverType;
+        let mut problem = crate::problem::problem1_with_stations();
+        problem.trains[0].running_time_cost = Some(10);
+        let ddd = [
+            SolverType::MaxSatDddLadderSc,
+            SolverType::MaxSatDddLadderRC2,
+            SolverType::SatDddSc,
+            SolverType::SatDddScFreshAddClauses,
+        ];
+        for solver in ddd {
+            assert_eq!(solver.unsupported_feature(&problem), Some("running-time costs"));
+        }
+        for solver in [SolverType::BigMEager, SolverType::BigMLazy, SolverType::MipHull] {
+            assert_eq!(solver.unsupported_feature(&problem), None);
+        }
+    }
+
+    #[test]
+    fn validate_and_normalize() {
+        let mut problem = crate::problem::problem1_with_stations();
+        assert!(problem.validate().is_empty());
+
+        problem.trains[0].visits[2].earliest = 0;
+        problem.trains[1].visits[0].aimed = Some(-5);
+        let issues = problem.validate();
+        assert_eq!(issues.len(), 2);
+        assert!(matches!(
+            issues[0],
+            ddd::problem::Issue::InconsistentEarliest {
+                train_idx: 0,
+                visit_idx: 2,
+                earliest: 0,
+                reachable: 6,
+            }
         ));
+        assert!(issues[1].is_warning());
+
+        assert_eq!(problem.normalize(), 1);
+        assert_eq!(problem.trains[0].visits[2].earliest, 6);
+        assert_eq!(problem.validate(), vec![issues[1]]);
+
+        problem.trains[3].visits.clear();
+        assert!(!problem.validate()[1].is_warning());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let mut problem = crate::problem::problem1_with_stations();
+        problem.trains[1].weight = 3;
+        problem.trains[2].visits[2].latest = Some(100);
+        problem.headways.insert_pair(1, 2, crate::problem::Headway {
+            same_direction: 5,
+            opposite_direction: 7,
+        });
+        let named = named_problem(problem);
+
+        let dir = std::env::temp_dir();
+        let instance_fn = dir.join("ddd_json_round_trip.json");
+        let instance_fn = instance_fn.to_str().unwrap();
+        ddd::parser::write_json(instance_fn, &named, Some("finsteps123")).unwrap();
+        let read = ddd::parser::read_json(instance_fn).unwrap();
+        assert_eq!(read.objective.as_deref(), Some("finsteps123"));
+        assert_eq!(read.problem.train_names, named.train_names);
+        assert_eq!(read.problem.problem.trains[1].weight, 3);
+        assert_eq!(read.problem.problem.trains[2].visits[2].latest, Some(100));
+        assert_eq!(read.problem.problem.headways.get(2, 1).opposite_direction, 7);
+        assert_eq!(
+            read.problem.problem.conflicts.iter().collect::<Vec<_>>(),
+            named.problem.conflicts.iter().collect::<Vec<_>>()
+        );
+
+        let solution = ddd::solvers::ddd::shared::greedy::greedy_schedule(&named.problem);
+        let json = ddd::parser::JsonSolution::new(&named, &solution, delay_cost_type, "finsteps123");
+        assert_eq!(
+            Some(json.cost),
+            named.problem.verify_solution(&solution, delay_cost_type)
+        );
+        let solution_fn = dir.join("ddd_json_round_trip_solution.json");
+        let solution_fn = solution_fn.to_str().unwrap();
+        ddd::parser::write_json_solution(solution_fn, &json).unwrap();
+        let read = ddd::parser::read_json_solution(solution_fn).unwrap();
+        assert_eq!(read.times(), solution);
+    }
+
+    #[test]
+    fn train_weights() {
+        let delay_cost_type = DelayCostType::Continuous;
+        // Train 0 needs resource 0 from 0 to 10 and train 1 from 2 to 5.
+        // Letting train 1 go first delays both visits of train 0 by 5,
+        // letting train 0 go first delays both visits of train 1 by 8.
+        let with_weight = |weight| {
+            let visit = |resource_id, earliest, travel_time| ddd::problem::Visit {
+                resource_id,
+                earliest,
+                aimed: Some(earliest),
+                latest: None,
+                travel_time,
+                max_travel_time: None,
+                no_early_departure: false,
+            };
+            let train = |visits, weight| ddd::problem::Train {
+                visits,
+                weight,
+                visit_costs: Vec::new(),
+                cancellation_cost: None,
+                running_time_cost: None,
+                earliness_cost: None,
+            };
+            Problem {
+                name: "weights".to_string(),
+                trains: vec![
+                    train(vec![visit(0, 0, 10), visit(1, 10, 0)], weight),
+                    train(vec![visit(0, 2, 3), visit(1, 5, 0)], 1),
+                ],
+                conflicts: [(0, 0)].into_iter().collect(),
+                headways: Default::default(),
+                capacities: Default::default(),
+                connections: Vec::new(),
+                turnarounds: Vec::new(),
+                blocked: Vec::new(),
+                orders: Vec::new(),
+            }
+        };
+
+        for (weight, train_0_first, cost) in [(1, false, 10), (2, true, 16)] {
+            let problem = with_weight(weight);
+            assert!(problem.validate().is_empty());
+            let bigm_solution = solve_bigm(&problem, delay_cost_type, false);
+            let (ddd_solution, ddd_cost) = solve_ladder_sc(&problem, delay_cost_type);
+            assert_eq!(ddd_cost, cost);
+            assert_eq!(problem.verify_solution(&bigm_solution, delay_cost_type), Some(cost));
+            for solution in [bigm_solution, ddd_solution] {
+                assert_eq!(solution[1][0] >= solution[0][1], train_0_first);
+            }
+        }
+    }
+
+    #[test]
+    fn conflict_graph() {
+        let graph: ddd::problem::ConflictGraph =
+            [(0, 0), (2, 1), (1, 2), (3, 1)].into_iter().collect();
+        assert_eq!(graph.len(), 3);
+        assert!(graph.contains(0, 0) && graph.contains(1, 2) && graph.contains(1, 3));
+        assert!(!graph.contains(1, 1) && !graph.contains(0, 4));
+        assert_eq!(graph.neighbours(1), &[2, 3]);
+        assert_eq!(graph.neighbours(0), &[0]);
+        assert!(graph.neighbours(7).is_empty());
+        assert_eq!(graph.iter().collect::<Vec<_>>(), vec![(0, 0), (1, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn verify_report() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let problem = crate::problem::problem1_with_stations();
+        let mut solution = ddd::solvers::ddd::shared::greedy::greedy_schedule(&problem);
+        let report = problem.verify(&solution, delay_cost_type);
+        assert!(report.is_feasible());
+        assert_eq!(report.train_costs.iter().sum::<i32>(), report.cost);
+        assert_eq!(report.feasible_cost(), problem.verify_solution(&solution, delay_cost_type));
+
+        // Trains 0 and 2 both pass resource 1 as their third visit.
+        solution[2] = solution[0].clone();
+        let report = problem.verify(&solution, delay_cost_type);
+        assert!(report
+            .violations
+            .iter()
+            .any(|v| matches!(v, ddd::problem::Violation::ResourceConflict { .. })));
+
+        solution[0].pop();
+        let report = problem.verify(&solution, delay_cost_type);
+        assert!(report.violations.contains(&ddd::problem::Violation::Shape {
+            train_idx: Some(0),
+            expected: problem.trains[0].visits.len() + 1,
+            found: problem.trains[0].visits.len(),
+        }));
+    }
+
+    #[test]
+    fn decomposition() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let mut problem = crate::problem::problem1_with_stations();
+        assert_eq!(problem.components(), vec![vec![0, 1, 2, 3]]);
+        problem.conflicts = [0, 2, 3, 4, 5, 6].into_iter().map(|r| (r, r)).collect();
+        assert_eq!(problem.components(), vec![vec![0], vec![1], vec![2, 3]]);
+
+        let named = named_problem(problem);
+        let solve = |p: &NamedProblem, stats: &mut serde_json::Map<String, serde_json::Value>| {
+            ddd::solvers::ddd::maxsat_ladder::solve(
+                &crate::mk_env,
+                satcoder::solvers::minisat::Solver::new(),
+                &p.problem,
+                30.0,
+                delay_cost_type,
+                |k, v| {
+                    stats.insert(k, v);
+                },
+            )
+            .map(|(solution, _)| solution)
+        };
+        let cost = named
+            .problem
+            .verify_solution(&solve(&named, &mut Default::default()).unwrap(), delay_cost_type);
+
+        let mut stats = Vec::new();
+        let solution = ddd::solvers::util::decomposition::solve_decomposed(
+            &named,
+            delay_cost_type,
+            solve,
+            |k, v| stats.push((k, v)),
+        )
+        .unwrap();
+        assert_eq!(named.problem.verify_solution(&solution, delay_cost_type), cost);
+        assert_eq!(stats[0].1.as_array().unwrap().len(), 3);
+        assert!(stats[0].1[0]["solver_stats"].is_object());
+    }
+
+    #[test]
+    fn rolling_horizon() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let problem = crate::problem::problem1_with_stations();
+        let named = named_problem(problem);
+        let solve = |p: &NamedProblem, stats: &mut serde_json::Map<String, serde_json::Value>| {
+            ddd::solvers::ddd::maxsat_ladder_sc::solve(
+                crate::mk_env,
+                satcoder::solvers::minisat::Solver::new(),
+                &p.problem,
+                30.0,
+                delay_cost_type,
+                |k, v| {
+                    stats.insert(k, v);
+                },
+            )
+            .map(|(solution, _)| solution)
+        };
+        let optimum = named
+            .problem
+            .verify_solution(&solve(&named, &mut Default::default()).unwrap(), delay_cost_type);
+
+        for (window, overlap, n_windows) in [(1000, 0, 1), (8, 2, 2)] {
+            let mut stats = Vec::new();
+            let solution = ddd::solvers::util::rolling_horizon::solve_rolling_horizon(
+                &named,
+                delay_cost_type,
+                window,
+                overlap,
+                solve,
+                |k, v| stats.push((k, v)),
+            )
+            .unwrap();
+            let cost = named.problem.verify_solution(&solution, delay_cost_type);
+            assert!(cost.is_some() && cost >= optimum);
+            if n_windows == 1 {
+                assert_eq!(cost, optimum);
+            }
+            assert!(stats[0].1.as_array().unwrap().len() >= n_windows);
+            assert!(stats[0].1[0]["solver_stats"].is_object());
+        }
+    }
+
+    #[test]
+    fn headways() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let mut problem = crate::problem::problem1_with_stations();
+        let solve = |problem: &ddd::problem::Problem| {
+            ddd::solvers::ddd::maxsat_ladder::solve(
+                &crate::mk_env,
+                satcoder::solvers::minisat::Solver::new(),
+                problem,
+                30.0,
+                delay_cost_type,
+                |_, _| {},
+            )
+            .unwrap()
+            .0
+        };
+        let cost = problem.verify_solution(&solve(&problem), delay_cost_type).unwrap();
+
+        problem.headways.default = crate::problem::Headway {
+            same_direction: 10,
+            opposite_direction: 20,
+        };
+        let headway_cost = problem.verify_solution(&solve(&problem), delay_cost_type).unwrap();
+        assert!(headway_cost >= cost);
+
+        // No headway is kept between the visits of one train.
+        problem.trains.truncate(1);
+        let resource_id = problem.trains[0].visits[0].resource_id;
+        for visit in problem.trains[0].visits.iter_mut() {
+            visit.resource_id = resource_id;
+        }
+        problem.conflicts.insert(resource_id, resource_id);
+        let visits = &problem.trains[0].visits;
+        let mut times = vec![visits[0].earliest_start()];
+        for (visit_idx, visit) in visits.iter().enumerate() {
+            let earliest = visits.get(visit_idx + 1).map_or(i32::MIN, |v| v.earliest_start());
+            times.push(earliest.max(times[visit_idx] + visit.travel_time));
+        }
+        assert!(problem.verify_solution(&[times], delay_cost_type).is_some());
+    }
+
+    #[test]
+    fn measurement_policy() {
+        use ddd::problem::{DelayMeasurementType, MeasurementPolicy};
+        let read = |policy: MeasurementPolicy| {
+            crate::parser::read_txt_file(
+                "instances/original/InstanceA1.txt",
+                policy,
+                false,
+                None,
+                |_| {},
+            )
+            .unwrap()
+            .0
+        };
+
+        let earliest = read(DelayMeasurementType::EverywhereEarliest.into());
+        for train in earliest.problem.trains.iter() {
+            for visit in train.visits[..train.visits.len() - 1].iter() {
+                assert_eq!(visit.aimed, Some(visit.earliest));
+            }
+        }
+
+        let mut policy = MeasurementPolicy::from(DelayMeasurementType::FinalStationArrival);
+        policy
+            .trains
+            .insert("Train12".to_string(), DelayMeasurementType::AllStationDepartures);
+        policy
+            .stations
+            .insert("T29_S26_to_S27".to_string(), DelayMeasurementType::AllStationArrivals);
+        let mixed = read(policy);
+        let mut n_station_rules = 0;
+        for (train, name) in mixed.problem.trains.iter().zip(mixed.train_names.iter()) {
+            for (visit_idx, visit) in train.visits.iter().enumerate() {
+                let is_track = visit_idx % 2 == 1;
+                if name == "Train12" {
+                    assert_eq!(visit.aimed.is_some(), is_track);
+                } else if is_track && mixed.resource_names[visit.resource_id] == "T29_S26_to_S27" {
+                    assert!(train.visits[visit_idx - 1].aimed.is_some());
+                    n_station_rules += 1;
+                }
+            }
+        }
+        assert!(n_station_rules > 0);
+    }
+
+    #[test]
+    fn txt_parse_errors() {
+        use ddd::parser::{read_txt_file, ParseErrorKind};
+        use ddd::problem::DelayMeasurementType;
+        let dir = std::env::temp_dir();
+        let read = |name: &str, txt: &str| {
+            let instance_fn = dir.join(name);
+            let instance_fn = instance_fn.to_str().unwrap().to_string();
+            std::fs::write(&instance_fn, txt).unwrap();
+            let result = read_txt_file(
+                &instance_fn,
+                DelayMeasurementType::FinalStationArrival,
+                false,
+                None,
+                |_| {},
+            );
+            (instance_fn, result)
+        };
+
+        let plain = "TrainId=1 Delay=0 FreeRun=0\n\
+            T1 Train1 AimedDepartureTime=100 WaitTime=0 BaseTime=0 RunTime=50\n\
+            T2 Train1 AimedDepartureTime=160 WaitTime=10 BaseTime=60 RunTime=40\n";
+        let commented = "# a single train\n\
+            FreeRun=0 TrainId=1 Delay=0\n\
+            T1 Train1 RunTime=50 BaseTime=0 WaitTime=0 AimedDepartureTime=100 // first\n\
+            # between the tracks\n\
+            T2 Train1 WaitTime=10 AimedDepartureTime=160 RunTime=40 BaseTime=60\n";
+        let (_, plain) = read("ddd_txt_plain.txt", plain);
+        let (_, commented) = read("ddd_txt_commented.txt", commented);
+        let (plain, commented) = (plain.unwrap().0, commented.unwrap().0);
+        assert_eq!(commented.train_names, plain.train_names);
+        assert_eq!(commented.resource_names, plain.resource_names);
+        assert_eq!(
+            serde_json::to_value(&commented.problem.trains).unwrap(),
+            serde_json::to_value(&plain.problem.trains).unwrap()
+        );
+
+        let malformed = "TrainId=1 Delay=0 FreeRun=0\n\
+            T1 Train1 AimedDepartureTime=100 WaitTime=0 BaseTime=0 RunTime=50\n\
+            T2 Train1 AimedDepartureTime=1x0 WaitTime=10 BaseTime=60 RunTime=40\n";
+        let (file, result) = read("ddd_txt_malformed.txt", malformed);
+        let error = result.unwrap_err();
+        assert_eq!((error.file.as_str(), error.line, error.column), (file.as_str(), 3, 11));
+        assert_eq!(error.expected, "AimedDepartureTime");
+        assert_eq!(error.kind, ParseErrorKind::InvalidValue("1x0".to_string()));
+
+        let missing = "TrainId=1 Delay=0 FreeRun=0\n\
+            T1 Train1 AimedDepartureTime=100 BaseTime=0 RunTime=50\n";
+        let error = read("ddd_txt_missing.txt", missing).1.unwrap_err();
+        assert_eq!((error.line, error.expected.as_str()), (2, "WaitTime"));
+        assert_eq!(error.kind, ParseErrorKind::Missing);
+    }
+
+    #[test]
+    fn piecewise_linear_objective() {
+        use ddd::problem::{PiecewiseLinearCost, PiecewiseSegment};
+        let segment = |from, to, from_value, slope| PiecewiseSegment {
+            from,
+            to,
+            from_value,
+            slope,
+        };
+        let function = PiecewiseLinearCost::new(
+            100,
+            vec![segment(10, 20, 5.0, 0.25), segment(0, 5, 1.0, 0.5)],
+        )
+        .unwrap();
+        // The gap between the two segments is filled with a flat segment.
+        assert_eq!(function.segments.len(), 3);
+        assert_eq!(function.eval(99), 0);
+        assert_eq!(function.eval(100), 1);
+        assert_eq!(function.eval(103), 2);
+        assert_eq!(function.eval(107), 3);
+        assert_eq!(function.eval(130), 10);
+        // Decreasing penalties are rejected.
+        assert!(PiecewiseLinearCost::new(
+            0,
+            vec![segment(0, 10, 5.0, 0.0), segment(10, 20, 1.0, 0.0)]
+        )
+        .is_err());
+
+        let mut problem = crate::problem::problem1_with_stations();
+        for train in problem.trains.iter_mut() {
+            let n_visits = train.visits.len();
+            let last = train.visits[n_visits - 1];
+            train.visit_costs = vec![None; n_visits];
+            train.visit_costs[n_visits - 1] = Some(
+                PiecewiseLinearCost::new(
+                    last.earliest,
+                    vec![segment(0, 3, 1.0, 1.0), segment(3, 100, 10.0, 2.0)],
+                )
+                .unwrap(),
+            );
+        }
+        let delay_cost_type = DelayCostType::PiecewiseLinear;
+
+        let (_, ddd_cost) = solve_ladder_sc(&problem, delay_cost_type);
+        let bigm_solution = solve_bigm(&problem, delay_cost_type, false);
+        let bigm_cost = problem.verify_solution(&bigm_solution, delay_cost_type).unwrap();
+        assert_eq!(ddd_cost, bigm_cost);
+        assert!(ddd_cost >= problem.trains.len() as i32);
+    }
+
+    #[test]
+    fn lexicographic() {
+        let primary = DelayCostType::FiniteSteps123;
+        let secondary = DelayCostType::Continuous;
+        let problem = crate::problem::problem1_with_stations();
+        let ladder = |delay_cost_type, primary_bound| {
+            ddd::solvers::ddd::maxsat_ladder_sc::solve_with_settings(
+                crate::mk_env,
+                satcoder::solvers::minisat::Solver::new(),
+                &problem,
+                30.0,
+                delay_cost_type,
+                ddd::solvers::ddd::maxsat_ladder_sc::MaxSatDddLadderScSettings {
+                    primary_bound,
+                    ..Default::default()
+                },
+                |_, _| {},
+            )
+            .map(|(solution, _)| solution)
+        };
+        let sat = |delay_cost_type, primary_bound| {
+            ddd::solvers::ddd::incremental_sat::solve_sc_with_encoding_and_settings(
+                crate::mk_env,
+                satcoder::solvers::minisat::Solver::new(),
+                &problem,
+                30.0,
+                delay_cost_type,
+                ddd::solvers::ddd::incremental_sat::SatObjectiveEncoding::Scpb,
+                ddd::solvers::ddd::incremental_sat::SatDddSettings {
+                    primary_bound,
+                    ..Default::default()
+                },
+                |_, _| {},
+            )
+            .map(|(solution, _)| solution)
+        };
+        let primary_only = ladder(primary, None).unwrap();
+        let primary_cost = problem.verify_solution(&primary_only, primary);
+        let secondary_only = ladder(secondary, None).unwrap();
+
+        type Solve<'a> = &'a dyn Fn(
+            DelayCostType,
+            Option<ddd::problem::CostBound>,
+        ) -> Result<Vec<Vec<i32>>, ddd::solvers::SolverError>;
+        for solve in [&ladder as Solve, &sat] {
+            let mut stats = serde_json::Map::new();
+            let solution = ddd::solvers::util::lexicographic::solve_lexicographic(
+                &problem,
+                primary,
+                secondary,
+                |c, bound, _| solve(c, bound),
+                |k, v| {
+                    stats.insert(k, v);
+                },
+            )
+            .unwrap();
+            assert_eq!(problem.verify_solution(&solution, primary), primary_cost);
+            assert!(problem.cost(&solution, secondary) <= problem.cost(&primary_only, secondary));
+            assert!(problem.cost(&solution, secondary) >= problem.cost(&secondary_only, secondary));
+            assert_eq!(stats["primary_cost"], primary_cost.unwrap());
+            assert_eq!(stats["secondary_cost"], problem.cost(&solution, secondary));
+        }
+
+        // A bound below the optimum leaves no schedule.
+        let bound = ddd::problem::CostBound {
+            cost_type: primary,
+            cost: primary_cost.unwrap() - 1,
+        };
+        if bound.cost >= 0 {
+            assert!(ladder(secondary, Some(bound)).is_err());
+        }
+    }
+
+    #[test]
+    fn cumulative_resources() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let base = crate::problem::problem1_with_stations();
+        // Resource 1 becomes cumulative instead of conflicting with itself.
+        let with_capacity = |capacity| {
+            let mut problem = base.clone();
+            problem.conflicts = (0..=6).filter(|r| *r != 1).map(|r| (r, r)).collect();
+            problem.capacities.insert(1, capacity);
+            problem
+        };
+        let solve = |problem: &Problem| solve_ladder_sc(problem, delay_cost_type).1;
+
+        let single = with_capacity(1);
+        assert!(single.validate().is_empty());
+        let greedy = ddd::solvers::ddd::shared::greedy::greedy_schedule(&single);
+        assert!(single
+            .verify(&greedy, delay_cost_type)
+            .violations
+            .iter()
+            .any(|v| matches!(v, ddd::problem::Violation::CapacityExceeded { resource_id: 1, .. })));
+        assert_eq!(solve(&single), solve(&base));
+
+        let double = with_capacity(2);
+        let ddd_cost = solve(&double);
+        assert!(ddd_cost <= solve(&single));
+
+        for lazy in [false, true] {
+            let bigm_solution = solve_bigm(&double, delay_cost_type, lazy);
+            assert_eq!(
+                double.verify_solution(&bigm_solution, delay_cost_type),
+                Some(ddd_cost)
+            );
+        }
+
+        let ladder_solution = ddd::solvers::ddd::maxsat_ladder::solve(
+            &crate::mk_env,
+            satcoder::solvers::minisat::Solver::new(),
+            &double,
+            30.0,
+            delay_cost_type,
+            |_, _| {},
+        )
+        .unwrap()
+        .0;
+        assert_eq!(
+            double.verify_solution(&ladder_solution, delay_cost_type),
+            Some(ddd_cost)
+        );
+
+        assert!(!with_capacity(0).validate().is_empty());
+    }
+
+    #[test]
+    fn connections() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let base = crate::problem::problem1_with_stations();
+        // Train 1 leaves the station 2 after train 0 has arrived there.
+        let with_connection = |penalty| {
+            let mut problem = base.clone();
+            problem.connections.push(ddd::problem::Connection {
+                from: (0, 1),
+                to: (1, 2),
+                min_transfer: 2,
+                penalty,
+            });
+            problem
+        };
+        let solve = |problem: &Problem| solve_ladder_sc(problem, delay_cost_type).1;
+
+        let greedy = ddd::solvers::ddd::shared::greedy::greedy_schedule(&base);
+        let greedy_cost = base.verify_solution(&greedy, delay_cost_type).unwrap();
+        let hard = with_connection(None);
+        assert!(hard.validate().is_empty());
+        assert!(matches!(
+            hard.verify(&greedy, delay_cost_type).violations[..],
+            [ddd::problem::Violation::BrokenConnection { connection_idx: 0, .. }]
+        ));
+        let soft = with_connection(Some(1));
+        let report = soft.verify(&greedy, delay_cost_type);
+        assert!(report.is_feasible());
+        assert_eq!(report.broken_connections, vec![0]);
+        assert_eq!(report.cost, greedy_cost + 1);
+
+        let base_cost = solve(&base);
+        let hard_cost = solve(&hard);
+        assert!(hard_cost >= base_cost);
+        assert_eq!(solve(&with_connection(Some(1000))), hard_cost);
+        let soft_cost = solve(&soft);
+        assert!(soft_cost <= hard_cost.min(base_cost + 1));
+
+        for (problem, cost) in [(&hard, hard_cost), (&soft, soft_cost)] {
+            let bigm_solution = solve_bigm(problem, delay_cost_type, false);
+            assert_eq!(problem.verify_solution(&bigm_solution, delay_cost_type), Some(cost));
+        }
+
+        // Connected trains are solved together.
+        assert!(hard.components().iter().any(|c| c.contains(&0) && c.contains(&1)));
+    }
+
+    #[test]
+    fn turnarounds() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let mut problem = crate::problem::problem1_with_stations();
+        // The trainset of train 1 (which ends at 7 at the earliest) runs
+        // train 3 next.
+        problem.turnarounds.push(ddd::problem::Turnaround {
+            from: 1,
+            to: 3,
+            time: 5,
+        });
+        assert!(problem.validate().is_empty());
+        let earliest = ddd::solvers::ddd::shared::precedence::chain_earliest(&problem);
+        assert_eq!(earliest[3], vec![12, 22, 22]);
+
+        let greedy = ddd::solvers::ddd::shared::greedy::greedy_schedule(&problem);
+        assert!(problem
+            .verify(&greedy, delay_cost_type)
+            .violations
+            .iter()
+            .any(|v| matches!(v, ddd::problem::Violation::Turnaround { turnaround_idx: 0, .. })));
+
+        let (solution, ddd_cost) = solve_ladder_sc(&problem, delay_cost_type);
+        assert!(solution[3][0] >= solution[1].last().unwrap() + 5);
+
+        let bigm_solution = solve_bigm(&problem, delay_cost_type, true);
+        assert_eq!(
+            problem.verify_solution(&bigm_solution, delay_cost_type),
+            Some(ddd_cost)
+        );
+    }
+
+    #[test]
+    fn fixed_orders() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let base = crate::problem::problem1_with_stations();
+        let solve = |problem: &Problem| solve_ladder_sc(problem, delay_cost_type).0;
+        let base_cost = base.verify_solution(&solve(&base), delay_cost_type).unwrap();
+
+        // Trains 0 and 2 both pass resource 1 as their third visit, train 1
+        // never visits resource 6.
+        let mut problem = base.clone();
+        problem.orders.push(ddd::problem::TrainOrder {
+            resource_id: 6,
+            trains: vec![0, 1],
+        });
+        assert!(matches!(
+            problem.validate()[..],
+            [ddd::problem::Issue::InvalidOrder { order_idx: 0 }]
+        ));
+        let ordered = |trains: Vec<usize>| {
+            let mut problem = base.clone();
+            problem.orders.push(ddd::problem::TrainOrder {
+                resource_id: 1,
+                trains,
+            });
+            assert!(problem.validate().is_empty());
+            problem
+        };
+        let (first_0, first_2) = (ordered(vec![0, 2]), ordered(vec![2, 0]));
+        let solutions = [solve(&first_0), solve(&first_2)];
+        for (problem, solution) in [&first_0, &first_2].into_iter().zip(solutions.iter()) {
+            let cost = problem.verify_solution(solution, delay_cost_type).unwrap();
+            assert!(cost >= base_cost);
+        }
+        assert!(solutions[0][2][2] >= solutions[0][0][3]);
+        assert!(solutions[1][0][2] >= solutions[1][2][3]);
+        assert!(first_2
+            .verify(&solutions[0], delay_cost_type)
+            .violations
+            .iter()
+            .any(|v| matches!(v, ddd::problem::Violation::Order { order_idx: 0, .. })));
+    }
+
+    #[test]
+    fn max_travel_time() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let with_supplement = |supplement: i32, running_time_cost: Option<i32>| {
+            let mut problem = crate::problem::problem1_with_stations();
+            for train in problem.trains.iter_mut() {
+                train.running_time_cost = running_time_cost;
+                for visit in train.visits.iter_mut() {
+                    visit.max_travel_time = Some(visit.travel_time + supplement);
+                }
+            }
+            assert!(problem.validate().is_empty());
+            problem
+        };
+
+        // Without supplements the trains can only wait before they start.
+        let no_waiting = with_supplement(0, None);
+        let (ddd_solution, ddd_cost) = solve_ladder_sc(&no_waiting, delay_cost_type);
+        let bigm_solution = solve_bigm(&no_waiting, delay_cost_type, false);
+        let bigm_cost = no_waiting.verify_solution(&bigm_solution, delay_cost_type).unwrap();
+        assert_eq!(ddd_cost, bigm_cost);
+        for (train, times) in no_waiting.trains.iter().zip(ddd_solution.iter()) {
+            for (visit_idx, visit) in train.visits.iter().enumerate() {
+                assert_eq!(times[visit_idx + 1] - times[visit_idx], visit.travel_time);
+            }
+        }
+
+        let mut stretched = ddd_solution.clone();
+        for t in stretched[0][1..].iter_mut() {
+            *t += 1000;
+        }
+        assert!(no_waiting
+            .verify(&stretched, delay_cost_type)
+            .violations
+            .iter()
+            .any(|v| matches!(
+                v,
+                ddd::problem::Violation::MaxTravelTime { train_idx: 0, visit_idx: 0, .. }
+            )));
+
+        // The schedule without waiting has no supplements, so it bounds the
+        // cost when supplements are allowed but expensive.
+        let costly = with_supplement(5, Some(100));
+        let costly_solution = solve_bigm(&costly, delay_cost_type, false);
+        let costly_cost = costly.verify_solution(&costly_solution, delay_cost_type).unwrap();
+        assert!(costly_cost <= ddd_cost);
+        assert_eq!(costly.cost(&ddd_solution, delay_cost_type), ddd_cost);
+
+        let mut negative = costly.clone();
+        negative.trains[0].running_time_cost = Some(-1);
+        assert!(matches!(
+            negative.validate()[..],
+            [ddd::problem::Issue::NegativeRunningTimeCost { train_idx: 0, cost: -1 }]
+        ));
+    }
+
+    #[test]
+    fn early_departure() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let base = crate::problem::problem1_with_stations();
+        let solve = |problem: &Problem| {
+            let (ddd_solution, ddd_cost) = solve_ladder_sc(problem, delay_cost_type);
+            let bigm_solution = solve_bigm(problem, delay_cost_type, false);
+            let bigm_cost = problem.verify_solution(&bigm_solution, delay_cost_type).unwrap();
+            assert_eq!(ddd_cost, bigm_cost);
+            (ddd_solution, ddd_cost)
+        };
+        let (base_solution, _) = solve(&base);
+
+        // Train 1 may not leave its origin before 20.
+        let mut no_early = base.clone();
+        no_early.trains[1].visits[0].aimed = Some(20);
+        no_early.trains[1].visits[0].no_early_departure = true;
+        assert!(no_early.validate().is_empty());
+        let (solution, _) = solve(&no_early);
+        assert!(solution[1][0] >= 20);
+        let mut early = solution.clone();
+        early[1][0] = 19;
+        assert!(no_early
+            .verify(&early, delay_cost_type)
+            .violations
+            .iter()
+            .any(|v| matches!(
+                v,
+                ddd::problem::Violation::EarlyDeparture { train_idx: 1, visit_idx: 0, .. }
+            )));
+
+        // Without the flag, entering 5 before `earliest` is tolerated.
+        let mut tolerated = no_early.clone();
+        tolerated.trains[1].visits[0].no_early_departure = false;
+        tolerated.trains[1].visits[0].earliest = solution[1][0] + 5;
+        assert!(tolerated.verify(&solution, delay_cost_type).violations.is_empty());
+
+        // Train 3 pays for each time unit it leaves its origin before 5.
+        let mut early_cost = base.clone();
+        early_cost.trains[3].visits[0].aimed = Some(5);
+        early_cost.trains[3].earliness_cost = Some(1);
+        assert_eq!(
+            early_cost.trains[3].visit_earliness_cost(0, 2),
+            3 * early_cost.trains[3].weight
+        );
+        assert_eq!(early_cost.trains[3].visit_delay_cost(delay_cost_type, 0, 2), 0);
+        let (_, cost) = solve(&early_cost);
+        assert!(cost <= early_cost.cost(&base_solution, delay_cost_type));
+        let sat_solution = ddd::solvers::ddd::incremental_sat::solve_sc_with_encoding_and_settings(
+            crate::mk_env,
+            satcoder::solvers::minisat::Solver::new(),
+            &early_cost,
+            30.0,
+            delay_cost_type,
+            ddd::solvers::ddd::incremental_sat::SatObjectiveEncoding::Scpb,
+            Default::default(),
+            |_, _| {},
+        )
+        .unwrap()
+        .0;
+        assert_eq!(early_cost.verify_solution(&sat_solution, delay_cost_type), Some(cost));
+
+        early_cost.trains[3].earliness_cost = Some(-1);
+        assert!(matches!(
+            early_cost.validate()[..],
+            [ddd::problem::Issue::NegativeEarlinessCost { train_idx: 3, cost: -1 }]
+        ));
+    }
+
+    #[test]
+    fn robust_scenarios() {
+        use ddd::problem::DelayScenario;
+        use ddd::solvers::util::scenarios::{self, RobustObjective};
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let problem = crate::problem::problem1_with_stations();
+        let scenarios = [
+            DelayScenario {
+                name: "on time".to_string(),
+                weight: 3,
+                delays: Vec::new(),
+            },
+            DelayScenario {
+                name: "train 1 late".to_string(),
+                weight: 1,
+                delays: vec![(1, 0, 10)],
+            },
+        ];
+        let expanded = scenarios::expand_scenarios(&problem, &scenarios, RobustObjective::Expected);
+        assert_eq!(expanded.trains.len(), 2 * problem.trains.len());
+        assert_eq!(expanded.trains[5].visits[0].earliest, 10);
+        assert_eq!(expanded.trains[5].weight, 1);
+        assert_eq!(expanded.trains[0].weight, 3);
+
+        let named = named_problem(problem.clone());
+        let read = |txt: &str| {
+            let scenarios_fn = std::env::temp_dir().join("ddd_scenarios.txt");
+            let scenarios_fn = scenarios_fn.to_str().unwrap().to_string();
+            std::fs::write(&scenarios_fn, txt).unwrap();
+            crate::parser::read_scenarios(&scenarios_fn, &named)
+        };
+        let read_back = read("# weight 3\non_time 3\ntrain_1_late 1 T1 0 10 T9 0 5\n").unwrap();
+        assert_eq!(read_back.iter().map(|s| s.weight).collect::<Vec<_>>(), vec![3, 1]);
+        assert_eq!(read_back[1].delays, scenarios[1].delays);
+        let n_visits = problem.trains[1].visits.len();
+        let error = read(&format!("late 1 T1 {} 10\n", n_visits)).unwrap_err();
+        assert_eq!((error.line, error.column, error.expected.as_str()), (1, 11, "visit index"));
+        assert!(read("late 1 T1 0\n").is_err());
+
+        let solve = |objective| {
+            let mut stats = serde_json::Map::new();
+            let solution = scenarios::solve_robust(
+                &problem,
+                &scenarios,
+                objective,
+                delay_cost_type,
+                |expanded, expansion, _| {
+                    ddd::solvers::ddd::maxsat_ladder_sc::solve_scenarios(
+                        crate::mk_env,
+                        satcoder::solvers::minisat::Solver::new(),
+                        expanded,
+                        30.0,
+                        delay_cost_type,
+                        expansion,
+                        |_, _| {},
+                    )
+                    .map(|(solution, _)| solution)
+                },
+                |k, v| {
+                    stats.insert(k, v);
+                },
+            )
+            .unwrap();
+            assert!(problem.verify_solution(&solution, delay_cost_type).is_some());
+            (stats["expected_cost"].as_f64().unwrap(), stats["worst_case_cost"].as_i64().unwrap())
+        };
+        let (expected, worst_of_expected) = solve(RobustObjective::Expected);
+        let (expected_of_worst, worst) = solve(RobustObjective::WorstCase);
+        assert!(expected <= expected_of_worst);
+        assert!(worst <= worst_of_expected);
+    }
+
+    #[test]
+    fn buffers() {
+        use crate::problem::BufferKind;
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let problem = crate::problem::problem1_with_stations();
+        let cap = 60;
+        let buffer = |solution: &[Vec<i32>], kind| {
+            problem.buffer_stats(solution, cap).value(kind)
+        };
+
+        let solve = |buffer_objective| {
+            let settings = ddd::solvers::ddd::maxsat_ladder_sc::MaxSatDddLadderScSettings {
+                buffer_objective,
+                ..Default::default()
+            };
+            ddd::solvers::ddd::maxsat_ladder_sc::solve_with_settings(
+                crate::mk_env,
+                satcoder::solvers::minisat::Solver::new(),
+                &problem,
+                30.0,
+                delay_cost_type,
+                settings,
+                |_, _| {},
+            )
+            .unwrap()
+            .0
+        };
+        let plain = solve(None);
+        let cost = problem.verify_solution(&plain, delay_cost_type).unwrap();
+
+        // A conflict given in both orders counts once.
+        let mut symmetric = problem.clone();
+        symmetric.conflicts =
+            problem.conflicts.iter().flat_map(|(a, b)| [(a, b), (b, a)]).collect();
+        assert_eq!(symmetric.buffer_stats(&plain, cap), problem.buffer_stats(&plain, cap));
+
+        for kind in [BufferKind::Min, BufferKind::Total] {
+            let buffered = solve(Some(BufferObjective { kind, cap }));
+            assert_eq!(problem.verify_solution(&buffered, delay_cost_type), Some(cost));
+            assert!(buffer(&buffered, kind) >= buffer(&plain, kind));
+        }
+
+        for kind in [BufferKind::Min, BufferKind::Total] {
+            let buffer_objective = Some(BufferObjective { kind, cap });
+            let bigm_solution =
+                solve_bigm_with_objectives(&problem, delay_cost_type, true, buffer_objective);
+            assert_eq!(problem.verify_solution(&bigm_solution, delay_cost_type), Some(cost));
+            assert!(buffer(&bigm_solution, kind) >= buffer(&plain, kind));
+        }
+    }
+
+    #[test]
+    fn cancellation() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let base = crate::problem::problem1_with_stations();
+        let with_cancellation_cost = |cost: i32| {
+            let mut problem = base.clone();
+            for train in problem.trains.iter_mut() {
+                train.cancellation_cost = Some(cost);
+            }
+            problem
+        };
+        let solve = |problem: &Problem| solve_ladder_sc(problem, delay_cost_type).1;
+
+        // A train without times is only accepted if it may be cancelled.
+        let greedy = ddd::solvers::ddd::shared::greedy::greedy_schedule(&base);
+        let greedy_cost = base.verify_solution(&greedy, delay_cost_type).unwrap();
+        let mut without_first = greedy.clone();
+        without_first[0].clear();
+        assert!(matches!(
+            base.verify(&without_first, delay_cost_type).violations[..],
+            [ddd::problem::Violation::Shape { train_idx: Some(0), .. }]
+        ));
+        let cheap = with_cancellation_cost(1);
+        assert!(cheap.validate().is_empty());
+        let report = cheap.verify(&without_first, delay_cost_type);
+        assert!(report.is_feasible());
+        assert_eq!(report.cancelled_trains, vec![0]);
+        assert_eq!(
+            report.cost,
+            greedy_cost - base.train_cost(&greedy, delay_cost_type, 0) + 1
+        );
+
+        // A trainset cannot be cancelled for one of its services only.
+        let mut with_turnaround = cheap.clone();
+        with_turnaround.turnarounds.push(ddd::problem::Turnaround {
+            from: 1,
+            to: 3,
+            time: 5,
+        });
+        assert!(matches!(
+            with_turnaround.validate()[..],
+            [ddd::problem::Issue::InvalidTurnaround { turnaround_idx: 0 }]
+        ));
+
+        let base_cost = solve(&base);
+        assert_eq!(solve(&with_cancellation_cost(0)), 0);
+        assert_eq!(solve(&with_cancellation_cost(1_000_000)), base_cost);
+        let cheap_cost = solve(&cheap);
+        assert!(cheap_cost <= base_cost.min(base.trains.len() as i32));
+
+        for lazy in [false, true] {
+            let bigm_solution = solve_bigm(&cheap, delay_cost_type, lazy);
+            assert_eq!(
+                cheap.verify_solution(&bigm_solution, delay_cost_type),
+                Some(cheap_cost)
+            );
+        }
+    }
+
+    #[test]
+    fn blocked_intervals() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let base = crate::problem::problem1_with_stations();
+        let greedy = ddd::solvers::ddd::shared::greedy::greedy_schedule(&base);
+
+        // Close the first resource of train 0 while the greedy schedule uses it.
+        let mut problem = base.clone();
+        let (t_in, t_out) = (greedy[0][0], greedy[0][1]);
+        problem.blocked.push(ddd::problem::BlockedInterval {
+            resource_id: problem.trains[0].visits[0].resource_id,
+            from: t_in,
+            to: t_out.max(t_in + 1),
+        });
+        assert!(problem.validate().is_empty());
+        assert!(problem
+            .verify(&greedy, delay_cost_type)
+            .violations
+            .iter()
+            .any(|v| matches!(v, ddd::problem::Violation::Blocked { blocked_idx: 0, .. })));
+
+        let solve = |problem: &Problem| solve_ladder_sc(problem, delay_cost_type).1;
+        let cost = solve(&problem);
+        assert!(cost >= solve(&base));
+
+        for lazy in [false, true] {
+            let bigm_solution = solve_bigm(&problem, delay_cost_type, lazy);
+            assert_eq!(problem.verify_solution(&bigm_solution, delay_cost_type), Some(cost));
+        }
+
+        let ladder_solution = ddd::solvers::ddd::maxsat_ladder::solve(
+            &crate::mk_env,
+            satcoder::solvers::minisat::Solver::new(),
+            &problem,
+            30.0,
+            delay_cost_type,
+            |_, _| {},
+        )
+        .unwrap()
+        .0;
+        assert_eq!(problem.verify_solution(&ladder_solution, delay_cost_type), Some(cost));
+    }
+
+    #[test]
+    fn freeze_before() {
+        let delay_cost_type = DelayCostType::FiniteSteps123;
+        let base = crate::problem::problem1_with_stations();
+        let realised = ddd::solvers::ddd::shared::greedy::greedy_schedule(&base);
+        let realised_cost = base.verify_solution(&realised, delay_cost_type).unwrap();
+        let now = realised[0][1] + 1;
+
+        let frozen = base.freeze_before(now, &realised);
+        for (train, &train_idx) in frozen.problem.trains.iter().zip(frozen.trains.iter()) {
+            if realised[train_idx][0] < now {
+                assert_eq!(train.visits[0].latest, Some(train.visits[0].earliest));
+            }
+            assert!(train.visits[1..].iter().all(|v| v.earliest >= now));
+        }
+        assert!(!frozen.trains.contains(&0) || frozen.dropped_visits[0] >= 1);
+
+        let solution = ddd::solvers::ddd::maxsat_ladder_sc::solve(
+            crate::mk_env,
+            satcoder::solvers::minisat::Solver::new(),
+            &frozen.problem,
+            30.0,
+            delay_cost_type,
+            |_, _| {},
+        )
+        .unwrap()
+        .0;
+        let expanded = frozen.expand_solution(&realised, &solution);
+        let cost = base.verify_solution(&expanded, delay_cost_type).unwrap();
+        assert!(cost <= realised_cost);
+        for (times, realised_times) in expanded.iter().zip(realised.iter()) {
+            for (t, realised_t) in times.iter().zip(realised_times.iter()) {
+                assert!(if *realised_t < now { t == realised_t } else { *t >= now });
+            }
+        }
+
+        // A hard connection from a visit that has started becomes an
+        // earliest time of the visit that waits for it.
+        let mut connected = base.clone();
+        let to = (1..connected.trains.len())
+            .flat_map(|t| (0..connected.trains[t].visits.len()).map(move |v| (t, v)))
+            .find(|&(t, v)| realised[t][v] >= now)
+            .unwrap();
+        connected.connections.push(ddd::problem::Connection {
+            from: (0, 0),
+            to,
+            min_transfer: 600,
+            penalty: None,
+        });
+        let frozen = connected.freeze_before(now, &realised);
+        assert!(frozen.problem.connections.is_empty());
+        let to_train = frozen.trains.iter().position(|t| *t == to.0).unwrap();
+        let to_visit_idx = to.1 - frozen.dropped_visits[to_train];
+        let to_visit = &frozen.problem.trains[to_train].visits[to_visit_idx];
+        assert!(to_visit.earliest >= realised[0][0] + 600);
+
+        // A train that has finished still blocks its last resource for the
+        // headway after it.
+        let mut with_headways = base.clone();
+        with_headways.headways.default = ddd::problem::Headway {
+            same_direction: 100,
+            opposite_direction: 100,
+        };
+        let n_visits = base.trains[0].visits.len();
+        let left = realised[0][n_visits];
+        let frozen = with_headways.freeze_before(left + 1, &realised);
+        assert!(!frozen.trains.contains(&0));
+        assert!(frozen.problem.blocked.contains(&ddd::problem::BlockedInterval {
+            resource_id: base.trains[0].visits[n_visits - 1].resource_id,
+            from: realised[0][n_visits - 1],
+            to: left + 100,
+        }));
+        assert!(base.freeze_before(left + 1, &realised).problem.blocked.is_empty());
     }
 
     #[test]
@@ -1,4 +1,8 @@
-use crate::problem::{DelayMeasurementType, NamedProblem, Problem, Visit};
+use crate::problem::{
+    BlockedInterval, Connection, DelayCostType, DelayMeasurementType, DelayScenario, Headway,
+    Headways, MeasurementPolicy, NamedProblem, PiecewiseLinearCost, PiecewiseSegment, Problem,
+    TrainOrder, Turnaround, Visit,
+};
 use chrono::{Duration, NaiveDateTime};
 use log::debug;
 use std::{collections::HashMap, mem::take};
@@ -9,14 +13,180 @@ enum Pos<'a> {
     NotStarted(i32),
 }
 
+/// Why a txt instance could not be read, see [`ParseError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The file could not be read.
+    Io(String),
+    /// The expected field is missing from the line.
+    Missing,
+    /// The expected field has this value, which is not valid.
+    InvalidValue(String),
+}
+
+/// An error in a txt instance or a scenarios file, at a 1-based line and
+/// column of `file`. `expected` is the key, e.g. `AimedDepartureTime`, or
+/// what the field without a key at that position is, e.g. `track id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub expected: String,
+    pub kind: ParseErrorKind,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ParseErrorKind::Io(e) => write!(f, "{}: {}", self.file, e),
+            ParseErrorKind::Missing => write!(
+                f,
+                "{}:{}:{}: expected {}",
+                self.file, self.line, self.column, self.expected
+            ),
+            ParseErrorKind::InvalidValue(value) => write!(
+                f,
+                "{}:{}:{}: invalid {} `{}`",
+                self.file, self.line, self.column, self.expected, value
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The fields of a line of a txt instance, which come in two kinds:
+/// positional fields without a key (the track and train of a visit), and
+/// `key=value` fields in any order.
+struct TxtLine<'a> {
+    file: &'a str,
+    line: usize,
+    /// The column after the last field, where missing fields are reported.
+    end: usize,
+    positional: Vec<(usize, &'a str)>,
+    values: HashMap<&'a str, (usize, &'a str)>,
+}
+
+impl<'a> TxtLine<'a> {
+    /// Splits a line, without its comment, into fields with their columns.
+    fn new(file: &'a str, line: usize, content: &'a str) -> Self {
+        let mut positional = Vec::new();
+        let mut values = HashMap::new();
+        let mut end = 1;
+        let mut rest = content;
+        while let Some(start) = rest.find(|c: char| !c.is_ascii_whitespace()) {
+            let field_len = rest[start..]
+                .find(|c: char| c.is_ascii_whitespace())
+                .unwrap_or(rest.len() - start);
+            let field = &rest[start..start + field_len];
+            let column = content.len() - rest.len() + start + 1;
+            match field.split_once('=') {
+                Some((key, value)) => {
+                    values.insert(key, (column, value));
+                }
+                None => positional.push((column, field)),
+            }
+            end = column + field_len;
+            rest = &rest[start + field_len..];
+        }
+        TxtLine {
+            file,
+            line,
+            end,
+            positional,
+            values,
+        }
+    }
+
+    fn error(
+        &self,
+        column: usize,
+        expected: impl Into<String>,
+        kind: ParseErrorKind,
+    ) -> ParseError {
+        ParseError {
+            file: self.file.to_string(),
+            line: self.line,
+            column,
+            expected: expected.into(),
+            kind,
+        }
+    }
+
+    /// The positional field at `idx`, described as `expected`.
+    fn positional(&self, idx: usize, expected: &str) -> Result<(usize, &'a str), ParseError> {
+        self.positional
+            .get(idx)
+            .copied()
+            .ok_or_else(|| self.error(self.end, expected, ParseErrorKind::Missing))
+    }
+
+    /// The positional field at `idx`, parsed as a `T`.
+    fn parse_positional<T: std::str::FromStr>(
+        &self,
+        idx: usize,
+        expected: &str,
+    ) -> Result<(usize, T), ParseError> {
+        let (column, value) = self.positional(idx, expected)?;
+        let parsed = value.parse().map_err(|_| {
+            self.error(column, expected, ParseErrorKind::InvalidValue(value.to_string()))
+        })?;
+        Ok((column, parsed))
+    }
+
+    /// The value of `key`, if the line has it.
+    fn optional(&self, key: &str) -> Result<Option<i32>, ParseError> {
+        self.values
+            .get(key)
+            .map(|&(column, value)| {
+                value.parse::<i32>().map_err(|_| {
+                    self.error(column, key, ParseErrorKind::InvalidValue(value.to_string()))
+                })
+            })
+            .transpose()
+    }
+
+    fn get(&self, key: &str) -> Result<i32, ParseError> {
+        self.optional(key)?
+            .ok_or_else(|| self.error(self.end, key, ParseErrorKind::Missing))
+    }
+}
+
+/// A line without its comment, which starts with `#` or `//`. `None` if
+/// the line is only a comment, which is skipped rather than ending a train.
+fn txt_content(line: &str) -> Option<&str> {
+    let content = match (line.find('#'), line.find("//")) {
+        (Some(a), Some(b)) => &line[..a.min(b)],
+        (Some(a), None) | (None, Some(a)) => &line[..a],
+        (None, None) => line,
+    };
+    (content.len() == line.len() || !content.trim().is_empty()).then_some(content)
+}
+
+/// Reads an instance in the txt format: blocks of lines separated by empty
+/// lines, one per train, each a header line
+/// `TrainId=.. Delay=.. FreeRun=.. [Weight=..]` followed by a line
+/// `<track> <train> AimedDepartureTime=.. WaitTime=.. BaseTime=.. RunTime=..
+/// [LatestDepartureTime=..] [OptSolTime=..]` per track. The `key=value`
+/// fields may come in any order, and comments starting with `#` or `//` are
+/// ignored. With `with_solution`, the `OptSolTime` of each track is read
+/// into a solution.
 pub fn read_txt_file(
     instance_fn: &str,
-    measurement: DelayMeasurementType,
+    measurement: impl Into<MeasurementPolicy>,
     with_solution: bool,
     output_solution: Option<Vec<Vec<i32>>>,
     mut write_solution: impl FnMut(&str),
-) -> (NamedProblem, Option<Vec<Vec<i32>>>) {
-    let instance_txt = std::fs::read_to_string(instance_fn).unwrap();
+) -> Result<(NamedProblem, Option<Vec<Vec<i32>>>), ParseError> {
+    let measurement = measurement.into();
+    let instance_txt = std::fs::read_to_string(instance_fn).map_err(|e| ParseError {
+        file: instance_fn.to_string(),
+        line: 0,
+        column: 0,
+        expected: "a readable file".to_string(),
+        kind: ParseErrorKind::Io(e.to_string()),
+    })?;
     let mut train_names = Vec::new();
     let mut resource_names = Vec::new();
     let mut resources = HashMap::new();
@@ -25,77 +195,86 @@ pub fn read_txt_file(
     let any_station_resource = 0;
     resource_names.push("Any station".to_string());
 
-    let mut current_train: Option<Vec<Visit>> = None;
+    let mut current_train: Option<(usize, Vec<Visit>)> = None;
+    let mut current_weight = 1;
     let mut next_earliest: Option<i32> = None;
     let mut problem = Problem {
         name: instance_fn.to_string(),
-        conflicts: Vec::new(),
+        conflicts: Default::default(),
         trains: Vec::new(),
+        headways: Default::default(),
+        capacities: Default::default(),
+        connections: Vec::new(),
+        turnarounds: Vec::new(),
+        blocked: Vec::new(),
+        orders: Vec::new(),
     };
 
-    let mut lines = instance_txt.lines().peekable();
-    while let Some(line) = lines.next() {
-        if !line.trim().is_empty() {
-            let mut fields = line.split_ascii_whitespace();
-            fn get_pair(fields: &mut std::str::SplitAsciiWhitespace) -> (String, i32) {
-                // let name = fields.next().unwrap().to_string();
-                // let value = fields.next().unwrap().parse::<i64>().unwrap();
-                let pair = fields.next().unwrap();
-                let mut split = pair.split('=');
-                let name = split.next().unwrap().to_string();
-                let value = split.next().unwrap().parse::<i32>().unwrap();
-                (name, value)
-            }
+    let lines = instance_txt.lines().collect::<Vec<_>>();
+    for (line_idx, line) in lines.iter().copied().enumerate() {
+        let Some(content) = txt_content(line) else {
+            write_solution(line);
+            continue;
+        };
+        let next_content = lines[line_idx + 1..].iter().find_map(|l| txt_content(l));
+        let fields = TxtLine::new(instance_fn, line_idx + 1, content);
 
+        if !content.trim().is_empty() {
             match current_train.as_mut() {
                 None => {
                     // Expect train header
                     write_solution(line);
 
-                    let (train_id_field, train_id) = get_pair(&mut fields);
-                    assert!(train_id_field == "TrainId");
-                    let (delay_field, _delay) = get_pair(&mut fields);
-                    assert!(delay_field == "Delay");
-                    let (free_run_field, _free_run) = get_pair(&mut fields);
-                    assert!(free_run_field == "FreeRun");
+                    let train_id = fields.get("TrainId")?;
+                    fields.get("Delay")?;
+                    fields.get("FreeRun")?;
+                    // Optional priority weight of the train, e.g. `Weight=3`.
+                    current_weight = match fields.optional("Weight")? {
+                        Some(weight) if weight <= 0 => {
+                            let column = fields.values["Weight"].0;
+                            let value = ParseErrorKind::InvalidValue(weight.to_string());
+                            return Err(fields.error(column, "Weight", value));
+                        }
+                        weight => weight.unwrap_or(1) as usize,
+                    };
 
                     train_names.push(format!("Train{}", train_id));
                     if with_solution {
                         solution.push(Vec::new());
                     }
-                    current_train = Some(Vec::new());
+                    current_train = Some((line_idx + 1, Vec::new()));
                     next_earliest = None;
                 }
-                Some(train) => {
-                    let track_id = fields.next().unwrap();
-                    let train_id = fields.next().unwrap();
-                    assert!(train_names.last().map(String::as_str) == Some(train_id));
-
-                    let (aimeddep_field, aimeddep) = get_pair(&mut fields);
-                    assert!(aimeddep_field == "AimedDepartureTime");
-                    let (waittime_field, wait_time) = get_pair(&mut fields);
-                    assert!(waittime_field == "WaitTime");
-                    let (basetime_field, base_time) = get_pair(&mut fields);
-                    assert!(basetime_field == "BaseTime");
-                    let (runtime_field, run_time) = get_pair(&mut fields);
-                    assert!(runtime_field == "RunTime");
-
-                    let sol_time = with_solution.then(|| {
-                        let (soltime_field, sol_time) = get_pair(&mut fields);
-                        assert!(soltime_field == "OptSolTime");
-                        sol_time
-                    });
+                Some((_, train)) => {
+                    let (_, track_id) = fields.positional(0, "track id")?;
+                    let (train_column, train_id) = fields.positional(1, "train id")?;
+                    if train_names.last().map(String::as_str) != Some(train_id) {
+                        let value = ParseErrorKind::InvalidValue(train_id.to_string());
+                        return Err(fields.error(train_column, "train id", value));
+                    }
+
+                    let aimeddep = fields.get("AimedDepartureTime")?;
+                    let wait_time = fields.get("WaitTime")?;
+                    let base_time = fields.get("BaseTime")?;
+                    let run_time = fields.get("RunTime")?;
+                    // Optional hard deadline on the departure, e.g. `LatestDepartureTime=3600`.
+                    let latest_dep = fields.optional("LatestDepartureTime")?;
+
+                    let sol_time = with_solution.then(|| fields.get("OptSolTime")).transpose()?;
 
                     if let Some(s) = output_solution.as_ref() {
                         let current_train_idx = problem.trains.len();
                         let current_visit_idx = train.len() + 1;
                         let t = s[current_train_idx][current_visit_idx];
-                        write_solution(&format!("{} {} AimedDepartureTime={} WaitTime={} BaseTime={} RunTime={}\tOptSolTime={}", 
-                            track_id, train_id, aimeddep, wait_time, base_time, run_time, t,
+                        let latest_field = latest_dep
+                            .map(|l| format!(" LatestDepartureTime={}", l))
+                            .unwrap_or_default();
+                        write_solution(&format!("{} {} AimedDepartureTime={} WaitTime={} BaseTime={} RunTime={}{}\tOptSolTime={}", 
+                            track_id, train_id, aimeddep, wait_time, base_time, run_time, latest_field, t,
                         ));
                     }
 
-                    let is_last_track = match lines.peek() {
+                    let is_last_track = match next_content {
                         None => true,
                         Some(l) => l.trim().is_empty(),
                     };
@@ -106,7 +285,11 @@ pub fn read_txt_file(
                         idx
                     });
 
-                    let (aimed_in, aimed_out) = match measurement {
+                    let earliest_in = next_earliest.unwrap_or(base_time as i32 - wait_time as i32);
+                    let earliest_out = base_time as i32;
+                    next_earliest = Some(earliest_out + run_time);
+
+                    let (aimed_in, aimed_out) = match measurement.get(train_id, track_id) {
                         DelayMeasurementType::AllStationArrivals => {
                             (Some(aimeddep - wait_time), None)
                         }
@@ -114,25 +297,29 @@ pub fn read_txt_file(
                         DelayMeasurementType::FinalStationArrival => {
                             (None, is_last_track.then(|| aimeddep))
                         }
-                        DelayMeasurementType::EverywhereEarliest => todo!(),
+                        DelayMeasurementType::EverywhereEarliest => {
+                            (Some(earliest_in), Some(earliest_out))
+                        }
                     };
 
-                    let earliest_in = next_earliest.unwrap_or(base_time as i32 - wait_time as i32);
-                    let earliest_out = base_time as i32;
-                    next_earliest = Some(earliest_out + run_time);
-
                     train.push(Visit {
                         earliest: earliest_in,
                         aimed: aimed_in,
+                        latest: None,
                         resource_id: any_station_resource,
                         travel_time: wait_time as i32,
+                        max_travel_time: None,
+                        no_early_departure: false,
                     });
 
                     train.push(Visit {
                         earliest: earliest_out,
                         aimed: aimed_out,
+                        latest: latest_dep,
                         resource_id,
                         travel_time: run_time as i32,
+                        max_travel_time: None,
+                        no_early_departure: false,
                     });
 
                     if let Some(sol_time) = sol_time {
@@ -148,15 +335,28 @@ pub fn read_txt_file(
             write_solution(line);
         }
 
-        if line.trim().is_empty() || lines.peek().is_none() {
-            if let Some(mut visits) = current_train.take() {
+        if content.trim().is_empty() || next_content.is_none() {
+            if let Some((header_line, mut visits)) = current_train.take() {
+                // A train needs at least one track.
+                let Some(prev_visit) = visits.last().copied() else {
+                    return Err(ParseError {
+                        file: instance_fn.to_string(),
+                        line: header_line + 1,
+                        column: 1,
+                        expected: "track id".to_string(),
+                        kind: ParseErrorKind::Missing,
+                    });
+                };
+
                 // add the final station visit
-                let prev_visit = *visits.last().unwrap();
                 visits.push(Visit {
                     earliest: prev_visit.earliest + prev_visit.travel_time,
                     aimed: None,
+                    latest: None,
                     resource_id: any_station_resource,
                     travel_time: 0,
+                    max_travel_time: None,
+                    no_early_departure: false,
                 });
 
                 if with_solution {
@@ -164,27 +364,35 @@ pub fn read_txt_file(
                     solution.last_mut().unwrap().push(prev_time);
                 }
 
-                problem.trains.push(crate::problem::Train { visits })
+                problem.trains.push(crate::problem::Train {
+                    visits,
+                    weight: current_weight,
+                    visit_costs: Vec::new(),
+                    cancellation_cost: None,
+                    running_time_cost: None,
+                    earliness_cost: None,
+                })
             }
         }
     }
 
     // All tracks are exclusive
     for (_, id) in resources.iter() {
-        problem.conflicts.push((*id, *id));
+        problem.conflicts.insert(*id, *id);
     }
 
-    (
+    Ok((
         NamedProblem {
             problem,
             train_names,
             resource_names,
         },
         with_solution.then(|| solution),
-    )
+    ))
 }
 
-pub fn read_xml_file(instance_fn: &str, measurement: DelayMeasurementType) -> NamedProblem {
+pub fn read_xml_file(instance_fn: &str, measurement: impl Into<MeasurementPolicy>) -> NamedProblem {
+    let measurement = measurement.into();
     let date_format = "%Y-%m-%dT%H:%M:%S";
     let parse_date = |d| chrono::NaiveDateTime::parse_from_str(d, date_format).unwrap();
     let instance_xml = std::fs::read_to_string(instance_fn).unwrap();
@@ -241,7 +449,7 @@ pub fn read_xml_file(instance_fn: &str, measurement: DelayMeasurementType) -> Na
     }
 
     let minimum_running_times = get_runningtimes_map(&doc);
-    let _objective_map = get_objective_map(&doc);
+    let objective_map = get_objective_map(&doc);
     let (time_now, train_positions) = get_train_pos(&doc, date_format);
 
     let timetable_node = doc
@@ -260,6 +468,11 @@ pub fn read_xml_file(instance_fn: &str, measurement: DelayMeasurementType) -> Na
         let id = train_schedule.attribute("TrainId").unwrap();
         let speed_class = train_schedule.attribute("SpeedClass").unwrap();
 
+        let weight = train_schedule
+            .attribute("Weight")
+            .map(|w| w.parse::<usize>().unwrap())
+            .unwrap_or(1);
+
         let _type_ = train_schedule.attribute("Type").unwrap();
         let _origin_id = train_schedule.attribute("OriginId").unwrap();
         let _destination_id = train_schedule.attribute("DestinationId").unwrap();
@@ -381,7 +594,7 @@ pub fn read_xml_file(instance_fn: &str, measurement: DelayMeasurementType) -> Na
             }
 
             // println!("Train {} visits {:?}", id, visits);
-            problem_trains.push((id, visits, last_station_name));
+            problem_trains.push((id, visits, last_station_name, weight));
         } else {
             // println!("Ignoring train {} has left the network.", id);
         }
@@ -402,15 +615,24 @@ pub fn read_xml_file(instance_fn: &str, measurement: DelayMeasurementType) -> Na
     let mut problem = crate::problem::Problem {
         name: instance_fn.to_string(),
         trains: Vec::new(),
-        conflicts: Vec::new(),
+        conflicts: Default::default(),
+        headways: Default::default(),
+        capacities: Default::default(),
+        connections: Vec::new(),
+        turnarounds: Vec::new(),
+        blocked: Vec::new(),
+        orders: Vec::new(),
     };
     for ((res_type, _name), i) in resource_ids.iter() {
         if matches!(res_type, ResourceType::Track) {
-            problem.conflicts.push((*i, *i));
+            problem.conflicts.insert(*i, *i);
         }
     }
-    for (name, visits, last_station_name) in problem_trains.iter() {
+    for (train_name, visits, last_station_name, weight) in problem_trains.iter() {
         let mut t = Vec::new();
+        let mut visit_costs = Vec::new();
+        // The station a track visit departs from.
+        let mut station = "";
         for ((res_type, name), earliest, travel, aimed) in visits.iter() {
             // println!("Looking up resource {:?}", r);
             let resource_id = resource_ids[&(*res_type, *name)];
@@ -427,7 +649,30 @@ pub fn read_xml_file(instance_fn: &str, measurement: DelayMeasurementType) -> Na
             //     i
             // });
 
-            let aimed = match measurement {
+            if matches!(res_type, ResourceType::Station) {
+                station = *name;
+            }
+
+            // Penalties of the `PiecewiseLinear` objective are given per station arrival.
+            let penalty = objective_map
+                .get(&(**train_name, **name))
+                .filter(|_| matches!(res_type, ResourceType::Station))
+                .zip(*aimed)
+                .map(|(segments, aimed)| {
+                    let segments = segments
+                        .iter()
+                        .map(|(from, to, from_value, slope)| PiecewiseSegment {
+                            from: *from,
+                            to: *to,
+                            from_value: *from_value as f64,
+                            slope: *slope as f64,
+                        })
+                        .collect();
+                    PiecewiseLinearCost::new((aimed - time_now).num_seconds() as i32, segments)
+                        .unwrap_or_else(|e| panic!("train {} station {}: {}", train_name, name, e))
+                });
+            visit_costs.push(penalty);
+            let aimed = match measurement.get(train_name, station) {
                 DelayMeasurementType::AllStationArrivals => {
                     matches!(res_type, ResourceType::Station).then(|| *aimed)
                 }
@@ -448,11 +693,24 @@ pub fn read_xml_file(instance_fn: &str, measurement: DelayMeasurementType) -> Na
                 earliest: (*earliest - time_now).num_seconds() as i32,
                 travel_time: travel.num_seconds() as i32,
                 aimed,
+                latest: None,
+                max_travel_time: None,
+                no_early_departure: false,
             });
         }
 
-        problem.trains.push(crate::problem::Train { visits: t });
-        train_names.push(name.to_string());
+        if visit_costs.iter().all(Option::is_none) {
+            visit_costs.clear();
+        }
+        problem.trains.push(crate::problem::Train {
+            visits: t,
+            weight: *weight,
+            visit_costs,
+            cancellation_cost: None,
+            running_time_cost: None,
+            earliness_cost: None,
+        });
+        train_names.push(train_name.to_string());
     }
 
     NamedProblem {
@@ -462,6 +720,538 @@ pub fn read_xml_file(instance_fn: &str, measurement: DelayMeasurementType) -> Na
     }
 }
 
+/// Reads a train weight side file. Each non-empty line not starting with `#`
+/// holds a train name and its weight separated by whitespace, e.g.
+/// `Train12 5`.
+pub fn read_train_weights(weights_fn: &str) -> HashMap<String, usize> {
+    let weights_txt = std::fs::read_to_string(weights_fn).unwrap();
+    let mut weights = HashMap::new();
+    for line in weights_txt.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_ascii_whitespace();
+        let name = fields.next().unwrap();
+        let weight = fields.next().unwrap().parse::<usize>().unwrap();
+        assert!(weight > 0);
+        weights.insert(name.to_string(), weight);
+    }
+    weights
+}
+
+/// Reads a cancellation cost side file in the same format as
+/// [`read_train_weights`], e.g. `Train12 600`. The listed trains may be
+/// cancelled at that cost.
+pub fn read_cancellation_costs(costs_fn: &str) -> HashMap<String, i32> {
+    let costs_txt = std::fs::read_to_string(costs_fn).unwrap();
+    let mut costs = HashMap::new();
+    for line in costs_txt.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_ascii_whitespace();
+        let name = fields.next().unwrap();
+        let cost = fields.next().unwrap().parse::<i32>().unwrap();
+        costs.insert(name.to_string(), cost);
+    }
+    costs
+}
+
+/// Reads a delay measurement policy side file. Each non-empty line not
+/// starting with `#` is one of
+///
+///  * `default <measurement>`,
+///  * `train <train name> <measurement>`,
+///  * `station <station id> <measurement>`,
+///
+/// where `<measurement>` is a name accepted by
+/// [`DelayMeasurementType::from_name`], e.g. `station XX.S7 departures`.
+/// Without a `default` line, `default` is used for everything else.
+pub fn read_measurement_policy(
+    policy_fn: &str,
+    default: DelayMeasurementType,
+) -> MeasurementPolicy {
+    let policy_txt = std::fs::read_to_string(policy_fn).unwrap();
+    let mut policy = MeasurementPolicy::from(default);
+    let parse = |name: &str| {
+        DelayMeasurementType::from_name(name)
+            .unwrap_or_else(|| panic!("unknown delay measurement '{}'", name))
+    };
+    for line in policy_txt.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line.split_ascii_whitespace().collect::<Vec<_>>();
+        match fields.as_slice() {
+            ["default", m] => policy.default = parse(m),
+            ["train", name, m] => {
+                policy.trains.insert(name.to_string(), parse(m));
+            }
+            ["station", name, m] => {
+                policy.stations.insert(name.to_string(), parse(m));
+            }
+            _ => panic!("invalid measurement policy line '{}'", line),
+        }
+    }
+    policy
+}
+
+/// Reads headways from a side file with lines
+/// `default <same> [<opposite>]`, `resource <name> <same> [<opposite>]` or
+/// `pair <name> <name> <same> [<opposite>]`, where the opposite-direction
+/// headway defaults to the same-direction one. Resources that do not occur
+/// in `resource_names` are ignored, so one file can serve several instances.
+pub fn read_headways(headways_fn: &str, resource_names: &[String]) -> Headways {
+    let headways_txt = std::fs::read_to_string(headways_fn).unwrap();
+    let resource_ids = resource_names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect::<HashMap<_, _>>();
+
+    let mut headways = Headways::default();
+    for line in headways_txt.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line.split_ascii_whitespace().collect::<Vec<_>>();
+        let n_names = match fields[0] {
+            "default" => 0,
+            "resource" => 1,
+            "pair" => 2,
+            x => panic!("unknown headway entry {:?}", x),
+        };
+        let times = fields[1 + n_names..]
+            .iter()
+            .map(|x| x.parse::<i32>().unwrap())
+            .collect::<Vec<_>>();
+        assert!(times.len() == 1 || times.len() == 2);
+        let headway = Headway {
+            same_direction: times[0],
+            opposite_direction: *times.last().unwrap(),
+        };
+
+        let resources = fields[1..1 + n_names]
+            .iter()
+            .map(|name| resource_ids.get(name).copied())
+            .collect::<Option<Vec<_>>>();
+        match resources.as_deref() {
+            Some([]) => headways.default = headway,
+            Some([r]) => {
+                headways.resources.insert(*r, headway);
+            }
+            Some([r1, r2]) => headways.insert_pair(*r1, *r2, headway),
+            _ => debug!("Ignoring headway for unknown resource: {}", line),
+        }
+    }
+    headways
+}
+
+/// Reads passenger connections from a side file with lines
+/// `<arriving train> <visit> <departing train> <visit> <min transfer> [<penalty>]`,
+/// where visits are given by their index in the train's route. Connections
+/// without a penalty must be kept. Lines naming trains that are not in
+/// `problem` are ignored, so one file can serve several instances.
+pub fn read_connections(connections_fn: &str, problem: &NamedProblem) -> Vec<Connection> {
+    let connections_txt = std::fs::read_to_string(connections_fn).unwrap();
+    let train_ids = problem
+        .train_names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect::<HashMap<_, _>>();
+
+    let mut connections = Vec::new();
+    for line in connections_txt.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line.split_ascii_whitespace().collect::<Vec<_>>();
+        let (from_train, from_visit, to_train, to_visit, min_transfer, penalty) =
+            match fields.as_slice() {
+                [t1, v1, t2, v2, min_transfer] => (*t1, *v1, *t2, *v2, *min_transfer, None),
+                [t1, v1, t2, v2, min_transfer, penalty] => {
+                    (*t1, *v1, *t2, *v2, *min_transfer, Some(penalty.parse::<i32>().unwrap()))
+                }
+                _ => panic!("invalid connection line '{}'", line),
+            };
+        match (train_ids.get(from_train), train_ids.get(to_train)) {
+            (Some(t1), Some(t2)) => connections.push(Connection {
+                from: (*t1, from_visit.parse().unwrap()),
+                to: (*t2, to_visit.parse().unwrap()),
+                min_transfer: min_transfer.parse().unwrap(),
+                penalty,
+            }),
+            _ => debug!("Ignoring connection for unknown train: {}", line),
+        }
+    }
+    connections
+}
+
+/// Reads rolling-stock turnarounds from a side file with lines
+/// `<train> <next train> <turnaround time>`. Lines naming trains that are
+/// not in `problem` are ignored.
+pub fn read_turnarounds(turnarounds_fn: &str, problem: &NamedProblem) -> Vec<Turnaround> {
+    let turnarounds_txt = std::fs::read_to_string(turnarounds_fn).unwrap();
+    let train_ids = problem
+        .train_names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect::<HashMap<_, _>>();
+
+    let mut turnarounds = Vec::new();
+    for line in turnarounds_txt.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line.split_ascii_whitespace().collect::<Vec<_>>();
+        let [from, to, time] = fields.as_slice() else {
+            panic!("invalid turnaround line '{}'", line);
+        };
+        match (train_ids.get(from), train_ids.get(to)) {
+            (Some(from), Some(to)) => turnarounds.push(Turnaround {
+                from: *from,
+                to: *to,
+                time: time.parse().unwrap(),
+            }),
+            _ => debug!("Ignoring turnaround for unknown train: {}", line),
+        }
+    }
+    turnarounds
+}
+
+/// Reads fixed train orders from a side file with lines
+/// `<resource name> <train> <train> ...`, giving the order in which the
+/// trains must pass the resource. Trains that are not in `problem` are left
+/// out of the order, and lines naming an unknown resource are ignored.
+pub fn read_orders(orders_fn: &str, problem: &NamedProblem) -> Vec<TrainOrder> {
+    let orders_txt = std::fs::read_to_string(orders_fn).unwrap();
+    let train_ids = problem
+        .train_names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect::<HashMap<_, _>>();
+    let resource_ids = problem
+        .resource_names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect::<HashMap<_, _>>();
+
+    let mut orders = Vec::new();
+    for line in orders_txt.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line.split_ascii_whitespace().collect::<Vec<_>>();
+        let [resource, trains @ ..] = fields.as_slice() else {
+            unreachable!();
+        };
+        let Some(resource_id) = resource_ids.get(resource) else {
+            debug!("Ignoring order on unknown resource: {}", line);
+            continue;
+        };
+        let trains = trains
+            .iter()
+            .filter_map(|name| {
+                let train_idx = train_ids.get(name).copied();
+                if train_idx.is_none() {
+                    debug!("Ignoring unknown train {} in order: {}", name, line);
+                }
+                train_idx
+            })
+            .collect::<Vec<_>>();
+        if trains.len() >= 2 {
+            orders.push(TrainOrder {
+                resource_id: *resource_id,
+                trains,
+            });
+        }
+    }
+    orders
+}
+
+/// Reads delay scenarios from a side file with lines
+/// `<name> <weight> (<train> <visit index> <delay>)*`, each shifting the
+/// earliest times of the given visits by the delay. Delays of trains that
+/// are not in `problem` are ignored. Lines starting with `#` are comments.
+pub fn read_scenarios(
+    scenarios_fn: &str,
+    problem: &NamedProblem,
+) -> Result<Vec<DelayScenario>, ParseError> {
+    let scenarios_txt = std::fs::read_to_string(scenarios_fn).map_err(|e| ParseError {
+        file: scenarios_fn.to_string(),
+        line: 0,
+        column: 0,
+        expected: "a readable file".to_string(),
+        kind: ParseErrorKind::Io(e.to_string()),
+    })?;
+    let train_ids = problem
+        .train_names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect::<HashMap<_, _>>();
+
+    let mut scenarios = Vec::new();
+    for (line_idx, line) in scenarios_txt.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let fields = TxtLine::new(scenarios_fn, line_idx + 1, line);
+        let (_, name) = fields.positional(0, "scenario name")?;
+        let (_, weight) = fields.parse_positional::<usize>(1, "weight")?;
+
+        let mut delays = Vec::new();
+        for idx in (2..fields.positional.len()).step_by(3) {
+            let (_, train) = fields.positional(idx, "train")?;
+            let (visit_column, visit_idx) = fields.parse_positional(idx + 1, "visit index")?;
+            let (_, delay) = fields.parse_positional::<i32>(idx + 2, "delay")?;
+            let Some(&train_idx) = train_ids.get(train) else {
+                debug!("Ignoring unknown train {} in scenario: {}", train, line);
+                continue;
+            };
+            if visit_idx >= problem.problem.trains[train_idx].visits.len() {
+                let value = ParseErrorKind::InvalidValue(visit_idx.to_string());
+                return Err(fields.error(visit_column, "visit index", value));
+            }
+            delays.push((train_idx, visit_idx, delay));
+        }
+        scenarios.push(DelayScenario {
+            name: name.to_string(),
+            weight,
+            delays,
+        });
+    }
+    Ok(scenarios)
+}
+
+/// Reads blocked intervals from a side file with lines
+/// `<resource name> <from> <to>`, in the time unit of the instance.
+/// Resources that do not occur in `resource_names` are ignored.
+pub fn read_blocked_intervals(blocked_fn: &str, resource_names: &[String]) -> Vec<BlockedInterval> {
+    let blocked_txt = std::fs::read_to_string(blocked_fn).unwrap();
+    let resource_ids = resource_names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect::<HashMap<_, _>>();
+
+    let mut blocked = Vec::new();
+    for line in blocked_txt.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line.split_ascii_whitespace().collect::<Vec<_>>();
+        let [resource, from, to] = fields.as_slice() else {
+            panic!("invalid blocked interval line '{}'", line);
+        };
+        match resource_ids.get(resource) {
+            Some(resource_id) => blocked.push(BlockedInterval {
+                resource_id: *resource_id,
+                from: from.parse().unwrap(),
+                to: to.parse().unwrap(),
+            }),
+            None => debug!("Ignoring blocked interval for unknown resource: {}", line),
+        }
+    }
+    blocked
+}
+
+/// Version of the JSON instance and solution formats written by
+/// [`write_json`] and [`write_json_solution`]. Files with a newer version
+/// are rejected.
+pub const JSON_FORMAT_VERSION: u32 = 1;
+
+/// A problem instance in the JSON format. The problem itself is stored as
+/// in [`NamedProblem`], e.g.
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "objective": "finsteps123",
+///   "problem": {
+///     "name": "example",
+///     "trains": [
+///       { "weight": 1, "visits": [
+///         { "resource_id": 1, "earliest": 0, "aimed": 0, "latest": null, "travel_time": 60 },
+///         { "resource_id": 0, "earliest": 60, "aimed": 90, "travel_time": 0 } ] }
+///     ],
+///     "conflicts": [[1, 1]],
+///     "headways": { "default": { "same_direction": 0, "opposite_direction": 0 } }
+///   },
+///   "train_names": ["T1"],
+///   "resource_names": ["Any station", "A-B"]
+/// }
+/// ```
+///
+/// `objective` (a name as accepted by [`DelayCostType::from_name`]),
+/// `weight`, `aimed`, `latest`, `headways` and a train's
+/// `cancellation_cost` are optional.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JsonInstance {
+    pub version: u32,
+    #[serde(default)]
+    pub objective: Option<String>,
+    #[serde(flatten)]
+    pub problem: NamedProblem,
+}
+
+/// A solution in the JSON format: the entry and exit time of every visit,
+/// with the delay cost of each visit and train under `objective`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JsonSolution {
+    pub version: u32,
+    pub name: String,
+    pub objective: String,
+    pub cost: i32,
+    pub trains: Vec<JsonTrainSolution>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JsonTrainSolution {
+    pub name: String,
+    pub cost: i32,
+    /// A cancelled train has no visits and costs its cancellation cost.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+    pub visits: Vec<JsonVisitSolution>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JsonVisitSolution {
+    pub resource: String,
+    pub time_in: i32,
+    pub time_out: i32,
+    pub cost: i32,
+}
+
+impl JsonSolution {
+    pub fn new(
+        problem: &NamedProblem,
+        solution: &[Vec<i32>],
+        delay_cost_type: DelayCostType,
+        objective: &str,
+    ) -> Self {
+        let trains = problem
+            .problem
+            .trains
+            .iter()
+            .zip(solution.iter())
+            .enumerate()
+            .map(|(train_idx, (train, times))| {
+                if problem.problem.is_cancelled(solution, train_idx) {
+                    return JsonTrainSolution {
+                        name: problem.train_names[train_idx].clone(),
+                        cost: train.cancellation_cost.unwrap(),
+                        cancelled: true,
+                        visits: Vec::new(),
+                    };
+                }
+                let visits = train
+                    .visits
+                    .iter()
+                    .enumerate()
+                    .map(|(visit_idx, visit)| JsonVisitSolution {
+                        resource: problem.resource_names[visit.resource_id].clone(),
+                        time_in: times[visit_idx],
+                        time_out: times[visit_idx + 1],
+                        cost: (train.visit_delay_cost(delay_cost_type, visit_idx, times[visit_idx])
+                            + train.visit_earliness_cost(visit_idx, times[visit_idx]))
+                            as i32,
+                    })
+                    .collect::<Vec<_>>();
+                JsonTrainSolution {
+                    name: problem.train_names[train_idx].clone(),
+                    cost: visits.iter().map(|v| v.cost).sum(),
+                    cancelled: false,
+                    visits,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        JsonSolution {
+            version: JSON_FORMAT_VERSION,
+            name: problem.problem.name.clone(),
+            objective: objective.to_string(),
+            cost: trains.iter().map(|t| t.cost).sum(),
+            trains,
+        }
+    }
+
+    /// The solution in the usual shape, one `n_visits + 1` vector of times
+    /// per train.
+    pub fn times(&self) -> Vec<Vec<i32>> {
+        self.trains
+            .iter()
+            .map(|train| {
+                let mut times = train.visits.iter().map(|v| v.time_in).collect::<Vec<_>>();
+                times.extend(train.visits.last().map(|v| v.time_out));
+                times
+            })
+            .collect()
+    }
+}
+
+fn check_json_version(version: u32, filename: &str) -> Result<(), String> {
+    if version > JSON_FORMAT_VERSION {
+        return Err(format!(
+            "{}: format version {} is newer than the supported version {}",
+            filename, version, JSON_FORMAT_VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// Reads an instance in the JSON format (see [`JsonInstance`]).
+pub fn read_json(instance_fn: &str) -> Result<JsonInstance, String> {
+    let json = std::fs::read_to_string(instance_fn).map_err(|e| format!("{}: {}", instance_fn, e))?;
+    let instance: JsonInstance =
+        serde_json::from_str(&json).map_err(|e| format!("{}: {}", instance_fn, e))?;
+    check_json_version(instance.version, instance_fn)?;
+    Ok(instance)
+}
+
+/// Writes an instance in the JSON format (see [`JsonInstance`]).
+pub fn write_json(
+    instance_fn: &str,
+    problem: &NamedProblem,
+    objective: Option<&str>,
+) -> Result<(), String> {
+    let instance = JsonInstance {
+        version: JSON_FORMAT_VERSION,
+        objective: objective.map(str::to_string),
+        problem: problem.clone(),
+    };
+    let json = serde_json::to_string_pretty(&instance).map_err(|e| e.to_string())?;
+    std::fs::write(instance_fn, json).map_err(|e| format!("{}: {}", instance_fn, e))
+}
+
+/// Reads a solution in the JSON format (see [`JsonSolution`]).
+pub fn read_json_solution(solution_fn: &str) -> Result<JsonSolution, String> {
+    let json = std::fs::read_to_string(solution_fn).map_err(|e| format!("{}: {}", solution_fn, e))?;
+    let solution: JsonSolution =
+        serde_json::from_str(&json).map_err(|e| format!("{}: {}", solution_fn, e))?;
+    check_json_version(solution.version, solution_fn)?;
+    Ok(solution)
+}
+
+/// Writes a solution in the JSON format (see [`JsonSolution`]).
+pub fn write_json_solution(solution_fn: &str, solution: &JsonSolution) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(solution).map_err(|e| e.to_string())?;
+    std::fs::write(solution_fn, json).map_err(|e| format!("{}: {}", solution_fn, e))
+}
+
 #[allow(unused)]
 fn station_info(stations: roxmltree::Node) {
     for station in stations.children().filter(|c| c.is_element()) {
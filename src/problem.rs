@@ -6,6 +6,55 @@ pub enum DelayMeasurementType {
     FinalStationArrival,
 }
 
+impl DelayMeasurementType {
+    /// Parses a measurement name: `earliest`, `arrivals`, `departures` or
+    /// `final`.
+    pub fn from_name(value: &str) -> Option<DelayMeasurementType> {
+        match value.to_ascii_lowercase().as_str() {
+            "earliest" => Some(DelayMeasurementType::EverywhereEarliest),
+            "arrivals" => Some(DelayMeasurementType::AllStationArrivals),
+            "departures" => Some(DelayMeasurementType::AllStationDepartures),
+            "final" => Some(DelayMeasurementType::FinalStationArrival),
+            _ => None,
+        }
+    }
+}
+
+/// Chooses the [`DelayMeasurementType`] separately for each train and
+/// station, i.e. which `Visit::aimed` times the instance readers fill in.
+/// A rule for the train takes precedence over a rule for the station, which
+/// takes precedence over the default.
+///
+/// Trains are identified by their name in the [`NamedProblem`]. Stations are
+/// identified by their `StationId` in XML instances and by the track id of
+/// the departure in txt instances, which have no station names.
+#[derive(Debug, Clone)]
+pub struct MeasurementPolicy {
+    pub default: DelayMeasurementType,
+    pub trains: std::collections::HashMap<String, DelayMeasurementType>,
+    pub stations: std::collections::HashMap<String, DelayMeasurementType>,
+}
+
+impl MeasurementPolicy {
+    pub fn get(&self, train: &str, station: &str) -> DelayMeasurementType {
+        self.trains
+            .get(train)
+            .or_else(|| self.stations.get(station))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+impl From<DelayMeasurementType> for MeasurementPolicy {
+    fn from(default: DelayMeasurementType) -> Self {
+        MeasurementPolicy {
+            default,
+            trains: Default::default(),
+            stations: Default::default(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum DelayCostType {
     FiniteSteps1_3Min,
@@ -17,36 +66,1702 @@ pub enum DelayCostType {
     InfiniteSteps180,
     InfiniteSteps360,
     Continuous,
+    /// A user-defined cost function. Custom functions are interned (see
+    /// [`DelayCostFunction::intern`]) so that `DelayCostType` stays `Copy`
+    /// and can be passed by value to every solver.
+    Custom(&'static DelayCostFunction),
+    /// The per-visit penalties stored in the instance (see
+    /// [`Train::visit_costs`]), e.g. the `PiecewiseLinear` objective of the
+    /// XML instances. Visits without a penalty cost nothing.
+    PiecewiseLinear,
+}
+
+impl DelayCostType {
+    /// Parses an objective name as accepted on the command line.
+    ///
+    /// Besides the built-in names (`finsteps123`, `infsteps180`, `cont`, ...),
+    /// `custom:<spec>` loads a [`DelayCostFunction`] from `<spec>`, see
+    /// [`DelayCostFunction::parse`].
+    pub fn from_name(value: &str) -> Result<DelayCostType, String> {
+        if let Some(spec) = value.strip_prefix("custom:") {
+            return DelayCostFunction::parse(spec)
+                .map(DelayCostFunction::intern)
+                .map_err(|e| format!("invalid custom delay cost function '{}': {}", spec, e));
+        }
+
+        let key = value.to_ascii_lowercase();
+        match key.as_str() {
+            "finsteps1_5min" => Ok(DelayCostType::FiniteSteps1_5Min),
+            "finsteps1_3min" => Ok(DelayCostType::FiniteSteps1_3Min),
+            "finsteps123" => Ok(DelayCostType::FiniteSteps123),
+            "finsteps12345" => Ok(DelayCostType::FiniteSteps12345),
+            "finsteps139" => Ok(DelayCostType::FiniteSteps139),
+            "infsteps60" => Ok(DelayCostType::InfiniteSteps60),
+            "infsteps180" => Ok(DelayCostType::InfiniteSteps180),
+            "infsteps360" => Ok(DelayCostType::InfiniteSteps360),
+            // Alias: "infsteps123" means extending finsteps123 (1/2/3 at 180s steps) without cap.
+            "infsteps123" => Ok(DelayCostType::InfiniteSteps180),
+            "cont" => Ok(DelayCostType::Continuous),
+            "piecewise" => Ok(DelayCostType::PiecewiseLinear),
+            _ => Err(format!("unknown objective type '{}'", value)),
+        }
+    }
+}
+
+/// An upper bound on the cost of a schedule under `cost_type`, which a
+/// solver keeps as a hard constraint while it optimises another cost type
+/// (see [`solve_lexicographic`](crate::solvers::util::lexicographic::solve_lexicographic)).
+#[derive(Debug, Clone, Copy)]
+pub struct CostBound {
+    pub cost_type: DelayCostType,
+    pub cost: i32,
+}
+
+/// A piecewise delay cost function built from three kinds of pieces whose
+/// costs are summed:
+///
+///  * `steps`: a finite staircase in the same `(threshold, cost)` form as
+///    [`DelayCostThresholds`], i.e. a delay `> threshold` costs at least `cost`.
+///  * `repeat`: an unbounded staircase adding `increment` for every started
+///    `interval` seconds of delay beyond `start`.
+///  * `linear`: ramps costing `rate` per second of delay beyond `from`.
+///
+/// All pieces are non-decreasing in the delay, and the sum is convex in the
+/// linear part, so it can be encoded exactly by both the DDD ladders and the
+/// MILP formulations.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DelayCostFunction {
+    #[serde(default)]
+    pub steps: Vec<(i32, usize)>,
+    #[serde(default)]
+    pub repeat: Option<RepeatingSteps>,
+    #[serde(default)]
+    pub linear: Vec<LinearSegment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RepeatingSteps {
+    pub start: i32,
+    pub interval: i32,
+    pub increment: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LinearSegment {
+    pub from: i32,
+    pub rate: usize,
+}
+
+impl DelayCostFunction {
+    /// Parses a cost function specification. Accepted forms:
+    ///
+    ///  * inline JSON, e.g. `{"steps":[[0,1],[180,2]],"linear":[{"from":600,"rate":1}]}`,
+    ///  * a path to a `.json` file containing the same,
+    ///  * a path to a `.toml` file with the same fields, e.g.
+    ///    `steps = [[0, 1], [180, 2]]` and
+    ///    `repeat = { start = 360, interval = 180, increment = 1 }`,
+    ///  * a compact list of `;`-separated pieces:
+    ///    `step@<threshold>=<cost>`, `repeat@<start>/<interval>=<increment>`
+    ///    and `linear@<from>=<rate>`, e.g. `step@0=1;step@180=2;repeat@360/180=1`.
+    pub fn parse(spec: &str) -> Result<DelayCostFunction, String> {
+        let spec = spec.trim();
+        let function = if spec.starts_with('{') {
+            serde_json::from_str(spec).map_err(|e| e.to_string())?
+        } else if spec.ends_with(".json") {
+            let contents = std::fs::read_to_string(spec).map_err(|e| e.to_string())?;
+            serde_json::from_str(&contents).map_err(|e| e.to_string())?
+        } else if spec.ends_with(".toml") {
+            let contents = std::fs::read_to_string(spec).map_err(|e| e.to_string())?;
+            toml::from_str(&contents).map_err(|e| e.to_string())?
+        } else {
+            Self::parse_compact(spec)?
+        };
+        function.normalized()
+    }
+
+    fn parse_compact(spec: &str) -> Result<DelayCostFunction, String> {
+        fn num<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+            s.trim()
+                .parse::<T>()
+                .map_err(|_| format!("expected a number, found '{}'", s))
+        }
+
+        let mut function = DelayCostFunction::default();
+        for piece in spec.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let (kind, rest) = piece
+                .split_once('@')
+                .ok_or_else(|| format!("expected <kind>@..., found '{}'", piece))?;
+            let (args, value) = rest
+                .split_once('=')
+                .ok_or_else(|| format!("expected '=' in '{}'", piece))?;
+            match kind {
+                "step" => function.steps.push((num(args)?, num(value)?)),
+                "repeat" => {
+                    if function.repeat.is_some() {
+                        return Err("only one repeat piece is allowed".to_string());
+                    }
+                    let (start, interval) = args
+                        .split_once('/')
+                        .ok_or_else(|| format!("expected <start>/<interval> in '{}'", piece))?;
+                    function.repeat = Some(RepeatingSteps {
+                        start: num(start)?,
+                        interval: num(interval)?,
+                        increment: num(value)?,
+                    });
+                }
+                "linear" => function.linear.push(LinearSegment {
+                    from: num(args)?,
+                    rate: num(value)?,
+                }),
+                _ => return Err(format!("unknown piece kind '{}'", kind)),
+            }
+        }
+        Ok(function)
+    }
+
+    /// Sorts the staircase into the descending order used by
+    /// [`DelayCostThresholds`] and checks that the function is non-decreasing.
+    fn normalized(mut self) -> Result<DelayCostFunction, String> {
+        self.steps.sort_by_key(|(threshold, _)| -threshold);
+        self.steps.dedup_by_key(|(threshold, _)| *threshold);
+        for pair in self.steps.windows(2) {
+            if pair[0].1 <= pair[1].1 {
+                return Err(format!(
+                    "step costs must increase with the threshold ({:?} vs. {:?})",
+                    pair[1], pair[0]
+                ));
+            }
+        }
+        if let Some(repeat) = self.repeat {
+            if repeat.interval <= 0 || repeat.increment == 0 {
+                return Err("repeat interval and increment must be positive".to_string());
+            }
+        }
+        if self.steps.is_empty() && self.repeat.is_none() && self.linear.is_empty() {
+            return Err("the cost function has no pieces".to_string());
+        }
+        Ok(self)
+    }
+
+    /// The cost type of this function. Each distinct function is allocated
+    /// once for the whole run and shared by later calls with an equal one.
+    pub fn intern(self) -> DelayCostType {
+        static INTERNED: std::sync::Mutex<Vec<&'static DelayCostFunction>> =
+            std::sync::Mutex::new(Vec::new());
+        let mut interned = INTERNED.lock().unwrap();
+        let function = match interned.iter().find(|f| ***f == self) {
+            Some(f) => *f,
+            None => {
+                let f: &'static DelayCostFunction = Box::leak(Box::new(self));
+                interned.push(f);
+                f
+            }
+        };
+        DelayCostType::Custom(function)
+    }
+
+    pub fn thresholds(&self) -> DelayCostThresholds {
+        DelayCostThresholds {
+            thresholds: self.steps.clone(),
+        }
+    }
+
+    /// The cost of the finite staircase alone.
+    fn step_cost(&self, delay: i32) -> usize {
+        self.steps
+            .iter()
+            .find(|(threshold, _)| delay > *threshold)
+            .map(|(_, c)| *c)
+            .unwrap_or(0)
+    }
+
+    pub fn eval(&self, delay: i32) -> usize {
+        let mut cost = self.step_cost(delay);
+        if let Some(repeat) = self.repeat {
+            if delay > repeat.start {
+                cost += repeat.increment
+                    * infinite_staircase(delay - repeat.start, repeat.interval as usize);
+            }
+        }
+        for segment in self.linear.iter() {
+            cost += segment.rate * (delay - segment.from).max(0) as usize;
+        }
+        cost
+    }
+
+    /// Returns the delays at which the cost function steps up. Linear pieces
+    /// increase at every second and are not listed (like `Continuous`).
+    pub fn step_delays(&self, max_repeats: usize) -> Vec<i32> {
+        let mut delays = self.thresholds().step_delays();
+        if let Some(repeat) = self.repeat {
+            delays.extend(
+                (0..max_repeats as i32).map(|k| repeat.start + k * repeat.interval + 1),
+            );
+        }
+        delays.sort();
+        delays.dedup();
+        delays
+    }
+
+    pub fn max_cost(&self) -> Option<usize> {
+        (self.repeat.is_none() && self.linear.is_empty())
+            .then(|| self.steps.iter().map(|(_, c)| *c).max().unwrap_or(0))
+    }
+}
+
+/// A piecewise-linear delay penalty for one visit, as given by the
+/// `DelayPenalty` segments of the XML instances. The delay is measured
+/// against `aimed`. No penalty applies before the first segment, and the
+/// last segment extends to any larger delay.
+///
+/// Penalties are rounded to the nearest integer (halves down) so that they
+/// can be used as weights by the DDD solvers; the MILP objectives round the
+/// same way.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PiecewiseLinearCost {
+    pub aimed: i32,
+    pub segments: Vec<PiecewiseSegment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PiecewiseSegment {
+    pub from: i32,
+    pub to: i32,
+    pub from_value: f64,
+    pub slope: f64,
+}
+
+impl PiecewiseSegment {
+    fn value(&self, delay: i32) -> f64 {
+        self.from_value + self.slope * (delay - self.from) as f64
+    }
+}
+
+impl PiecewiseLinearCost {
+    /// Sorts the segments, fills gaps between them with flat segments and
+    /// checks that the penalty never decreases.
+    pub fn new(aimed: i32, mut segments: Vec<PiecewiseSegment>) -> Result<Self, String> {
+        segments.sort_by_key(|s| s.from);
+        let mut contiguous: Vec<PiecewiseSegment> = Vec::new();
+        for segment in segments {
+            if segment.to <= segment.from || segment.from_value < 0.0 || segment.slope < 0.0 {
+                return Err(format!("invalid penalty segment {:?}", segment));
+            }
+            if let Some(prev) = contiguous.last().copied() {
+                if segment.from < prev.to {
+                    return Err(format!("overlapping penalty segments {:?} and {:?}", prev, segment));
+                }
+                let prev_end = prev.value(prev.to);
+                if segment.from_value < prev_end {
+                    return Err(format!("decreasing penalty at delay {}", segment.from));
+                }
+                if segment.from > prev.to {
+                    contiguous.push(PiecewiseSegment {
+                        from: prev.to,
+                        to: segment.from,
+                        from_value: prev_end,
+                        slope: 0.0,
+                    });
+                }
+            }
+            contiguous.push(segment);
+        }
+        if contiguous.is_empty() {
+            return Err("no penalty segments".to_string());
+        }
+        Ok(PiecewiseLinearCost {
+            aimed,
+            segments: contiguous,
+        })
+    }
+
+    /// The unrounded penalty of entering the visit at time `t`.
+    pub fn value(&self, t: i32) -> f64 {
+        let delay = t - self.aimed;
+        self.segments
+            .iter()
+            .rev()
+            .find(|s| delay >= s.from)
+            .map(|s| s.value(delay))
+            .unwrap_or(0.0)
+    }
+
+    pub fn eval(&self, t: i32) -> usize {
+        (self.value(t) - 0.5).ceil().max(0.0) as usize
+    }
+
+    /// The times at which a segment starts, where the penalty may jump.
+    pub fn threshold_times(&self) -> impl Iterator<Item = i32> + '_ {
+        self.segments.iter().map(|s| self.aimed + s.from)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NamedProblem {
+    pub problem: Problem,
+    pub train_names: Vec<String>,
+    pub resource_names: Vec<String>,
+}
+
+impl NamedProblem {
+    /// Sets the priority weight of each train listed in `weights` (by train
+    /// name). Returns the names that did not match any train.
+    pub fn apply_train_weights(
+        &mut self,
+        weights: &std::collections::HashMap<String, usize>,
+    ) -> Vec<String> {
+        let mut unknown = Vec::new();
+        for (name, weight) in weights.iter() {
+            match self.train_names.iter().position(|n| n == name) {
+                Some(train_idx) => self.problem.trains[train_idx].weight = *weight,
+                None => unknown.push(name.clone()),
+            }
+        }
+        unknown
+    }
+
+    /// Sets the cancellation cost of each train listed in `costs` (by train
+    /// name). Returns the names that did not match any train.
+    pub fn apply_cancellation_costs(
+        &mut self,
+        costs: &std::collections::HashMap<String, i32>,
+    ) -> Vec<String> {
+        let mut unknown = Vec::new();
+        for (name, cost) in costs.iter() {
+            match self.train_names.iter().position(|n| n == name) {
+                Some(train_idx) => self.problem.trains[train_idx].cancellation_cost = Some(*cost),
+                None => unknown.push(name.clone()),
+            }
+        }
+        unknown
+    }
+
+    /// See [`Problem::subproblem`].
+    pub fn subproblem(&self, trains: &[usize]) -> NamedProblem {
+        NamedProblem {
+            problem: self.problem.subproblem(trains),
+            train_names: trains.iter().map(|t| self.train_names[*t].clone()).collect(),
+            resource_names: self.resource_names.clone(),
+        }
+    }
+
+    /// Checks the instance for inconsistencies (see [`Problem::validate`]),
+    /// including resources that have no name, and describes each issue
+    /// using the train and resource names.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let n_resources = self.resource_names.len();
+        let mut issues = self.problem.validate();
+        for (train_idx, train) in self.problem.trains.iter().enumerate() {
+            for (visit_idx, visit) in train.visits.iter().enumerate() {
+                if visit.resource_id >= n_resources {
+                    issues.push(Issue::UnknownResource {
+                        train_idx,
+                        visit_idx,
+                        resource_id: visit.resource_id,
+                    });
+                }
+            }
+        }
+        for (r1, r2) in self.problem.conflicts.iter() {
+            for resource_id in [r1, r2] {
+                if resource_id >= n_resources {
+                    issues.push(Issue::UnknownConflictResource { resource_id });
+                }
+            }
+        }
+        for (blocked_idx, blocked) in self.problem.blocked.iter().enumerate() {
+            if blocked.resource_id >= n_resources {
+                issues.push(Issue::InvalidBlockedInterval { blocked_idx });
+            }
+        }
+
+        issues
+            .into_iter()
+            .map(|issue| Diagnostic {
+                message: self.describe(&issue),
+                issue,
+            })
+            .collect()
+    }
+
+    fn describe(&self, issue: &Issue) -> String {
+        let train = |train_idx: usize| {
+            self.train_names
+                .get(train_idx)
+                .map(|n| format!("train {}", n))
+                .unwrap_or_else(|| format!("train #{}", train_idx))
+        };
+        let resource = |resource_id: usize| {
+            self.resource_names
+                .get(resource_id)
+                .map(|n| format!("resource {}", n))
+                .unwrap_or_else(|| format!("resource #{}", resource_id))
+        };
+        let visit = |train_idx: usize, visit_idx: usize| {
+            let resource_id = self.problem.trains[train_idx].visits[visit_idx].resource_id;
+            format!(
+                "{} visit {} ({})",
+                train(train_idx),
+                visit_idx,
+                resource(resource_id)
+            )
+        };
+
+        match *issue {
+            Issue::EmptyTrain { train_idx } => format!("{} has no visits", train(train_idx)),
+            Issue::NegativeTravelTime {
+                train_idx,
+                visit_idx,
+                travel_time,
+            } => format!(
+                "{} has negative travel time {}",
+                visit(train_idx, visit_idx),
+                travel_time
+            ),
+            Issue::InvalidMaxTravelTime {
+                train_idx,
+                visit_idx,
+                max_travel_time,
+            } => format!(
+                "{} has maximum travel time {} below its travel time",
+                visit(train_idx, visit_idx),
+                max_travel_time
+            ),
+            Issue::InconsistentEarliest {
+                train_idx,
+                visit_idx,
+                earliest,
+                reachable,
+            } => format!(
+                "{} has earliest {}, but cannot be reached before {}",
+                visit(train_idx, visit_idx),
+                earliest,
+                reachable
+            ),
+            Issue::AimedBeforeEarliest {
+                train_idx,
+                visit_idx,
+                aimed,
+                earliest,
+            } => format!(
+                "{} has aimed time {} before its earliest time {}",
+                visit(train_idx, visit_idx),
+                aimed,
+                earliest
+            ),
+            Issue::MissingAimed {
+                train_idx,
+                visit_idx,
+            } => format!(
+                "{} may not depart early, but has no aimed time",
+                visit(train_idx, visit_idx)
+            ),
+            Issue::UnknownResource {
+                train_idx,
+                visit_idx,
+                resource_id,
+            } => format!(
+                "{} visit {} uses unknown resource #{}",
+                train(train_idx),
+                visit_idx,
+                resource_id
+            ),
+            Issue::UnknownConflictResource { resource_id } => {
+                format!("conflicts refer to unknown resource #{}", resource_id)
+            }
+            Issue::InvalidCapacity {
+                resource_id,
+                capacity,
+            } => format!(
+                "{} has capacity {}, which must be positive and not combined with conflicts",
+                resource(resource_id),
+                capacity
+            ),
+            Issue::InvalidConnection { connection_idx } => {
+                let connection = &self.problem.connections[connection_idx];
+                format!(
+                    "connection #{} from {} visit {} to {} visit {} is invalid",
+                    connection_idx,
+                    train(connection.from.0),
+                    connection.from.1,
+                    train(connection.to.0),
+                    connection.to.1
+                )
+            }
+            Issue::InvalidTurnaround { turnaround_idx } => {
+                let turnaround = &self.problem.turnarounds[turnaround_idx];
+                format!(
+                    "turnaround #{} from {} to {} is invalid",
+                    turnaround_idx,
+                    train(turnaround.from),
+                    train(turnaround.to)
+                )
+            }
+            Issue::NegativeCancellationCost { train_idx, cost } => {
+                format!("{} has negative cancellation cost {}", train(train_idx), cost)
+            }
+            Issue::NegativeRunningTimeCost { train_idx, cost } => {
+                format!("{} has negative running-time cost {}", train(train_idx), cost)
+            }
+            Issue::NegativeEarlinessCost { train_idx, cost } => {
+                format!("{} has negative earliness cost {}", train(train_idx), cost)
+            }
+            Issue::InvalidBlockedInterval { blocked_idx } => {
+                let blocked = &self.problem.blocked[blocked_idx];
+                format!(
+                    "blocked interval #{} of {} from {} to {} is invalid",
+                    blocked_idx,
+                    resource(blocked.resource_id),
+                    blocked.from,
+                    blocked.to
+                )
+            }
+            Issue::InvalidOrder { order_idx } => {
+                let order = &self.problem.orders[order_idx];
+                format!(
+                    "order #{} of {} on {} is invalid",
+                    order_idx,
+                    order
+                        .trains
+                        .iter()
+                        .map(|t| train(*t))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    resource(order.resource_id)
+                )
+            }
+        }
+    }
+}
+
+/// An inconsistency in a problem instance, found by [`Problem::validate`]
+/// or [`NamedProblem::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Issue {
+    EmptyTrain {
+        train_idx: usize,
+    },
+    NegativeTravelTime {
+        train_idx: usize,
+        visit_idx: usize,
+        travel_time: i32,
+    },
+    /// The visit's `max_travel_time` is below its minimum travel time.
+    InvalidMaxTravelTime {
+        train_idx: usize,
+        visit_idx: usize,
+        max_travel_time: i32,
+    },
+    /// The visit's `earliest` is before the time the train can get there
+    /// from its previous visit. Fixed by [`Problem::normalize`].
+    InconsistentEarliest {
+        train_idx: usize,
+        visit_idx: usize,
+        earliest: i32,
+        reachable: i32,
+    },
+    /// Harmless, but the visit can never be on time.
+    AimedBeforeEarliest {
+        train_idx: usize,
+        visit_idx: usize,
+        aimed: i32,
+        earliest: i32,
+    },
+    /// The visit has `no_early_departure`, but no aimed time to depart at.
+    MissingAimed {
+        train_idx: usize,
+        visit_idx: usize,
+    },
+    UnknownResource {
+        train_idx: usize,
+        visit_idx: usize,
+        resource_id: usize,
+    },
+    UnknownConflictResource {
+        resource_id: usize,
+    },
+    /// A resource has capacity zero, or has a capacity and is also in the
+    /// conflict graph.
+    InvalidCapacity {
+        resource_id: usize,
+        capacity: usize,
+    },
+    /// A connection refers to an unknown visit, connects a train to
+    /// itself, or has a negative penalty.
+    InvalidConnection {
+        connection_idx: usize,
+    },
+    /// A turnaround refers to an unknown or empty train, links a train to
+    /// itself, or links a train that may be cancelled.
+    InvalidTurnaround {
+        turnaround_idx: usize,
+    },
+    NegativeCancellationCost {
+        train_idx: usize,
+        cost: i32,
+    },
+    NegativeRunningTimeCost {
+        train_idx: usize,
+        cost: i32,
+    },
+    NegativeEarlinessCost {
+        train_idx: usize,
+        cost: i32,
+    },
+    /// A blocked interval is empty or refers to an unknown resource.
+    InvalidBlockedInterval {
+        blocked_idx: usize,
+    },
+    /// A fixed order has a train more than once, or a train that does not
+    /// visit its resource.
+    InvalidOrder {
+        order_idx: usize,
+    },
+}
+
+impl Issue {
+    /// Whether solvers can be expected to work on the instance despite the
+    /// issue. Most solvers propagate earliest times along the trains
+    /// themselves, so inconsistent earliest times are only a warning.
+    pub fn is_warning(&self) -> bool {
+        matches!(
+            self,
+            Issue::AimedBeforeEarliest { .. }
+                | Issue::InconsistentEarliest { .. }
+                | Issue::MissingAimed { .. }
+        )
+    }
+}
+
+/// An [`Issue`] with a description using the instance's names.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub issue: Issue,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = if self.issue.is_warning() { "warning" } else { "error" };
+        write!(f, "{}: {}", level, self.message)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Problem {
+    pub name: String,
+    pub trains: Vec<Train>,
+    pub conflicts: ConflictGraph,
+    #[serde(default)]
+    pub headways: Headways,
+    /// Cumulative resources, such as a station with several platforms, and
+    /// the number of trains each can hold at the same time. These resources
+    /// should not be in `conflicts`, and headways do not apply to them.
+    /// The Big-M and hull MILPs and the DDD solvers `maxsat_ladder_sc`,
+    /// `incremental_sat`, `puresat`, `maxsat_ladder`, `maxsat_rc2` and
+    /// `maxsat_ladder_abstract` support them; the other solvers ignore them
+    /// and are not run on such instances.
+    #[serde(default)]
+    pub capacities: std::collections::HashMap<usize, usize>,
+    #[serde(default)]
+    pub connections: Vec<Connection>,
+    #[serde(default)]
+    pub turnarounds: Vec<Turnaround>,
+    #[serde(default)]
+    pub blocked: Vec<BlockedInterval>,
+    #[serde(default)]
+    pub orders: Vec<TrainOrder>,
+}
+
+/// A time window in which a resource cannot be used, e.g. a track closed
+/// for maintenance. It acts as a fixed occupation of the resource from
+/// `from` to `to`, which no visit to the resource itself or to a resource
+/// in conflict with it may overlap. Headways do not apply around it: the
+/// window is taken to already include any clearance time the closure
+/// needs, and a headway is defined between two trains rather than between
+/// a train and the infrastructure.
+///
+/// The Big-M and hull MILPs and the DDD solvers `maxsat_ladder_sc`,
+/// `incremental_sat`, `puresat`, `maxsat_ladder`, `maxsat_rc2` and
+/// `maxsat_ladder_abstract` support blocked intervals; the other solvers
+/// ignore them and are not run on such instances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BlockedInterval {
+    pub resource_id: usize,
+    pub from: i32,
+    pub to: i32,
+}
+
+/// Trains that must pass a resource in the given order, e.g. on a single
+/// track without passing loops. Each train may only enter its (first) visit
+/// to the resource once the train before it has left its visit and the
+/// headway between them has passed. An order of two trains is a fixed
+/// precedence between a pair of trains.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TrainOrder {
+    pub resource_id: usize,
+    pub trains: Vec<usize>,
+}
+
+/// A delay forecast: each `(train_idx, visit_idx, delay)` raises the
+/// visit's `earliest` time by `delay`. The `weight` is the relative
+/// likelihood of the scenario (see
+/// [`solvers::util::scenarios`](crate::solvers::util::scenarios)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DelayScenario {
+    pub name: String,
+    pub weight: usize,
+    pub delays: Vec<(usize, usize, i32)>,
+}
+
+impl DelayScenario {
+    /// The instance as it is in this scenario.
+    pub fn apply(&self, problem: &Problem) -> Problem {
+        let mut problem = problem.clone();
+        for &(train_idx, visit_idx, delay) in self.delays.iter() {
+            problem.trains[train_idx].visits[visit_idx].earliest += delay;
+        }
+        problem
+    }
+}
+
+/// A trainset that runs train `to` after train `from`: `to` cannot start
+/// its first visit until `time` after `from` has left its last visit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Turnaround {
+    pub from: usize,
+    pub to: usize,
+    pub time: i32,
+}
+
+/// What to maximise about the buffers of a schedule (see
+/// [`Problem::buffer_stats`]) among the schedules with the optimal delay
+/// cost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferKind {
+    /// The smallest buffer.
+    Min,
+    /// The sum of the buffers.
+    Total,
+}
+
+impl BufferKind {
+    pub fn from_name(value: &str) -> Option<BufferKind> {
+        match value {
+            "min" => Some(BufferKind::Min),
+            "total" => Some(BufferKind::Total),
+            _ => None,
+        }
+    }
+}
+
+/// A secondary objective on the buffers of a schedule. Buffers count up to
+/// `cap`, beyond which a delay is assumed not to reach the next train.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferObjective {
+    pub kind: BufferKind,
+    pub cap: i32,
+}
+
+/// The buffers between the occupations of conflicting resources in a
+/// schedule, see [`Problem::buffer_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct BufferStats {
+    /// The number of pairs of visits to conflicting resources.
+    pub pairs: usize,
+    /// The smallest buffer, if there are any pairs.
+    pub min: Option<i32>,
+    /// The number of pairs without any buffer.
+    pub zero: usize,
+    /// The sum of the buffers, each counting up to `cap`.
+    pub total: i64,
+    pub cap: i32,
+}
+
+impl BufferStats {
+    /// The value of a buffer objective with this stats' `cap`.
+    pub fn value(&self, kind: BufferKind) -> i64 {
+        match kind {
+            BufferKind::Min => self.min.map_or(self.cap, |min| min.min(self.cap)) as i64,
+            BufferKind::Total => self.total,
+        }
+    }
+}
+
+/// The part of a problem that is still open at a given time, see
+/// [`Problem::freeze_before`].
+#[derive(Debug, Clone)]
+pub struct FrozenProblem {
+    pub problem: Problem,
+    /// The index of each train of `problem` in the original problem.
+    pub trains: Vec<usize>,
+    /// The number of leading visits of each train of `problem` that were
+    /// dropped from the original train.
+    pub dropped_visits: Vec<usize>,
+}
+
+impl FrozenProblem {
+    /// A solution of the original problem, from the realised times that
+    /// were frozen and a solution of the frozen problem.
+    pub fn expand_solution(
+        &self,
+        realised_times: &[Vec<i32>],
+        solution: &[Vec<i32>],
+    ) -> Vec<Vec<i32>> {
+        let mut expanded = realised_times.to_vec();
+        for (train_idx, times) in solution.iter().enumerate() {
+            let original = self.trains[train_idx];
+            expanded[original] = if times.is_empty() {
+                Vec::new()
+            } else {
+                let dropped = &realised_times[original][..self.dropped_visits[train_idx]];
+                dropped.iter().chain(times.iter()).copied().collect()
+            };
+        }
+        expanded
+    }
 }
 
-#[derive(Debug)]
-pub struct NamedProblem {
-    pub problem: Problem,
-    pub train_names: Vec<String>,
-    pub resource_names: Vec<String>,
-}
+/// A planned passenger transfer: the visit `to` may not start until
+/// `min_transfer` after the visit `from` has started. Usually `from` is the
+/// arriving train's visit to the station and `to` is the departing train's
+/// visit after the station, so that it leaves the station only once the
+/// passengers are on board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Connection {
+    /// `(train_idx, visit_idx)` of the arriving train.
+    pub from: (usize, usize),
+    /// `(train_idx, visit_idx)` of the departing train.
+    pub to: (usize, usize),
+    pub min_transfer: i32,
+    /// The cost of breaking the connection. Without a penalty the
+    /// connection must be kept.
+    #[serde(default)]
+    pub penalty: Option<i32>,
+}
+
+impl Connection {
+    pub fn is_kept(&self, solution: &[Vec<i32>]) -> bool {
+        let ((t1, v1), (t2, v2)) = (self.from, self.to);
+        solution[t2][v2] >= solution[t1][v1] + self.min_transfer
+    }
+}
+
+/// Minimum time between one train leaving a resource and the next train
+/// entering it (or a conflicting resource), including route release and
+/// clearing times.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Headway {
+    pub same_direction: i32,
+    pub opposite_direction: i32,
+}
+
+impl Headway {
+    pub fn max(self, other: Headway) -> Headway {
+        Headway {
+            same_direction: self.same_direction.max(other.same_direction),
+            opposite_direction: self.opposite_direction.max(other.opposite_direction),
+        }
+    }
+}
+
+/// Headways between occupations of conflicting resources. A resource pair
+/// entry takes precedence over the entries of the two resources, which take
+/// precedence over the default. Without any entries, occupations only need
+/// to not overlap.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Headways {
+    pub default: Headway,
+    pub resources: std::collections::HashMap<usize, Headway>,
+    /// Keyed by `(min, max)` resource id, see [`Headways::insert_pair`].
+    /// Serialized as a list of `[[r1, r2], headway]` entries.
+    #[serde(with = "headway_pairs")]
+    pub pairs: std::collections::HashMap<(usize, usize), Headway>,
+}
+
+mod headway_pairs {
+    use super::Headway;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    pub fn serialize<S: Serializer>(
+        pairs: &HashMap<(usize, usize), Headway>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries = pairs.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        entries.sort_by_key(|(k, _)| *k);
+        entries.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<(usize, usize), Headway>, D::Error> {
+        let entries = Vec::<((usize, usize), Headway)>::deserialize(deserializer)?;
+        Ok(entries
+            .into_iter()
+            .map(|((r1, r2), headway)| ((r1.min(r2), r1.max(r2)), headway))
+            .collect())
+    }
+}
+
+impl Headways {
+    pub fn is_empty(&self) -> bool {
+        self.default == Headway::default() && self.resources.is_empty() && self.pairs.is_empty()
+    }
+
+    pub fn insert_pair(&mut self, r1: usize, r2: usize, headway: Headway) {
+        self.pairs.insert((r1.min(r2), r1.max(r2)), headway);
+    }
+
+    pub fn get(&self, r1: usize, r2: usize) -> Headway {
+        if let Some(headway) = self.pairs.get(&(r1.min(r2), r1.max(r2))) {
+            return *headway;
+        }
+        let resource = |r: usize| self.resources.get(&r).copied().unwrap_or(self.default);
+        resource(r1).max(resource(r2))
+    }
+
+    /// The largest headway required between the two resources in any direction.
+    pub fn max_headway(&self, r1: usize, r2: usize) -> i32 {
+        let headway = self.get(r1, r2);
+        headway.same_direction.max(headway.opposite_direction)
+    }
+}
+
+/// Which resources cannot be occupied at the same time. The relation is
+/// symmetric: inserting `(a, b)` also makes `b` conflict with `a`.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(from = "Vec<(usize, usize)>", into = "Vec<(usize, usize)>")]
+pub struct ConflictGraph {
+    /// Each conflicting pair once, as `(min, max)`, in insertion order.
+    pairs: Vec<(usize, usize)>,
+    set: std::collections::HashSet<(usize, usize)>,
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl ConflictGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, a: usize, b: usize) {
+        let pair = (a.min(b), a.max(b));
+        if !self.set.insert(pair) {
+            return;
+        }
+        self.pairs.push(pair);
+        if self.adjacency.len() <= pair.1 {
+            self.adjacency.resize(pair.1 + 1, Vec::new());
+        }
+        self.adjacency[a].push(b);
+        if a != b {
+            self.adjacency[b].push(a);
+        }
+    }
+
+    pub fn contains(&self, a: usize, b: usize) -> bool {
+        self.set.contains(&(a.min(b), a.max(b)))
+    }
+
+    /// The resources conflicting with `resource` (including itself, if it
+    /// conflicts with itself).
+    pub fn neighbours(&self, resource: usize) -> &[usize] {
+        self.adjacency
+            .get(resource)
+            .map(|rs| rs.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Iterates over the conflicting pairs, each once as `(min, max)`.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.pairs.iter().copied()
+    }
+
+    /// The number of conflicting pairs.
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+impl From<Vec<(usize, usize)>> for ConflictGraph {
+    fn from(pairs: Vec<(usize, usize)>) -> Self {
+        pairs.into_iter().collect()
+    }
+}
+
+impl From<ConflictGraph> for Vec<(usize, usize)> {
+    fn from(graph: ConflictGraph) -> Self {
+        graph.pairs
+    }
+}
+
+impl FromIterator<(usize, usize)> for ConflictGraph {
+    fn from_iter<I: IntoIterator<Item = (usize, usize)>>(iter: I) -> Self {
+        let mut graph = Self::new();
+        for (a, b) in iter {
+            graph.insert(a, b);
+        }
+        graph
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Train {
+    pub visits: Vec<Visit>,
+    /// Priority weight multiplying every delay cost of this train (default 1).
+    #[serde(default = "default_weight")]
+    pub weight: usize,
+    /// Per-visit penalties used by [`DelayCostType::PiecewiseLinear`]. Either
+    /// empty or one entry per visit.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub visit_costs: Vec<Option<PiecewiseLinearCost>>,
+    /// If set, the train may be cancelled at this cost instead of being
+    /// scheduled. A cancelled train has no times in the solution, occupies
+    /// no resources and has no delay cost.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cancellation_cost: Option<i32>,
+    /// Cost per time unit that the train takes longer than the minimum
+    /// running time on the visits that have a `max_travel_time`, for the
+    /// energy and comfort of running slower. Added to the delay cost.
+    /// Only the Big-M and hull MILPs optimise this cost; the DDD solvers
+    /// take `max_travel_time` as a hard bound only and are not run on
+    /// instances with running-time costs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub running_time_cost: Option<i32>,
+    /// Cost per time unit that a visit is entered before its aimed time,
+    /// e.g. for freight that should not run early. Multiplied by the weight
+    /// and added to the delay cost. Only the Big-M and hull MILPs,
+    /// `maxsat_ladder_sc` and `incremental_sat` optimise this cost; the other
+    /// solvers are not run on instances with earliness costs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub earliness_cost: Option<i32>,
+}
+
+fn default_weight() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct Visit {
+    pub resource_id: usize,
+    pub earliest: i32,
+    #[serde(default)]
+    pub aimed: Option<i32>,
+    /// Hard deadline: the visit must be entered no later than this time.
+    #[serde(default)]
+    pub latest: Option<i32>,
+    pub travel_time: i32,
+    /// The longest the train may take to pass the resource, i.e. the
+    /// minimum running time plus the largest running-time supplement. The
+    /// train cannot wait on the resource beyond this. Without a maximum the
+    /// train may stay on the resource for as long as it needs to. The DDD
+    /// solvers treat the time between the minimum and the maximum as free
+    /// slack, like dwell; see [`Train::running_time_cost`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_travel_time: Option<i32>,
+    /// The visit may not be entered before its aimed time, e.g. a departure
+    /// from a passenger stop before the published time.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_early_departure: bool,
+}
+
+impl Visit {
+    /// The first time at which the visit may be entered: its `earliest`
+    /// time, or its aimed time if that is later and the visit has
+    /// `no_early_departure`.
+    pub fn earliest_start(&self) -> i32 {
+        match self.aimed {
+            Some(aimed) if self.no_early_departure => self.earliest.max(aimed),
+            _ => self.earliest,
+        }
+    }
+
+    /// The first time at which the visit can no longer be entered. This is
+    /// the final (always false) time point of the DDD time ladders.
+    pub fn ladder_end(&self) -> i32 {
+        self.latest.map_or(i32::MAX, |latest| latest + 1)
+    }
+}
+
+impl Problem {
+    /// Whether two visits pass their resources in the same direction, judged
+    /// by the resources the trains come from and continue to. Visits that
+    /// share neither are treated as running in the same direction.
+    pub fn same_direction(&self, (t1, v1): (usize, usize), (t2, v2): (usize, usize)) -> bool {
+        let neighbours = |train_idx: usize, visit_idx: usize| {
+            let visits = &self.trains[train_idx].visits;
+            let prev = visit_idx.checked_sub(1).map(|v| visits[v].resource_id);
+            let next = visits.get(visit_idx + 1).map(|v| v.resource_id);
+            (prev, next)
+        };
+        let (prev1, next1) = neighbours(t1, v1);
+        let (prev2, next2) = neighbours(t2, v2);
+        let same = (prev1.is_some() && prev1 == prev2) || (next1.is_some() && next1 == next2);
+        let opposite = (prev1.is_some() && prev1 == next2) || (next1.is_some() && next1 == prev2);
+        same || !opposite
+    }
+
+    /// The required separation between the occupations of two visits to
+    /// conflicting resources, whichever of them goes first.
+    pub fn headway(&self, a: (usize, usize), b: (usize, usize)) -> i32 {
+        if self.headways.is_empty() {
+            return 0;
+        }
+        let headway = self.headways.get(
+            self.trains[a.0].visits[a.1].resource_id,
+            self.trains[b.0].visits[b.1].resource_id,
+        );
+        if headway.same_direction == headway.opposite_direction || self.same_direction(a, b) {
+            headway.same_direction
+        } else {
+            headway.opposite_direction
+        }
+    }
+
+    /// Groups the trains into independent components: trains in different
+    /// components never visit conflicting resources, so each component can be
+    /// scheduled on its own. Components are ordered by their first train, and
+    /// the trains in each component are in increasing order.
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        fn find(parent: &mut [usize], mut x: usize) -> usize {
+            while parent[x] != x {
+                parent[x] = parent[parent[x]];
+                x = parent[x];
+            }
+            x
+        }
+
+        let mut parent = (0..self.trains.len()).collect::<Vec<_>>();
+        let mut union = |a: usize, b: usize| {
+            let (a, b) = (find(&mut parent, a), find(&mut parent, b));
+            parent[a.max(b)] = a.min(b);
+        };
+
+        let mut resource_trains: std::collections::HashMap<usize, Vec<usize>> = Default::default();
+        for (train_idx, train) in self.trains.iter().enumerate() {
+            for visit in train.visits.iter() {
+                resource_trains
+                    .entry(visit.resource_id)
+                    .or_default()
+                    .push(train_idx);
+            }
+        }
+        for connection in self.connections.iter() {
+            union(connection.from.0, connection.to.0);
+        }
+        for turnaround in self.turnarounds.iter() {
+            union(turnaround.from, turnaround.to);
+        }
+        for order in self.orders.iter() {
+            for pair in order.trains.windows(2) {
+                union(pair[0], pair[1]);
+            }
+        }
+        let cumulative = self.capacities.keys().map(|r| (*r, *r));
+        for (r1, r2) in self.conflicts.iter().chain(cumulative) {
+            let trains = resource_trains
+                .get(&r1)
+                .into_iter()
+                .chain(resource_trains.get(&r2))
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>();
+            for other in trains.iter().skip(1) {
+                union(trains[0], *other);
+            }
+        }
+
+        let mut components: Vec<Vec<usize>> = Vec::new();
+        let mut component_of_root = std::collections::HashMap::new();
+        for train_idx in 0..self.trains.len() {
+            let root = find(&mut parent, train_idx);
+            let idx = *component_of_root.entry(root).or_insert_with(|| {
+                components.push(Vec::new());
+                components.len() - 1
+            });
+            components[idx].push(train_idx);
+        }
+        components
+    }
 
-#[derive(Debug, Clone)]
-pub struct Problem {
-    pub name: String,
-    pub trains: Vec<Train>,
-    pub conflicts: Vec<(usize, usize)>,
-}
+    /// The problem restricted to the given trains, in the given order.
+    /// Resources, conflicts and headways are kept as they are. Connections
+    /// and turnarounds involving trains that are left out are dropped, and
+    /// so are those trains in the fixed orders.
+    pub fn subproblem(&self, trains: &[usize]) -> Problem {
+        let new_idx = |train_idx: usize| trains.iter().position(|t| *t == train_idx);
+        let connections = self
+            .connections
+            .iter()
+            .filter_map(|c| {
+                Some(Connection {
+                    from: (new_idx(c.from.0)?, c.from.1),
+                    to: (new_idx(c.to.0)?, c.to.1),
+                    ..*c
+                })
+            })
+            .collect();
+        let turnarounds = self
+            .turnarounds
+            .iter()
+            .filter_map(|t| {
+                Some(Turnaround {
+                    from: new_idx(t.from)?,
+                    to: new_idx(t.to)?,
+                    time: t.time,
+                })
+            })
+            .collect();
+        let orders = self
+            .orders
+            .iter()
+            .map(|order| TrainOrder {
+                resource_id: order.resource_id,
+                trains: order.trains.iter().filter_map(|t| new_idx(*t)).collect(),
+            })
+            .filter(|order| order.trains.len() >= 2)
+            .collect();
+        Problem {
+            name: self.name.clone(),
+            trains: trains.iter().map(|t| self.trains[*t].clone()).collect(),
+            conflicts: self.conflicts.clone(),
+            headways: self.headways.clone(),
+            capacities: self.capacities.clone(),
+            connections,
+            turnarounds,
+            blocked: self.blocked.clone(),
+            orders,
+        }
+    }
 
-#[derive(Debug, Clone)]
-pub struct Train {
-    pub visits: Vec<Visit>,
-}
+    /// The problem that remains to be solved at time `now`, given the
+    /// `realised_times` of a schedule (in the solution format) of which the
+    /// times before `now` have happened. The rest of `realised_times` is
+    /// ignored, and an empty row means that nothing of the train has happened.
+    ///
+    /// Trains that have left their last visit before `now` are dropped. While
+    /// the headway after such a train has not passed, its last resource
+    /// stays blocked (see [`BlockedInterval`]) until it has, using the
+    /// largest headway to any resource in conflict with it. Of a
+    /// train that is under way, the visits it has already left are dropped
+    /// and the visit it is on is fixed to its realised time, so that its
+    /// ladder has a single time point; such a train can no longer be
+    /// cancelled. No remaining visit can start before `now`. Connections to
+    /// a visit that has started, and turnarounds to a train that has
+    /// started, are dropped. A visit still waits for a hard connection from
+    /// a dropped visit, and a train that follows a finished train still
+    /// waits for its turnaround time, through their earliest times; soft
+    /// connections from a dropped visit are dropped. A train that has left
+    /// its visit to the resource of a fixed order is dropped from the order.
+    /// Blocked intervals that end before `now` are dropped.
+    pub fn freeze_before(&self, now: i32, realised_times: &[Vec<i32>]) -> FrozenProblem {
+        let started = |train_idx: usize| {
+            let n_visits = self.trains[train_idx].visits.len();
+            let times = &realised_times[train_idx];
+            times.iter().take(n_visits + 1).take_while(|t| **t < now).count()
+        };
+        let n_started = (0..self.trains.len()).map(started).collect::<Vec<_>>();
+        let finished =
+            |train_idx: usize| n_started[train_idx] > self.trains[train_idx].visits.len();
 
-#[derive(Debug, Clone, Copy)]
-pub struct Visit {
-    pub resource_id: usize,
-    pub earliest: i32,
-    pub aimed: Option<i32>,
-    pub travel_time: i32,
-}
+        let mut trains = Vec::new();
+        let mut dropped_visits = Vec::new();
+        let mut frozen_trains = Vec::new();
+        for (train_idx, train) in self.trains.iter().enumerate() {
+            if finished(train_idx) {
+                continue;
+            }
+            let n_dropped = n_started[train_idx].saturating_sub(1);
+            let mut frozen = train.clone();
+            frozen.visits.drain(..n_dropped);
+            if !frozen.visit_costs.is_empty() {
+                frozen.visit_costs.drain(..n_dropped);
+            }
+            for visit in frozen.visits.iter_mut() {
+                visit.earliest = visit.earliest.max(now);
+            }
+            if n_started[train_idx] > 0 {
+                let t = realised_times[train_idx][n_dropped];
+                frozen.visits[0].earliest = t;
+                frozen.visits[0].latest = Some(t);
+                frozen.visits[0].no_early_departure = false;
+                frozen.cancellation_cost = None;
+            }
+            trains.push(train_idx);
+            dropped_visits.push(n_dropped);
+            frozen_trains.push(frozen);
+        }
+
+        let new_idx = |train_idx: usize| trains.iter().position(|t| *t == train_idx);
+        let new_visit = |(train_idx, visit_idx): (usize, usize)| {
+            let new_train_idx = new_idx(train_idx)?;
+            let visit_idx = visit_idx.checked_sub(dropped_visits[new_train_idx])?;
+            Some((new_train_idx, visit_idx))
+        };
+        let mut connections = Vec::new();
+        for connection in self.connections.iter() {
+            let Some(to) = new_visit(connection.to) else {
+                continue;
+            };
+            // The visit a train is on has started, so it does not wait.
+            if to.1 == 0 && n_started[connection.to.0] > 0 {
+                continue;
+            }
+            match new_visit(connection.from) {
+                Some(from) => connections.push(Connection {
+                    from,
+                    to,
+                    ..*connection
+                }),
+                None if connection.penalty.is_none() => {
+                    let from_start = realised_times[connection.from.0][connection.from.1];
+                    let to_visit = &mut frozen_trains[to.0].visits[to.1];
+                    to_visit.earliest = to_visit.earliest.max(from_start + connection.min_transfer);
+                }
+                None => {}
+            }
+        }
+        let mut turnarounds = Vec::new();
+        for turnaround in self.turnarounds.iter() {
+            let Some(to) = new_idx(turnaround.to).filter(|_| n_started[turnaround.to] == 0) else {
+                continue;
+            };
+            match new_idx(turnaround.from) {
+                Some(from) => turnarounds.push(Turnaround {
+                    from,
+                    to,
+                    time: turnaround.time,
+                }),
+                None => {
+                    let from_end = *realised_times[turnaround.from].last().unwrap();
+                    let first_visit = &mut frozen_trains[to].visits[0];
+                    first_visit.earliest = first_visit.earliest.max(from_end + turnaround.time);
+                }
+            }
+        }
+
+        let orders = self
+            .orders
+            .iter()
+            .map(|order| TrainOrder {
+                resource_id: order.resource_id,
+                trains: order
+                    .trains
+                    .iter()
+                    .filter_map(|&t| {
+                        let visit_idx = self.visit_to(t, order.resource_id)?;
+                        new_visit((t, visit_idx)).map(|(new_t, _)| new_t)
+                    })
+                    .collect(),
+            })
+            .filter(|order| order.trains.len() >= 2)
+            .collect();
+
+        let mut blocked = self.blocked.iter().filter(|b| b.to > now).copied().collect::<Vec<_>>();
+        for (train_idx, train) in self.trains.iter().enumerate() {
+            let Some(last_visit) = train.visits.last().filter(|_| finished(train_idx)) else {
+                continue;
+            };
+            // Resources that trains may share have no headway.
+            let resource_id = last_visit.resource_id;
+            if !self.conflicts.contains(resource_id, resource_id) {
+                continue;
+            }
+            let headway = self
+                .conflicts
+                .neighbours(resource_id)
+                .iter()
+                .map(|&other| self.headways.max_headway(resource_id, other))
+                .max()
+                .unwrap_or(0);
+            let n_visits = train.visits.len();
+            let from = realised_times[train_idx][n_visits - 1];
+            let to = realised_times[train_idx][n_visits] + headway;
+            if to > now && from < to {
+                blocked.push(BlockedInterval { resource_id, from, to });
+            }
+        }
+
+        FrozenProblem {
+            problem: Problem {
+                name: self.name.clone(),
+                trains: frozen_trains,
+                conflicts: self.conflicts.clone(),
+                headways: self.headways.clone(),
+                capacities: self.capacities.clone(),
+                connections,
+                turnarounds,
+                blocked,
+                orders,
+            },
+            trains,
+            dropped_visits,
+        }
+    }
+
+    /// Checks the trains and visits for inconsistencies that the solvers do
+    /// not expect. See [`NamedProblem::validate`] for a version that also
+    /// checks resources and reports names.
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        for (train_idx, train) in self.trains.iter().enumerate() {
+            if train.visits.is_empty() {
+                issues.push(Issue::EmptyTrain { train_idx });
+            }
+            if let Some(cost) = train.cancellation_cost.filter(|&c| c < 0) {
+                issues.push(Issue::NegativeCancellationCost { train_idx, cost });
+            }
+            if let Some(cost) = train.running_time_cost.filter(|&c| c < 0) {
+                issues.push(Issue::NegativeRunningTimeCost { train_idx, cost });
+            }
+            if let Some(cost) = train.earliness_cost.filter(|&c| c < 0) {
+                issues.push(Issue::NegativeEarlinessCost { train_idx, cost });
+            }
+            let mut reachable: Option<i32> = None;
+            for (visit_idx, visit) in train.visits.iter().enumerate() {
+                if visit.travel_time < 0 {
+                    issues.push(Issue::NegativeTravelTime {
+                        train_idx,
+                        visit_idx,
+                        travel_time: visit.travel_time,
+                    });
+                }
+                if let Some(max_travel_time) =
+                    visit.max_travel_time.filter(|&m| m < visit.travel_time)
+                {
+                    issues.push(Issue::InvalidMaxTravelTime {
+                        train_idx,
+                        visit_idx,
+                        max_travel_time,
+                    });
+                }
+                if let Some(reachable) = reachable.filter(|&r| r > visit.earliest) {
+                    issues.push(Issue::InconsistentEarliest {
+                        train_idx,
+                        visit_idx,
+                        earliest: visit.earliest,
+                        reachable,
+                    });
+                }
+                if let Some(aimed) = visit.aimed.filter(|&a| a < visit.earliest) {
+                    issues.push(Issue::AimedBeforeEarliest {
+                        train_idx,
+                        visit_idx,
+                        aimed,
+                        earliest: visit.earliest,
+                    });
+                }
+                if visit.no_early_departure && visit.aimed.is_none() {
+                    issues.push(Issue::MissingAimed {
+                        train_idx,
+                        visit_idx,
+                    });
+                }
+                reachable = Some(
+                    reachable
+                        .map_or(visit.earliest, |r| r.max(visit.earliest))
+                        .saturating_add(visit.travel_time),
+                );
+            }
+        }
+        for (&resource_id, &capacity) in self.capacities.iter() {
+            if capacity == 0 || !self.conflicts.neighbours(resource_id).is_empty() {
+                issues.push(Issue::InvalidCapacity {
+                    resource_id,
+                    capacity,
+                });
+            }
+        }
+        let is_visit = |(train_idx, visit_idx): (usize, usize)| {
+            self.trains
+                .get(train_idx)
+                .map_or(false, |train| visit_idx < train.visits.len())
+        };
+        for (connection_idx, connection) in self.connections.iter().enumerate() {
+            if !is_visit(connection.from)
+                || !is_visit(connection.to)
+                || connection.from.0 == connection.to.0
+                || connection.penalty.map_or(false, |p| p < 0)
+            {
+                issues.push(Issue::InvalidConnection { connection_idx });
+            }
+        }
+        for (turnaround_idx, turnaround) in self.turnarounds.iter().enumerate() {
+            let has_visits = |train_idx: usize| {
+                self.trains.get(train_idx).map_or(false, |train| {
+                    !train.visits.is_empty() && train.cancellation_cost.is_none()
+                })
+            };
+            if !has_visits(turnaround.from)
+                || !has_visits(turnaround.to)
+                || turnaround.from == turnaround.to
+            {
+                issues.push(Issue::InvalidTurnaround { turnaround_idx });
+            }
+        }
+        for (blocked_idx, blocked) in self.blocked.iter().enumerate() {
+            if blocked.to <= blocked.from {
+                issues.push(Issue::InvalidBlockedInterval { blocked_idx });
+            }
+        }
+        for (order_idx, order) in self.orders.iter().enumerate() {
+            let mut trains = order.trains.clone();
+            trains.sort();
+            trains.dedup();
+            if trains.len() < order.trains.len()
+                || order
+                    .trains
+                    .iter()
+                    .any(|t| self.visit_to(*t, order.resource_id).is_none())
+            {
+                issues.push(Issue::InvalidOrder { order_idx });
+            }
+        }
+        issues
+    }
+
+    /// The turnaround as a hard connection from the last visit of `from` to
+    /// the first visit of `to`.
+    pub fn turnaround_connection(&self, turnaround: &Turnaround) -> Connection {
+        let last = self.trains[turnaround.from].visits.len() - 1;
+        Connection {
+            from: (turnaround.from, last),
+            to: (turnaround.to, 0),
+            min_transfer: self.trains[turnaround.from].visits[last].travel_time
+                + turnaround.time,
+            penalty: None,
+        }
+    }
+
+    /// The first visit of the train to the resource.
+    pub fn visit_to(&self, train_idx: usize, resource_id: usize) -> Option<usize> {
+        self.trains
+            .get(train_idx)?
+            .visits
+            .iter()
+            .position(|v| v.resource_id == resource_id)
+    }
+
+    /// The pairs of consecutive visits in the fixed orders, with the index
+    /// of their order. Trains that do not visit the resource are skipped.
+    pub fn ordered_visits(
+        &self,
+    ) -> impl Iterator<Item = (usize, (usize, usize), (usize, usize))> + '_ {
+        self.orders
+            .iter()
+            .enumerate()
+            .flat_map(move |(order_idx, order)| {
+                let visits = order
+                    .trains
+                    .iter()
+                    .filter_map(|&t| Some((t, self.visit_to(t, order.resource_id)?)))
+                    .collect::<Vec<_>>();
+                (1..visits.len()).map(move |i| (order_idx, visits[i - 1], visits[i]))
+            })
+    }
+
+    /// The order of two visits as a hard connection from the time the first
+    /// one leaves its resource to the start of the second one.
+    pub fn order_connection(&self, first: (usize, usize), second: (usize, usize)) -> Connection {
+        let headway = self.headway(first, second);
+        let (train_idx, visit_idx) = first;
+        let visits = &self.trains[train_idx].visits;
+        let (from, min_transfer) = if visit_idx + 1 < visits.len() {
+            ((train_idx, visit_idx + 1), headway)
+        } else {
+            (first, visits[visit_idx].travel_time + headway)
+        };
+        Connection {
+            from,
+            to: second,
+            min_transfer,
+            penalty: None,
+        }
+    }
+
+    /// The connections followed by the turnarounds and the fixed orders,
+    /// which are all precedences between visits of different trains.
+    pub fn precedences(&self) -> impl Iterator<Item = Connection> + '_ {
+        self.connections
+            .iter()
+            .copied()
+            .chain(
+                self.turnarounds
+                    .iter()
+                    .map(|turnaround| self.turnaround_connection(turnaround)),
+            )
+            .chain(
+                self.ordered_visits()
+                    .map(|(_, first, second)| self.order_connection(first, second)),
+            )
+    }
+
+    /// The first blocked interval (and its index) that an occupation of
+    /// `resource_id` from `t_in` to `t_out` overlaps. No headway is added
+    /// around the interval, see [`BlockedInterval`].
+    pub fn blocking_interval(
+        &self,
+        resource_id: usize,
+        t_in: i32,
+        t_out: i32,
+    ) -> Option<(usize, &BlockedInterval)> {
+        self.blocked.iter().enumerate().find(|(_, blocked)| {
+            (blocked.resource_id == resource_id
+                || self.conflicts.contains(blocked.resource_id, resource_id))
+                && t_in < blocked.to
+                && blocked.from < t_out
+        })
+    }
+
+    /// The capacity of `resource_id` if it is a cumulative resource.
+    pub fn capacity(&self, resource_id: usize) -> Option<usize> {
+        self.capacities.get(&resource_id).copied()
+    }
+
+    /// Tightens each visit's `earliest` time to when the train can actually
+    /// get there (see [`chain_earliest`](crate::solvers::ddd::shared::precedence::chain_earliest)),
+    /// which resolves all [`Issue::InconsistentEarliest`] issues and moves
+    /// the visits with `no_early_departure` to their aimed times. Returns the
+    /// number of visits that were changed.
+    pub fn normalize(&mut self) -> usize {
+        let bounds = crate::solvers::ddd::shared::precedence::chain_earliest(self);
+        let mut n_changed = 0;
+        for (train, bounds) in self.trains.iter_mut().zip(bounds) {
+            for (visit, earliest) in train.visits.iter_mut().zip(bounds) {
+                if visit.earliest != earliest {
+                    visit.earliest = earliest;
+                    n_changed += 1;
+                }
+            }
+        }
+        n_changed
+    }
+
+    /// Checks that every `latest` bound can be met when each train runs as
+    /// early as possible, ignoring resource conflicts. If this fails, no
+    /// solution exists.
+    pub fn latest_bounds_reachable(&self) -> bool {
+        self.trains.iter().all(|train| {
+            let mut t = i32::MIN;
+            train.visits.iter().all(|visit| {
+                t = t.max(visit.earliest_start());
+                let ok = visit.latest.map_or(true, |latest| t <= latest);
+                t += visit.travel_time;
+                ok
+            })
+        })
+    }
+
+    /// Whether `solution` cancels the train, i.e. has no times for it.
+    pub fn is_cancelled(&self, solution: &[Vec<i32>], train_idx: usize) -> bool {
+        solution[train_idx].is_empty() && self.trains[train_idx].cancellation_cost.is_some()
+    }
 
-impl Problem {
     pub fn train_cost(
         &self,
         solution: &[Vec<i32>],
@@ -55,17 +1770,21 @@ impl Problem {
     ) -> i32 {
         let mut sum_cost = 0;
         let train = &self.trains[train_idx];
+        if self.is_cancelled(solution, train_idx) {
+            return train.cancellation_cost.unwrap();
+        }
         for (visit_idx, Visit { .. }) in train.visits.iter().enumerate() {
             let t1_in = solution[train_idx][visit_idx];
 
-            let cost = train.visit_delay_cost(delay_cost_type, visit_idx, t1_in) as i32;
+            let cost = (train.visit_delay_cost(delay_cost_type, visit_idx, t1_in)
+                + train.visit_earliness_cost(visit_idx, t1_in)) as i32;
             if cost > 0 {
                 // println!("Added cost for t{} v{} = {}", train_idx, visit_idx, cost);
                 sum_cost += cost;
             }
         }
 
-        sum_cost
+        sum_cost + train.supplement_cost(&solution[train_idx])
     }
 
     pub fn cost(&self, solution: &[Vec<i32>], delay_cost_type: DelayCostType) -> i32 {
@@ -73,130 +1792,734 @@ impl Problem {
 
         // Check the running times and sum up the delays
         for (train_idx, train) in self.trains.iter().enumerate() {
+            if self.is_cancelled(solution, train_idx) {
+                sum_cost += train.cancellation_cost.unwrap();
+                continue;
+            }
             for visit_idx in 0..train.visits.len() {
                 let t1_in = solution[train_idx][visit_idx];
-                let cost = train.visit_delay_cost(delay_cost_type, visit_idx, t1_in) as i32;
+                let cost = (train.visit_delay_cost(delay_cost_type, visit_idx, t1_in)
+                    + train.visit_earliness_cost(visit_idx, t1_in)) as i32;
                 if cost > 0 {
                     // println!("Added cost for t{} v{} = {}", train_idx, visit_idx, cost);
                     sum_cost += cost;
                 }
             }
+            sum_cost += train.supplement_cost(&solution[train_idx]);
         }
-        sum_cost
+        sum_cost + self.broken_connections_cost(solution)
     }
 
-    pub fn verify_solution(
-        &self,
-        solution: &[Vec<i32>],
-        delay_cost_type: DelayCostType,
-    ) -> Option<i32> {
-        let _p = hprof::enter("verify_solution");
-        // Check the shape of the solution
-        assert!(self.trains.len() == solution.len());
+    /// The total penalty of the soft connections that `solution` breaks.
+    /// Connections to or from a cancelled train do not apply.
+    pub fn broken_connections_cost(&self, solution: &[Vec<i32>]) -> i32 {
+        self.connections
+            .iter()
+            .filter(|c| !self.is_cancelled(solution, c.from.0))
+            .filter(|c| !self.is_cancelled(solution, c.to.0))
+            .filter(|c| !c.is_kept(solution))
+            .filter_map(|c| c.penalty)
+            .sum()
+    }
+
+    /// The buffer between two visits to conflicting resources in
+    /// `solution`: the time from the one that goes first leaving, plus the
+    /// headway, until the other one enters. Negative if they conflict.
+    pub fn buffer(&self, solution: &[Vec<i32>], a: (usize, usize), b: (usize, usize)) -> i32 {
+        let a_first = solution[b.0][b.1] - solution[a.0][a.1 + 1];
+        let b_first = solution[a.0][a.1] - solution[b.0][b.1 + 1];
+        a_first.max(b_first) - self.headway(a, b)
+    }
+
+    /// The buffers of all pairs of visits of different trains to
+    /// conflicting resources in `solution`. Pairs that are far apart count
+    /// up to `cap` in the total, so that it is the sum of the buffers
+    /// between consecutive occupations, each up to `cap`, if those are
+    /// shorter than `cap`. Cancelled trains are left out.
+    pub fn buffer_stats(&self, solution: &[Vec<i32>], cap: i32) -> BufferStats {
+        let mut resource_visits: std::collections::HashMap<usize, Vec<(usize, usize)>> =
+            Default::default();
         for (train_idx, train) in self.trains.iter().enumerate() {
-            assert!(solution[train_idx].len() == train.visits.len() + 1);
+            if self.is_cancelled(solution, train_idx) {
+                continue;
+            }
+            for (visit_idx, visit) in train.visits.iter().enumerate() {
+                resource_visits
+                    .entry(visit.resource_id)
+                    .or_default()
+                    .push((train_idx, visit_idx));
+            }
         }
 
-        let mut sum_cost = 0;
+        let mut stats = BufferStats {
+            pairs: 0,
+            min: None,
+            zero: 0,
+            total: 0,
+            cap,
+        };
+        // The conflict graph has each unordered pair of resources once, so
+        // each pair of visits is counted once.
+        for (r1, r2) in self.conflicts.iter() {
+            let visits = (resource_visits.get(&r1), resource_visits.get(&r2));
+            let (Some(visits1), Some(visits2)) = visits else {
+                continue;
+            };
+            for (i, a) in visits1.iter().enumerate() {
+                // Each pair on the same resource once.
+                let others = if r1 == r2 { &visits2[i + 1..] } else { &visits2[..] };
+                for b in others.iter().filter(|b| b.0 != a.0) {
+                    let buffer = self.buffer(solution, *a, *b);
+                    stats.pairs += 1;
+                    stats.min = Some(stats.min.map_or(buffer, |min| min.min(buffer)));
+                    stats.zero += (buffer <= 0) as usize;
+                    stats.total += buffer.min(cap) as i64;
+                }
+            }
+        }
+        stats
+    }
+
+    /// Checks a solution against the problem and reports every violation
+    /// found, together with the delay cost of each train. Resource conflicts
+    /// are found by a sweep over the visits to each conflicting resource pair
+    /// sorted by entry time, so this is cheap enough to run on every
+    /// incumbent.
+    ///
+    /// A train that may be cancelled is cancelled by giving it no times at
+    /// all. It then costs its cancellation cost, and its conflicts,
+    /// connections and capacity usage are not checked.
+    pub fn verify(&self, solution: &[Vec<i32>], delay_cost_type: DelayCostType) -> VerifyReport {
+        let _p = hprof::enter("verify");
+        let mut violations = Vec::new();
+
+        // Check the shape of the solution
+        if solution.len() != self.trains.len() {
+            violations.push(Violation::Shape {
+                train_idx: None,
+                expected: self.trains.len(),
+                found: solution.len(),
+            });
+            return VerifyReport {
+                violations,
+                train_costs: Vec::new(),
+                broken_connections: Vec::new(),
+                cancelled_trains: Vec::new(),
+                cost: 0,
+            };
+        }
+        let mut well_formed = vec![true; self.trains.len()];
+        let mut train_costs = vec![0; self.trains.len()];
+        let mut cancelled_trains = Vec::new();
+        for (train_idx, train) in self.trains.iter().enumerate() {
+            if self.is_cancelled(solution, train_idx) {
+                // Skipped by all the checks below.
+                well_formed[train_idx] = false;
+                train_costs[train_idx] = train.cancellation_cost.unwrap();
+                cancelled_trains.push(train_idx);
+            } else if solution[train_idx].len() != train.visits.len() + 1 {
+                violations.push(Violation::Shape {
+                    train_idx: Some(train_idx),
+                    expected: train.visits.len() + 1,
+                    found: solution[train_idx].len(),
+                });
+                well_formed[train_idx] = false;
+            }
+        }
 
         // Check the running times and sum up the delays
         for (train_idx, train) in self.trains.iter().enumerate() {
-            for (
-                visit_idx,
-                Visit {
-                    earliest,
-                    travel_time,
-                    ..
-                },
-            ) in train.visits.iter().enumerate()
-            {
-                let t1_in = solution[train_idx][visit_idx];
-                let t1_out = solution[train_idx][visit_idx + 1];
+            if !well_formed[train_idx] {
+                continue;
+            }
+            for (visit_idx, visit) in train.visits.iter().enumerate() {
+                let t_in = solution[train_idx][visit_idx];
+                let t_out = solution[train_idx][visit_idx + 1];
 
-                if t1_in < *earliest - 10 {
-                    println!("Earliest entry conflict t{} v{}", train_idx, visit_idx);
-                    println!("train {}: {:?}", train_idx, train);
-                    println!("  train solution: {:?}", solution[train_idx]);
-                    return None;
+                // Entering up to 10 time units early is tolerated.
+                if t_in < visit.earliest - 10 {
+                    violations.push(Violation::Earliest {
+                        train_idx,
+                        visit_idx,
+                        resource_id: visit.resource_id,
+                        earliest: visit.earliest,
+                        time: t_in,
+                    });
                 }
 
-                if t1_in + travel_time > t1_out {
-                    println!("Travel time conflict t{} v{}", train_idx, visit_idx);
-                    println!("train {}: {:?}", train_idx, train);
-                    println!("  train solution: {:?}", solution[train_idx]);
-                    return None;
+                if let Some(aimed) = visit.aimed.filter(|&a| visit.no_early_departure && t_in < a) {
+                    violations.push(Violation::EarlyDeparture {
+                        train_idx,
+                        visit_idx,
+                        resource_id: visit.resource_id,
+                        aimed,
+                        time: t_in,
+                    });
                 }
 
-                let cost = train.visit_delay_cost(delay_cost_type, visit_idx, t1_in) as i32;
-                if cost > 0 {
-                    // println!("Added cost for t{} v{} = {}", train_idx, visit_idx, cost);
-                    sum_cost += cost;
+                if let Some(latest) = visit.latest.filter(|&latest| t_in > latest) {
+                    violations.push(Violation::Latest {
+                        train_idx,
+                        visit_idx,
+                        resource_id: visit.resource_id,
+                        latest,
+                        time: t_in,
+                    });
+                }
+
+                if t_in + visit.travel_time > t_out {
+                    violations.push(Violation::TravelTime {
+                        train_idx,
+                        visit_idx,
+                        resource_id: visit.resource_id,
+                        travel_time: visit.travel_time,
+                        time_in: t_in,
+                        time_out: t_out,
+                    });
+                }
+
+                if let Some(max_travel_time) = visit.max_travel_time.filter(|&m| t_out - t_in > m) {
+                    violations.push(Violation::MaxTravelTime {
+                        train_idx,
+                        visit_idx,
+                        resource_id: visit.resource_id,
+                        max_travel_time,
+                        time_in: t_in,
+                        time_out: t_out,
+                    });
                 }
+
+                train_costs[train_idx] += (train.visit_delay_cost(delay_cost_type, visit_idx, t_in)
+                    + train.visit_earliness_cost(visit_idx, t_in))
+                    as i32;
             }
+            train_costs[train_idx] += train.supplement_cost(&solution[train_idx]);
         }
 
-        // Check all pairs of visits for resource conflicts.
-        for (train_idx1, train1) in self.trains.iter().enumerate() {
-            for (
-                visit_idx1,
-                Visit {
-                    resource_id: r1, ..
-                },
-            ) in train1.visits.iter().enumerate()
-            {
-                for (train_idx2, train2) in self.trains.iter().enumerate() {
-                    for (
-                        visit_idx2,
-                        Visit {
-                            resource_id: r2, ..
-                        },
-                    ) in train2.visits.iter().enumerate()
+        // Check for resource conflicts, one resource pair at a time.
+        let mut visits_on: std::collections::HashMap<usize, Vec<(usize, usize)>> =
+            std::collections::HashMap::new();
+        for (train_idx, train) in self.trains.iter().enumerate() {
+            if !well_formed[train_idx] {
+                continue;
+            }
+            for (visit_idx, visit) in train.visits.iter().enumerate() {
+                visits_on
+                    .entry(visit.resource_id)
+                    .or_default()
+                    .push((train_idx, visit_idx));
+            }
+        }
+
+        let no_visits = Vec::new();
+        for (r1, r2) in self.conflicts.iter() {
+            let visits1 = visits_on.get(&r1).unwrap_or(&no_visits);
+            let visits2 = visits_on.get(&r2).unwrap_or(&no_visits);
+            if visits1.is_empty() || visits2.is_empty() {
+                continue;
+            }
+
+            // Two occupations conflict if neither ends (plus headway, minus one
+            // unit of rounding slack) before the other starts. Pad every
+            // occupation by the largest headway of the pair for the sweep, and
+            // check the exact headway on the candidates.
+            let padding = self.headways.max_headway(r1, r2) - 1;
+            let mut intervals = Vec::with_capacity(visits1.len() + visits2.len());
+            for (visits, resource_id) in [(visits1, r1), (visits2, r2)] {
+                intervals.extend(
+                    visits
+                        .iter()
+                        .map(|&(t, v)| (solution[t][v], solution[t][v + 1], (t, v), resource_id)),
+                );
+                if r1 == r2 {
+                    break;
+                }
+            }
+            intervals.sort_by_key(|&(t_in, _, visit, _)| (t_in, visit));
+
+            let mut active: Vec<(i32, i32, (usize, usize), usize)> = Vec::new();
+            for interval in intervals {
+                let (t2_in, t2_out, visit2, resource2) = interval;
+                active.retain(|&(_, t1_out, _, _)| t1_out + padding > t2_in);
+                for &(t1_in, t1_out, visit1, resource1) in active.iter() {
+                    if r1 != r2 && resource1 == resource2 {
+                        continue;
+                    }
+                    // A train's own visits are ordered by its travel times,
+                    // and the solvers do not keep a headway between them.
+                    if visit1.0 == visit2.0 {
+                        continue;
+                    }
+                    let headway = self.headway(visit1, visit2);
+                    let ok = t1_in >= t2_out + headway - 1 || t2_in >= t1_out + headway - 1;
+                    if !ok {
+                        violations.push(Violation::ResourceConflict {
+                            visits: (visit1, visit2),
+                            resources: (resource1, resource2),
+                            times: ((t1_in, t1_out), (t2_in, t2_out)),
+                            headway,
+                        });
+                    }
+                }
+                active.push(interval);
+            }
+        }
+
+        // Check that no visit overlaps a blocked interval.
+        if !self.blocked.is_empty() {
+            for (train_idx, train) in self.trains.iter().enumerate() {
+                if !well_formed[train_idx] {
+                    continue;
+                }
+                for (visit_idx, visit) in train.visits.iter().enumerate() {
+                    let times = (
+                        solution[train_idx][visit_idx],
+                        solution[train_idx][visit_idx + 1],
+                    );
+                    if let Some((blocked_idx, _)) =
+                        self.blocking_interval(visit.resource_id, times.0, times.1)
                     {
-                        if (train_idx1 != train_idx2 || visit_idx1 != visit_idx2)
-                            && self.conflicts.contains(&(*r1, *r2))
-                        {
-                            // Different visits to the conflicting resources.
-
-                            let t1_in = solution[train_idx1][visit_idx1];
-                            let t1_out = solution[train_idx1][visit_idx1 + 1];
-                            let t2_in = solution[train_idx2][visit_idx2];
-                            let t2_out = solution[train_idx2][visit_idx2 + 1];
-
-                            let ok = t1_in >= t2_out - 1 || t2_in >= t1_out - 1;
-                            if !ok {
-                                println!(
-                                    "Resource conflict {}-{} in t{} v{} {}-{} t{} v{} {}-{}",
-                                    *r1,
-                                    *r2,
-                                    train_idx1,
-                                    visit_idx1,
-                                    t1_in,
-                                    t1_out,
-                                    train_idx2,
-                                    visit_idx2,
-                                    t2_in,
-                                    t2_out
-                                );
-                                println!("train1 {}: {:?}", train_idx1, train1);
-                                println!("  train1 solution: {:?}", solution[train_idx1]);
-                                println!("  {:?}", train1.visits[visit_idx1]);
-                                println!("train2 {}: {:?}", train_idx2, train2);
-                                println!("  train2 solution: {:?}", solution[train_idx2]);
-                                println!("  {:?}", train2.visits[visit_idx2]);
-                                return None;
-                            }
-                        }
+                        violations.push(Violation::Blocked {
+                            blocked_idx,
+                            visit: (train_idx, visit_idx),
+                            times,
+                        });
                     }
                 }
             }
         }
 
-        println!("Solution verified. Cost {}", sum_cost);
-        Some(sum_cost)
+        // Check the capacity of cumulative resources, with the same unit of
+        // slack as for conflicts.
+        for (&resource_id, &capacity) in self.capacities.iter() {
+            let Some(visits) = visits_on.get(&resource_id) else {
+                continue;
+            };
+            let mut intervals = visits
+                .iter()
+                .map(|&(t, v)| (solution[t][v], solution[t][v + 1], (t, v)))
+                .collect::<Vec<_>>();
+            intervals.sort();
+
+            let mut active: Vec<(i32, i32, (usize, usize))> = Vec::new();
+            for interval @ (t_in, _, _) in intervals {
+                active.retain(|&(_, t_out, _)| t_out - 1 > t_in);
+                active.push(interval);
+                if active.len() > capacity {
+                    violations.push(Violation::CapacityExceeded {
+                        resource_id,
+                        capacity,
+                        time: t_in,
+                        visits: active.iter().map(|&(_, _, visit)| visit).collect(),
+                    });
+                }
+            }
+        }
+
+        // Broken hard connections are violations, the penalty of a broken
+        // soft connection is added to the cost of the departing train.
+        let mut broken_connections = Vec::new();
+        for (connection_idx, connection) in self.connections.iter().enumerate() {
+            let ((t1, v1), (t2, v2)) = (connection.from, connection.to);
+            if !well_formed[t1] || !well_formed[t2] || connection.is_kept(solution) {
+                continue;
+            }
+            match connection.penalty {
+                Some(penalty) => {
+                    train_costs[t2] += penalty;
+                    broken_connections.push(connection_idx);
+                }
+                None => violations.push(Violation::BrokenConnection {
+                    connection_idx,
+                    visits: (connection.from, connection.to),
+                    times: (solution[t1][v1], solution[t2][v2]),
+                    min_transfer: connection.min_transfer,
+                }),
+            }
+        }
+
+        for (turnaround_idx, turnaround) in self.turnarounds.iter().enumerate() {
+            let (from, to) = (turnaround.from, turnaround.to);
+            if !well_formed[from] || !well_formed[to] {
+                continue;
+            }
+            let (end, start) = (*solution[from].last().unwrap(), solution[to][0]);
+            if start < end + turnaround.time {
+                violations.push(Violation::Turnaround {
+                    turnaround_idx,
+                    trains: (from, to),
+                    times: (end, start),
+                    time: turnaround.time,
+                });
+            }
+        }
+
+        for (order_idx, first, second) in self.ordered_visits() {
+            if !well_formed[first.0] || !well_formed[second.0] {
+                continue;
+            }
+            if !self.order_connection(first, second).is_kept(solution) {
+                violations.push(Violation::Order {
+                    order_idx,
+                    visits: (first, second),
+                    times: (solution[first.0][first.1 + 1], solution[second.0][second.1]),
+                });
+            }
+        }
+
+        let cost = train_costs.iter().sum();
+        VerifyReport {
+            violations,
+            train_costs,
+            broken_connections,
+            cancelled_trains,
+            cost,
+        }
+    }
+
+    /// Verifies a solution, printing the first violation found. Returns the
+    /// cost of the solution if it is feasible. See [`Problem::verify`].
+    pub fn verify_solution(
+        &self,
+        solution: &[Vec<i32>],
+        delay_cost_type: DelayCostType,
+    ) -> Option<i32> {
+        let _p = hprof::enter("verify_solution");
+        let report = self.verify(solution, delay_cost_type);
+        if let Some(violation) = report.violations.first() {
+            println!("{}", violation);
+            for train_idx in violation.train_indices() {
+                println!("train {}: {:?}", train_idx, self.trains[train_idx]);
+                println!("  train solution: {:?}", solution[train_idx]);
+            }
+            return None;
+        }
+
+        println!("Solution verified. Cost {}", report.cost);
+        Some(report.cost)
+    }
+}
+
+/// A way in which a solution fails to satisfy its problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The solution has the wrong number of trains (`train_idx` is `None`)
+    /// or the wrong number of times for a train.
+    Shape {
+        train_idx: Option<usize>,
+        expected: usize,
+        found: usize,
+    },
+    Earliest {
+        train_idx: usize,
+        visit_idx: usize,
+        resource_id: usize,
+        earliest: i32,
+        time: i32,
+    },
+    Latest {
+        train_idx: usize,
+        visit_idx: usize,
+        resource_id: usize,
+        latest: i32,
+        time: i32,
+    },
+    /// A visit with `no_early_departure` is entered before its aimed time.
+    EarlyDeparture {
+        train_idx: usize,
+        visit_idx: usize,
+        resource_id: usize,
+        aimed: i32,
+        time: i32,
+    },
+    TravelTime {
+        train_idx: usize,
+        visit_idx: usize,
+        resource_id: usize,
+        travel_time: i32,
+        time_in: i32,
+        time_out: i32,
+    },
+    /// A train stays on a resource for longer than its `max_travel_time`.
+    MaxTravelTime {
+        train_idx: usize,
+        visit_idx: usize,
+        resource_id: usize,
+        max_travel_time: i32,
+        time_in: i32,
+        time_out: i32,
+    },
+    /// Two visits occupy conflicting resources at overlapping times (or
+    /// closer than the required headway).
+    ResourceConflict {
+        visits: ((usize, usize), (usize, usize)),
+        resources: (usize, usize),
+        times: ((i32, i32), (i32, i32)),
+        headway: i32,
+    },
+    /// More visits than the capacity of a cumulative resource occupy it at
+    /// `time`.
+    CapacityExceeded {
+        resource_id: usize,
+        capacity: usize,
+        time: i32,
+        visits: Vec<(usize, usize)>,
+    },
+    /// A connection without a penalty is not kept.
+    BrokenConnection {
+        connection_idx: usize,
+        visits: ((usize, usize), (usize, usize)),
+        times: (i32, i32),
+        min_transfer: i32,
+    },
+    /// A visit overlaps a blocked interval of its resource or of a
+    /// conflicting resource.
+    Blocked {
+        blocked_idx: usize,
+        visit: (usize, usize),
+        times: (i32, i32),
+    },
+    /// A train starts before the trainset has turned around after its
+    /// previous service.
+    Turnaround {
+        turnaround_idx: usize,
+        trains: (usize, usize),
+        times: (i32, i32),
+        time: i32,
+    },
+    /// A visit starts before the visit it must follow in a fixed order has
+    /// cleared the resource.
+    Order {
+        order_idx: usize,
+        visits: ((usize, usize), (usize, usize)),
+        times: (i32, i32),
+    },
+}
+
+impl Violation {
+    /// The trains involved in the violation.
+    pub fn train_indices(&self) -> Vec<usize> {
+        match self {
+            Violation::Shape { train_idx, .. } => train_idx.iter().copied().collect(),
+            Violation::Earliest { train_idx, .. }
+            | Violation::Latest { train_idx, .. }
+            | Violation::EarlyDeparture { train_idx, .. }
+            | Violation::TravelTime { train_idx, .. }
+            | Violation::MaxTravelTime { train_idx, .. } => vec![*train_idx],
+            Violation::ResourceConflict {
+                visits: ((t1, _), (t2, _)),
+                ..
+            } => {
+                if t1 == t2 {
+                    vec![*t1]
+                } else {
+                    vec![*t1, *t2]
+                }
+            }
+            Violation::CapacityExceeded { visits, .. } => {
+                let mut trains = visits.iter().map(|(t, _)| *t).collect::<Vec<_>>();
+                trains.sort();
+                trains.dedup();
+                trains
+            }
+            Violation::BrokenConnection {
+                visits: ((t1, _), (t2, _)),
+                ..
+            } => vec![*t1, *t2],
+            Violation::Blocked { visit: (t, _), .. } => vec![*t],
+            Violation::Turnaround { trains: (t1, t2), .. } => vec![*t1, *t2],
+            Violation::Order {
+                visits: ((t1, _), (t2, _)),
+                ..
+            } => vec![*t1, *t2],
+        }
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Shape {
+                train_idx: None,
+                expected,
+                found,
+            } => write!(f, "Shape conflict: {} trains, expected {}", found, expected),
+            Violation::Shape {
+                train_idx: Some(train_idx),
+                expected,
+                found,
+            } => write!(
+                f,
+                "Shape conflict t{}: {} times, expected {}",
+                train_idx, found, expected
+            ),
+            Violation::Earliest {
+                train_idx,
+                visit_idx,
+                resource_id,
+                earliest,
+                time,
+            } => write!(
+                f,
+                "Earliest entry conflict t{} v{} r{}: {} < {}",
+                train_idx, visit_idx, resource_id, time, earliest
+            ),
+            Violation::Latest {
+                train_idx,
+                visit_idx,
+                resource_id,
+                latest,
+                time,
+            } => write!(
+                f,
+                "Latest entry conflict t{} v{} r{}: {} > {}",
+                train_idx, visit_idx, resource_id, time, latest
+            ),
+            Violation::EarlyDeparture {
+                train_idx,
+                visit_idx,
+                resource_id,
+                aimed,
+                time,
+            } => write!(
+                f,
+                "Early departure conflict t{} v{} r{}: {} < {}",
+                train_idx, visit_idx, resource_id, time, aimed
+            ),
+            Violation::TravelTime {
+                train_idx,
+                visit_idx,
+                resource_id,
+                travel_time,
+                time_in,
+                time_out,
+            } => write!(
+                f,
+                "Travel time conflict t{} v{} r{}: {}-{} shorter than {}",
+                train_idx, visit_idx, resource_id, time_in, time_out, travel_time
+            ),
+            Violation::MaxTravelTime {
+                train_idx,
+                visit_idx,
+                resource_id,
+                max_travel_time,
+                time_in,
+                time_out,
+            } => write!(
+                f,
+                "Travel time conflict t{} v{} r{}: {}-{} longer than {}",
+                train_idx, visit_idx, resource_id, time_in, time_out, max_travel_time
+            ),
+            Violation::ResourceConflict {
+                visits: ((t1, v1), (t2, v2)),
+                resources: (r1, r2),
+                times: ((t1_in, t1_out), (t2_in, t2_out)),
+                headway,
+            } => write!(
+                f,
+                "Resource conflict {}-{} in t{} v{} {}-{} t{} v{} {}-{} (headway {})",
+                r1, r2, t1, v1, t1_in, t1_out, t2, v2, t2_in, t2_out, headway
+            ),
+            Violation::CapacityExceeded {
+                resource_id,
+                capacity,
+                time,
+                visits,
+            } => write!(
+                f,
+                "Capacity {} of resource {} exceeded at {} by {:?}",
+                capacity, resource_id, time, visits
+            ),
+            Violation::BrokenConnection {
+                connection_idx,
+                visits: ((t1, v1), (t2, v2)),
+                times: (t1_in, t2_in),
+                min_transfer,
+            } => write!(
+                f,
+                "Connection #{} broken: t{} v{} at {}, t{} v{} at {} (transfer {})",
+                connection_idx, t1, v1, t1_in, t2, v2, t2_in, min_transfer
+            ),
+            Violation::Blocked {
+                blocked_idx,
+                visit: (t, v),
+                times: (t_in, t_out),
+            } => write!(
+                f,
+                "Blocked interval #{} overlaps t{} v{} {}-{}",
+                blocked_idx, t, v, t_in, t_out
+            ),
+            Violation::Turnaround {
+                turnaround_idx,
+                trains: (t1, t2),
+                times: (t1_out, t2_in),
+                time,
+            } => write!(
+                f,
+                "Turnaround #{} too short: t{} ends at {}, t{} starts at {} (turnaround {})",
+                turnaround_idx, t1, t1_out, t2, t2_in, time
+            ),
+            Violation::Order {
+                order_idx,
+                visits: ((t1, v1), (t2, v2)),
+                times: (t1_out, t2_in),
+            } => write!(
+                f,
+                "Order #{} broken: t{} v{} leaves at {}, t{} v{} starts at {}",
+                order_idx, t1, v1, t1_out, t2, v2, t2_in
+            ),
+        }
+    }
+}
+
+/// The result of [`Problem::verify`].
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    pub violations: Vec<Violation>,
+    /// Delay cost of each train, indexed like `Problem::trains`, including
+    /// the penalties of the broken connections it departs on, or its
+    /// cancellation cost.
+    pub train_costs: Vec<i32>,
+    /// Indices of the soft connections that are broken.
+    pub broken_connections: Vec<usize>,
+    /// Indices of the trains that are cancelled.
+    pub cancelled_trains: Vec<usize>,
+    pub cost: i32,
+}
+
+impl VerifyReport {
+    pub fn is_feasible(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// The total cost, if the solution is feasible.
+    pub fn feasible_cost(&self) -> Option<i32> {
+        self.is_feasible().then_some(self.cost)
     }
 }
 
 impl Train {
+    /// The running-time cost of the train's `times` (see
+    /// [`Train::running_time_cost`]).
+    pub fn supplement_cost(&self, times: &[i32]) -> i32 {
+        let Some(cost) = self.running_time_cost else {
+            return 0;
+        };
+        self.visits
+            .iter()
+            .enumerate()
+            .filter(|(_, visit)| visit.max_travel_time.is_some())
+            .map(|(visit_idx, visit)| {
+                (times[visit_idx + 1] - times[visit_idx] - visit.travel_time).max(0) * cost
+            })
+            .sum()
+    }
+
+    /// The penalty of visit `path_idx` for [`DelayCostType::PiecewiseLinear`].
+    pub fn visit_cost(&self, path_idx: usize) -> Option<&PiecewiseLinearCost> {
+        self.visit_costs.get(path_idx).and_then(Option::as_ref)
+    }
+
     /// Returns the absolute times (> `earliest`) at which delay cost first increases
     /// for the given visit. Pre-seeding these into the DDD discretization ensures the
     /// solver can always reach every cost level, which is necessary for optimality.
@@ -206,6 +2529,13 @@ impl Train {
         path_idx: usize,
         earliest: i32,
     ) -> Vec<i32> {
+        if let DelayCostType::PiecewiseLinear = delay_cost_type {
+            return self
+                .visit_cost(path_idx)
+                .map(|f| f.threshold_times().filter(|&t| t > earliest).collect())
+                .unwrap_or_default();
+        }
+
         let aimed = match self.visits[path_idx].aimed {
             Some(a) => a,
             None => return vec![],
@@ -227,6 +2557,8 @@ impl Train {
                 iter_infinite_staircase(360).map(|(d, _)| d).take(100).collect()
             }
             DelayCostType::Continuous => vec![],
+            DelayCostType::Custom(f) => f.step_delays(100),
+            DelayCostType::PiecewiseLinear => unreachable!(),
         };
 
         step_delays
@@ -236,12 +2568,38 @@ impl Train {
             .collect()
     }
 
+    /// The weighted delay cost of entering visit `path_idx` at time `t`.
+    /// This never decreases with `t`; the earliness cost is separate (see
+    /// [`Train::visit_earliness_cost`]).
     pub fn visit_delay_cost(
         &self,
         delay_cost_type: DelayCostType,
         path_idx: usize,
         t: i32,
     ) -> usize {
+        self.weight * self.unweighted_visit_delay_cost(delay_cost_type, path_idx, t)
+    }
+
+    /// The weighted cost of entering visit `path_idx` at `t` before its
+    /// aimed time (see [`Train::earliness_cost`]). Added to the delay cost
+    /// of the schedule, but not to [`Train::visit_delay_cost`].
+    pub fn visit_earliness_cost(&self, path_idx: usize, t: i32) -> usize {
+        match (self.earliness_cost, self.visits[path_idx].aimed) {
+            (Some(cost), Some(aimed)) => self.weight * (cost * (aimed - t).max(0)) as usize,
+            _ => 0,
+        }
+    }
+
+    pub fn unweighted_visit_delay_cost(
+        &self,
+        delay_cost_type: DelayCostType,
+        path_idx: usize,
+        t: i32,
+    ) -> usize {
+        if let DelayCostType::PiecewiseLinear = delay_cost_type {
+            return self.visit_cost(path_idx).map_or(0, |f| f.eval(t));
+        }
+
         if let Some(aimed) = self.visits[path_idx].aimed {
             let d = (t - aimed).max(0);
             match delay_cost_type {
@@ -254,6 +2612,8 @@ impl Train {
                 DelayCostType::InfiniteSteps180 => infinite_staircase(d, 180),
                 DelayCostType::InfiniteSteps360 => infinite_staircase(d, 360),
                 DelayCostType::Continuous => d as usize,
+                DelayCostType::Custom(f) => f.eval(d),
+                DelayCostType::PiecewiseLinear => unreachable!(),
             }
         } else {
             0
@@ -327,6 +2687,9 @@ fn visit(resource_id: usize, earliest: i32, travel_time: i32) -> Visit {
         earliest,
         travel_time,
         aimed: Some(earliest),
+        latest: None,
+        max_travel_time: None,
+        no_early_departure: false,
     }
 }
 
@@ -352,6 +2715,11 @@ pub fn problem1_with_stations() -> Problem {
                     visit(7, 9, 0),
                     visit(6, 9, travel_times[6]),
                 ],
+                weight: 1,
+                visit_costs: Vec::new(),
+                cancellation_cost: None,
+                running_time_cost: None,
+                earliness_cost: None,
             },
             Train {
                 visits: vec![
@@ -359,6 +2727,11 @@ pub fn problem1_with_stations() -> Problem {
                     visit(7, 4, 0),
                     visit(1, 4, travel_times[1]),
                 ],
+                weight: 1,
+                visit_costs: Vec::new(),
+                cancellation_cost: None,
+                running_time_cost: None,
+                earliness_cost: None,
             },
             Train {
                 visits: vec![
@@ -368,6 +2741,11 @@ pub fn problem1_with_stations() -> Problem {
                     visit(7, 12, 0),
                     visit(5, 12, travel_times[5]),
                 ],
+                weight: 1,
+                visit_costs: Vec::new(),
+                cancellation_cost: None,
+                running_time_cost: None,
+                earliness_cost: None,
             },
             Train {
                 visits: vec![
@@ -375,10 +2753,21 @@ pub fn problem1_with_stations() -> Problem {
                     visit(7, 10, 0),
                     visit(5, 10, travel_times[5]),
                 ],
+                weight: 1,
+                visit_costs: Vec::new(),
+                cancellation_cost: None,
+                running_time_cost: None,
+                earliness_cost: None,
             },
         ],
 
         conflicts: (0..=6).map(|i| (i, i)).collect(), // resources only conflict with themselves.
+        headways: Headways::default(),
+        capacities: Default::default(),
+        connections: Vec::new(),
+        turnarounds: Vec::new(),
+        blocked: Vec::new(),
+        orders: Vec::new(),
     }
 }
 #[allow(unused)]
@@ -400,9 +2789,19 @@ pub fn problem1() -> Problem {
                     visit(1, 6, travel_times[1]),
                     visit(6, 9, travel_times[6]),
                 ],
+                weight: 1,
+                visit_costs: Vec::new(),
+                cancellation_cost: None,
+                running_time_cost: None,
+                earliness_cost: None,
             },
             Train {
                 visits: vec![visit(2, 0, travel_times[2]), visit(1, 4, travel_times[1])],
+                weight: 1,
+                visit_costs: Vec::new(),
+                cancellation_cost: None,
+                running_time_cost: None,
+                earliness_cost: None,
             },
             Train {
                 visits: vec![
@@ -410,12 +2809,28 @@ pub fn problem1() -> Problem {
                     visit(1, 9, travel_times[1]),
                     visit(5, 12, travel_times[5]),
                 ],
+                weight: 1,
+                visit_costs: Vec::new(),
+                cancellation_cost: None,
+                running_time_cost: None,
+                earliness_cost: None,
             },
             Train {
                 visits: vec![visit(4, 0, travel_times[4]), visit(5, 10, travel_times[5])],
+                weight: 1,
+                visit_costs: Vec::new(),
+                cancellation_cost: None,
+                running_time_cost: None,
+                earliness_cost: None,
             },
         ],
 
         conflicts: (0..=6).map(|i| (i, i)).collect(), // resources only conflict with themselves.
+        headways: Headways::default(),
+        capacities: Default::default(),
+        connections: Vec::new(),
+        turnarounds: Vec::new(),
+        blocked: Vec::new(),
+        orders: Vec::new(),
     }
 }
@@ -7,7 +7,7 @@ use std::{
 
 use crate::{
     debug::{DebugInfo, ResourceInterval, SolverAction},
-    problem::{DelayCostType, Problem},
+    problem::{CostBound, DelayCostType, Problem},
     solvers::util::{heuristic, value_trace::ValueTrace},
 };
 use rustsat::{
@@ -29,7 +29,7 @@ use rustsat::{
 };
 use rustsat_glucose::core::Glucose as RsGlucose;
 use satcoder::{
-    prelude::SymbolicModel, Bool, SatInstance, SatModel, SatResult, SatResultWithCore, SatSolver,
+    constraints::Totalizer, prelude::SymbolicModel, Bool, SatInstance, SatModel, SatResult, SatResultWithCore, SatSolver,
     SatSolverWithCore,
 };
 use typed_index_collections::TiVec;
@@ -103,6 +103,10 @@ pub struct SatDddSettings {
     /// If `false`, AMOs always use pairwise regardless of size.
     /// Default `true` (use SC for large cliques).
     pub use_sc_amo: bool,
+    /// Keep the cost under another cost type at most the bound as a hard
+    /// constraint, for lexicographic optimisation. The bound is encoded
+    /// with SCPB as permanent clauses, whatever the objective encoding.
+    pub primary_bound: Option<CostBound>,
 }
 
 impl Default for SatDddSettings {
@@ -113,6 +117,7 @@ impl Default for SatDddSettings {
             seed_precedence_from_earliest: false,
             seed_resource_conflicts: false,
             use_sc_amo: true,
+            primary_bound: None,
         }
     }
 }
@@ -1177,8 +1182,10 @@ fn compute_initial_heuristic_upper_bound<L: satcoder::Lit>(
             use_strong_branching,
             Some(&initial_solution),
         )? {
-            let ub_cost = problem.verify_solution(&ub_sol, delay_cost_type).unwrap();
-            return Ok(Some((ub_cost, ub_sol)));
+            // The heuristic ignores `latest` bounds, so its schedule may be rejected.
+            if let Some(ub_cost) = problem.verify_solution(&ub_sol, delay_cost_type) {
+                return Ok(Some((ub_cost, ub_sol)));
+            }
         }
     }
 
@@ -1279,6 +1286,31 @@ fn add_hybrid_amo<L: satcoder::Lit>(
     }
 }
 
+/// At-most-`k` over `lits` for the capacity of cumulative resources. `k == 1`
+/// goes through [`add_hybrid_amo`], larger bounds use a totalizer.
+fn add_at_most_k<L: satcoder::Lit>(
+    solver: &mut impl SatInstance<L>,
+    lits: &[Bool<L>],
+    k: usize,
+    use_sc_amo: bool,
+) {
+    if lits.len() <= k {
+        return;
+    }
+    match k {
+        0 => {
+            for lit in lits {
+                solver.add_clause(vec![!*lit]);
+            }
+        }
+        1 => add_hybrid_amo(solver, lits, use_sc_amo),
+        _ => {
+            let tot = Totalizer::count(solver, lits.iter().copied(), k as u32);
+            solver.add_clause(vec![!tot.rhs()[k]]); // tot <= k
+        }
+    }
+}
+
 /// Monotone delay literal `visit_id.start ≥ t`.
 /// Returns `true.into()` if t ≤ earliest (always satisfied),
 /// `false.into()` if t ≥ infinity sentinel (never satisfied),
@@ -1503,8 +1535,11 @@ fn solve_native_debug_with_mode(
     let mut train_visit_ids: Vec<Vec<VisitId>> = vec![Vec::new(); problem.trains.len()];
     let mut resource_visits: Vec<Vec<VisitId>> = Vec::new();
     let mut occupations: TiVec<VisitId, Occ<NativeLit>> = TiVec::new();
+    // The costs under the cost type of `settings.primary_bound`.
+    let mut primary_trees: TiVec<VisitId, CostTree<NativeLit>> = TiVec::new();
+    // The earliness costs, which fall with time, on the negated delay literals.
+    let mut earliness_trees: TiVec<VisitId, CostTree<NativeLit>> = TiVec::new();
     let mut touched_intervals = Vec::new();
-    let mut conflicts: HashMap<usize, Vec<usize>> = HashMap::new();
     let mut new_time_points: Vec<(VisitId, Bool<NativeLit>, i32)> = Vec::new();
     // Within-train chain propagation of earliest times (no ER).
     // Matches `maxsat_ladder_sc::compute_effective_earliest` exactly —
@@ -1521,11 +1556,16 @@ fn solve_native_debug_with_mode(
     let mut n_timepoints = 0usize;
     let mut n_conflict_constraints = 0usize;
 
-    for (a, b) in problem.conflicts.iter() {
-        conflicts.entry(*a).or_default().push(*b);
-        if *a != *b {
-            conflicts.entry(*b).or_default().push(*a);
-        }
+    // The time ladders below end at each visit's `latest` bound, which
+    // requires the bound to be reachable at all.
+    if !problem.latest_bounds_reachable() {
+        return Err(SolverError::NoSolution);
+    }
+
+    // The objective encodings below only cover delays, so the penalties of
+    // soft connections cannot be optimised.
+    if problem.connections.iter().any(|c| c.penalty.is_some()) {
+        return Err(SolverError::NoSolution);
     }
 
     for (train_idx, train) in problem.trains.iter().enumerate() {
@@ -1535,14 +1575,16 @@ fn solve_native_debug_with_mode(
             let earliest = effective_earliest
                 .as_ref()
                 .map(|bounds| bounds[train_idx][visit_idx])
-                .unwrap_or(visit.earliest);
+                .unwrap_or(visit.earliest_start());
 
             occupations.push(Occ {
                 cost: vec![true.into()],
                 cost_tree: CostTree::new(),
-                delays: vec![(true.into(), earliest), (false.into(), i32::MAX)],
+                delays: vec![(true.into(), earliest), (false.into(), visit.ladder_end())],
                 incumbent_idx: 0,
             });
+            primary_trees.push(CostTree::new());
+            earliness_trees.push(CostTree::new());
             n_timepoints += 1;
 
             while resource_visits.len() <= visit.resource_id {
@@ -1567,6 +1609,15 @@ fn solve_native_debug_with_mode(
         }
     }
 
+    let early_visits: Vec<(VisitId, i32)> = visits
+        .iter_enumerated()
+        .filter_map(|(visit_id, (train_idx, visit_idx))| {
+            let train = &problem.trains[*train_idx];
+            train.earliness_cost?;
+            Some((visit_id, train.visits[*visit_idx].aimed?))
+        })
+        .collect();
+
     let mut best_sol: Option<(i32, Vec<Vec<i32>>)> = None;
     let mut lower_bound: i32 = 0;
     let mut upper_bound: Option<i32> = None;
@@ -1584,9 +1635,36 @@ fn solve_native_debug_with_mode(
     let mut budget_gte = GeneralizedTotalizer::default();
     let mut last_added_bound: Option<usize> = None;
     let mut bit_totalizer = BitTotalizerObjective::default();
+    let mut primary_terms: Vec<(NativeLit, usize)> = Vec::new();
+    let mut primary_encoded_terms = 0;
 
     let mut added_resource_clique_rows: HashSet<ResourceCliqueRowKey> = HashSet::new();
+    // Over-capacity sets of cumulative resources already forbidden, keyed by
+    // sorted visit-set and tau.
+    let mut capacity_rows: HashSet<(Vec<VisitId>, i32)> = HashSet::new();
+    // (connection, arrival time) precedence rows already added.
+    let mut connection_rows: HashSet<(usize, i32)> = HashSet::new();
     let mut fixed_prec_rows: HashSet<(VisitId, i32)> = HashSet::new();
+    // Fixed orders come last in the precedences and are encoded eagerly:
+    // every time point of the visit that leaves first gets its row to the
+    // visit that follows.
+    let precedences = problem.precedences().collect::<Vec<_>>();
+    let mut order_rows_from: HashMap<VisitId, Vec<usize>> = HashMap::new();
+    let first_order_row = problem.connections.len() + problem.turnarounds.len();
+    for (connection_idx, connection) in precedences.iter().enumerate().skip(first_order_row) {
+        let from = train_visit_ids[connection.from.0][connection.from.1];
+        order_rows_from.entry(from).or_default().push(connection_idx);
+    }
+    // Visits with a maximum travel time, which are checked in every
+    // iteration as the violation may come from the next visit alone.
+    let bounded_visits: Vec<(VisitId, i32)> = visits
+        .iter_enumerated()
+        .filter_map(|(visit_id, (train_idx, visit_idx))| {
+            let train = &problem.trains[*train_idx];
+            let max_travel_time = train.visits[*visit_idx].max_travel_time?;
+            (*visit_idx + 1 < train.visits.len()).then_some((visit_id, max_travel_time))
+        })
+        .collect();
 
     if settings.seed_precedence_from_earliest {
         for visit_id in visits.keys() {
@@ -1642,7 +1720,6 @@ fn solve_native_debug_with_mode(
     if settings.seed_resource_conflicts {
         let mut seed_active_cache: HashMap<(VisitId, i32), Bool<NativeLit>> = HashMap::new();
         let mut n_seeded = 0usize;
-        let mut seen_resource_pairs: HashSet<(usize, usize)> = HashSet::new();
 
         // Helper: get earliest start + earliest end of a visit's occupation.
         let earliest_occupation = |visit_id: VisitId,
@@ -1721,54 +1798,42 @@ fn solve_native_debug_with_mode(
             };
 
         // Iterate over unique conflicting resource pairs.
-        let conflicts_snapshot: Vec<(usize, Vec<usize>)> = conflicts
-            .iter()
-            .map(|(k, v)| (*k, v.clone()))
-            .collect();
-        for (res_a, others) in &conflicts_snapshot {
-            for &res_b in others {
-                if *res_a > res_b {
-                    continue;
-                }
-                if !seen_resource_pairs.insert((*res_a, res_b)) {
-                    continue;
-                }
-                if *res_a >= resource_visits.len() || res_b >= resource_visits.len() {
-                    continue;
-                }
+        for (res_a, res_b) in problem.conflicts.iter() {
+            if res_a >= resource_visits.len() || res_b >= resource_visits.len() {
+                continue;
+            }
 
-                if *res_a == res_b {
-                    let visits_list = resource_visits[*res_a].clone();
-                    for i in 0..visits_list.len() {
-                        for j in (i + 1)..visits_list.len() {
-                            try_seed_pair(
-                                visits_list[i],
-                                visits_list[j],
-                                &mut solver,
-                                &mut occupations,
-                                &mut new_time_points,
-                                &mut fixed_prec_rows,
-                                &mut seed_active_cache,
-                                &mut n_seeded,
-                            );
-                        }
+            if res_a == res_b {
+                let visits_list = resource_visits[res_a].clone();
+                for i in 0..visits_list.len() {
+                    for j in (i + 1)..visits_list.len() {
+                        try_seed_pair(
+                            visits_list[i],
+                            visits_list[j],
+                            &mut solver,
+                            &mut occupations,
+                            &mut new_time_points,
+                            &mut fixed_prec_rows,
+                            &mut seed_active_cache,
+                            &mut n_seeded,
+                        );
                     }
-                } else {
-                    let visits_a = resource_visits[*res_a].clone();
-                    let visits_b = resource_visits[res_b].clone();
-                    for &v1 in &visits_a {
-                        for &v2 in &visits_b {
-                            try_seed_pair(
-                                v1,
-                                v2,
-                                &mut solver,
-                                &mut occupations,
-                                &mut new_time_points,
-                                &mut fixed_prec_rows,
-                                &mut seed_active_cache,
-                                &mut n_seeded,
-                            );
-                        }
+                }
+            } else {
+                let visits_a = resource_visits[res_a].clone();
+                let visits_b = resource_visits[res_b].clone();
+                for &v1 in &visits_a {
+                    for &v2 in &visits_b {
+                        try_seed_pair(
+                            v1,
+                            v2,
+                            &mut solver,
+                            &mut occupations,
+                            &mut new_time_points,
+                            &mut fixed_prec_rows,
+                            &mut seed_active_cache,
+                            &mut n_seeded,
+                        );
                     }
                 }
             }
@@ -1780,8 +1845,9 @@ fn solve_native_debug_with_mode(
         );
     }
 
+    // The heuristic does not keep the primary bound.
     const USE_INITIAL_HEURISTIC_UB_ONLY: bool = false;
-    if USE_INITIAL_HEURISTIC_UB_ONLY {
+    if USE_INITIAL_HEURISTIC_UB_ONLY && settings.primary_bound.is_none() {
         if let Some((ub_cost, ub_sol)) =
             compute_initial_heuristic_upper_bound(&mk_env, problem, delay_cost_type, &occupations)?
         {
@@ -1909,6 +1975,64 @@ fn solve_native_debug_with_mode(
                 }
             }
 
+            // Maximum travel time check: leaving at `t_out` requires having
+            // entered no earlier than `t_out - max_travel_time`.
+            for (visit_id, max_travel_time) in bounded_visits.iter().copied() {
+                let next_visit: VisitId = (usize::from(visit_id) + 1).into();
+                let t_in = occupations[visit_id].incumbent_time();
+                let t_out = occupations[next_visit].incumbent_time();
+                if t_out - t_in <= max_travel_time {
+                    continue;
+                }
+                found_travel_time_conflict = true;
+                stats.n_travel += 1;
+
+                let out_occ = &occupations[next_visit];
+                let out_lit = out_occ.delays[out_occ.incumbent_idx].0;
+                let in_lit = get_delay_lit_at(
+                    &mut solver,
+                    problem,
+                    &visits,
+                    &mut occupations,
+                    &mut new_time_points,
+                    &mut fixed_prec_rows,
+                    prec,
+                    visit_id,
+                    t_out - max_travel_time,
+                );
+                solver.add_clause(vec![!out_lit, in_lit]);
+            }
+
+            // Connections, turnarounds and fixed orders are precedence rows
+            // between the ladders of the arriving and the departing visit.
+            for (connection_idx, connection) in precedences.iter().enumerate() {
+                let from = train_visit_ids[connection.from.0][connection.from.1];
+                let to = train_visit_ids[connection.to.0][connection.to.1];
+                let t_from = occupations[from].incumbent_time();
+                let t_required = t_from + connection.min_transfer;
+                if occupations[to].incumbent_time() >= t_required
+                    || !connection_rows.insert((connection_idx, t_from))
+                {
+                    continue;
+                }
+                found_travel_time_conflict = true;
+                stats.n_travel += 1;
+
+                let from_lit = occupations[from].delays[occupations[from].incumbent_idx].0;
+                let to_lit = get_delay_lit_at(
+                    &mut solver,
+                    problem,
+                    &visits,
+                    &mut occupations,
+                    &mut new_time_points,
+                    &mut fixed_prec_rows,
+                    prec,
+                    to,
+                    t_required,
+                );
+                solver.add_clause(vec![!from_lit, to_lit]);
+            }
+
             #[derive(Clone, Copy)]
             struct ActiveInterval {
                 visit_id: VisitId,
@@ -1928,13 +2052,12 @@ fn solve_native_debug_with_mode(
             for &visit_id in &touched_intervals {
                 let (train_idx, visit_idx) = visits[visit_id];
                 let resource = problem.trains[train_idx].visits[visit_idx].resource_id;
-                if let Some(conflicting_resources) = conflicts.get(&resource) {
-                    for &other in conflicting_resources {
-                        if resource <= other {
-                            relevant_resource_pairs.insert((resource, other));
-                        } else {
-                            relevant_resource_pairs.insert((other, resource));
-                        }
+                let conflicting_resources = problem.conflicts.neighbours(resource);
+                for &other in conflicting_resources {
+                    if resource <= other {
+                        relevant_resource_pairs.insert((resource, other));
+                    } else {
+                        relevant_resource_pairs.insert((other, resource));
                     }
                 }
             }
@@ -2033,6 +2156,43 @@ fn solve_native_debug_with_mode(
                         resource_b,
                     ));
                 }
+
+                // Headways: the cliques above only cover occupations that
+                // overlap, so pairs that are too close are added as 2-member
+                // candidates with each occupation extended by the headway.
+                if !problem.headways.is_empty() {
+                    for (i, mi) in group_intervals.iter().enumerate() {
+                        for mj in group_intervals[i + 1..].iter() {
+                            if mi.train_idx == mj.train_idx
+                                || !(touched_set.contains(&mi.visit_id)
+                                    || touched_set.contains(&mj.visit_id))
+                            {
+                                continue;
+                            }
+                            let headway =
+                                problem.headway(visits[mi.visit_id], visits[mj.visit_id]);
+                            if headway <= 0
+                                || mi.end + headway <= mj.start
+                                || mj.end + headway <= mi.start
+                            {
+                                continue;
+                            }
+                            let members = vec![
+                                ActiveInterval {
+                                    end: mi.end + headway,
+                                    ..*mi
+                                },
+                                ActiveInterval {
+                                    end: mj.end + headway,
+                                    ..*mj
+                                },
+                            ];
+                            let tau = mi.start.max(mj.start);
+                            let severity = 2 * (mi.end.min(mj.end) + headway - tau).max(1) as i64;
+                            clique_candidates.push((severity, members, tau, resource_a, resource_b));
+                        }
+                    }
+                }
             }
 
             // Sort by severity descending — the most "dangerous" cliques (largest
@@ -2207,9 +2367,128 @@ fn solve_native_debug_with_mode(
             }
             touched_intervals = new_touched;
 
+            // Cumulative resources are not in the conflict graph: wherever
+            // more than `capacity` of their occupations are active at the
+            // same tau, at most `capacity` of them may stay active.
+            for (&resource_id, &capacity) in problem.capacities.iter() {
+                let Some(on_resource) = resource_visits.get(resource_id) else {
+                    continue;
+                };
+                let intervals = on_resource
+                    .iter()
+                    .map(|&visit_id| {
+                        let (train_idx, visit_idx) = visits[visit_id];
+                        let t_in = occupations[visit_id].incumbent_time();
+                        let t_out = if visit_idx + 1 < problem.trains[train_idx].visits.len() {
+                            occupations[VisitId::from(usize::from(visit_id) + 1)].incumbent_time()
+                        } else {
+                            t_in + problem.trains[train_idx].visits[visit_idx].travel_time
+                        };
+                        (t_in, t_out, visit_id)
+                    })
+                    .collect();
+                for (tau, mut members) in super::shared::capacity::over_capacity(intervals, capacity)
+                {
+                    members.sort_by_key(|v| v.0);
+                    if !capacity_rows.insert((members.clone(), tau)) {
+                        continue;
+                    }
+                    found_resource_conflict = true;
+                    stats.n_conflict += 1;
+
+                    let mut active_lits = Vec::with_capacity(members.len());
+                    for &v in &members {
+                        active_lits.push(build_active_lit(
+                            &mut solver,
+                            problem,
+                            &visits,
+                            &mut occupations,
+                            &mut new_time_points,
+                            &mut fixed_prec_rows,
+                            &mut active_lit_cache,
+                            prec,
+                            v,
+                            tau + 1,
+                        ));
+                    }
+                    add_at_most_k(&mut solver, &active_lits, capacity, settings.use_sc_amo);
+                    n_conflict_constraints += 1;
+                }
+            }
+
+            // A blocked interval is a fixed occupation: a visit that overlaps
+            // it must leave before the interval starts or enter after it ends.
+            if !problem.blocked.is_empty() {
+                for visit_id in visits.keys() {
+                    let (train_idx, visit_idx) = visits[visit_id];
+                    let visit = problem.trains[train_idx].visits[visit_idx];
+                    let has_next = visit_idx + 1 < problem.trains[train_idx].visits.len();
+                    let t_in = occupations[visit_id].incumbent_time();
+                    let t_out = if has_next {
+                        occupations[VisitId::from(usize::from(visit_id) + 1)].incumbent_time()
+                    } else {
+                        t_in + visit.travel_time
+                    };
+                    let Some((_, &blocked)) =
+                        problem.blocking_interval(visit.resource_id, t_in, t_out)
+                    else {
+                        continue;
+                    };
+                    found_resource_conflict = true;
+                    stats.n_conflict += 1;
+
+                    let (out_visit, out_t) = if has_next {
+                        (VisitId::from(usize::from(visit_id) + 1), blocked.from + 1)
+                    } else {
+                        (visit_id, blocked.from - visit.travel_time + 1)
+                    };
+                    let mut lits = Vec::with_capacity(2);
+                    for (v, t) in [(visit_id, blocked.to), (out_visit, out_t)] {
+                        lits.push(get_delay_lit_at(
+                            &mut solver,
+                            problem,
+                            &visits,
+                            &mut occupations,
+                            &mut new_time_points,
+                            &mut fixed_prec_rows,
+                            prec,
+                            v,
+                            t,
+                        ));
+                    }
+                    // Enter after the interval, or leave before it.
+                    solver.add_clause(vec![lits[0], !lits[1]]);
+                    n_conflict_constraints += 1;
+                }
+            }
+
+            // Earliness check: the earliness cost of a time point is only
+            // counted below the next time point, so an early incumbent needs
+            // the time point right after it to be charged exactly.
+            let mut found_earliness_gap = false;
+            for (visit_id, aimed) in early_visits.iter().copied() {
+                let occ = &occupations[visit_id];
+                let t = occ.incumbent_time();
+                if t >= aimed || occ.delays[occ.incumbent_idx + 1].1 == t + 1 {
+                    continue;
+                }
+                found_earliness_gap = true;
+                get_delay_lit_at(
+                    &mut solver,
+                    problem,
+                    &visits,
+                    &mut occupations,
+                    &mut new_time_points,
+                    &mut fixed_prec_rows,
+                    prec,
+                    visit_id,
+                    t + 1,
+                );
+            }
+
             let iterationtype = if found_travel_time_conflict && found_resource_conflict {
                 IterationType::TravelAndResourceConflict
-            } else if found_travel_time_conflict {
+            } else if found_travel_time_conflict || found_earliness_gap {
                 IterationType::TravelTimeConflict
             } else if found_resource_conflict {
                 IterationType::ResourceConflict
@@ -2218,7 +2497,7 @@ fn solve_native_debug_with_mode(
             };
             *iteration_types.entry(iterationtype).or_default() += 1;
 
-            if !(found_resource_conflict || found_travel_time_conflict) {
+            if !(found_resource_conflict || found_travel_time_conflict || found_earliness_gap) {
                 let sol = extract_solution(problem, &occupations);
                 let cost = problem.cost(&sol, delay_cost_type);
                 if trace_bound_queries {
@@ -2315,6 +2594,32 @@ fn solve_native_debug_with_mode(
             }
         }
 
+        // Fixed order rows from the new time points, including the ones that
+        // these rows add.
+        let mut point_idx = 0;
+        while point_idx < new_time_points.len() {
+            let (visit, lit, t) = new_time_points[point_idx];
+            point_idx += 1;
+            for &connection_idx in order_rows_from.get(&visit).into_iter().flatten() {
+                if !connection_rows.insert((connection_idx, t)) {
+                    continue;
+                }
+                let connection = &precedences[connection_idx];
+                let to_lit = get_delay_lit_at(
+                    &mut solver,
+                    problem,
+                    &visits,
+                    &mut occupations,
+                    &mut new_time_points,
+                    &mut fixed_prec_rows,
+                    prec,
+                    train_visit_ids[connection.to.0][connection.to.1],
+                    t + connection.min_transfer,
+                );
+                solver.add_clause(vec![!lit, to_lit]);
+            }
+        }
+
         for (visit, new_timepoint_var, new_t) in new_time_points.drain(..) {
             n_timepoints += 1;
             let (train_idx, visit_idx) = visits[visit];
@@ -2365,6 +2670,69 @@ fn solve_native_debug_with_mode(
                     }
                 }
             }
+
+            if let Some(bound) = settings.primary_bound {
+                let primary_cost = problem.trains[train_idx].visit_delay_cost(
+                    bound.cost_type,
+                    visit_idx,
+                    new_t,
+                );
+                if primary_cost > 0 {
+                    primary_trees[visit].add_cost(
+                        &mut solver,
+                        new_timepoint_var,
+                        primary_cost,
+                        &mut |weight, cost_var| {
+                            let lit = cost_var
+                                .lit()
+                                .expect("CostTree produced a non-literal primary bound term");
+                            primary_terms.push((lit, weight));
+                        },
+                    );
+                }
+            }
+
+            // Being before this time point means entering at most at
+            // `new_t - 1`, which costs at least that much earliness.
+            let earliness_cost =
+                problem.trains[train_idx].visit_earliness_cost(visit_idx, new_t - 1);
+            if earliness_cost > 0 && new_t > occupations[visit].delays[0].1 {
+                let has_primary_bound = settings.primary_bound.is_some();
+                earliness_trees[visit].add_cost(
+                    &mut solver,
+                    !new_timepoint_var,
+                    earliness_cost,
+                    &mut |weight, cost_var| {
+                        let lit = cost_var
+                            .lit()
+                            .expect("CostTree produced a non-literal earliness term");
+                        if has_primary_bound {
+                            primary_terms.push((lit, weight));
+                        }
+                        match encoding {
+                            SatObjectiveEncoding::Scpb => {
+                                scpb_terms.push((lit, weight));
+                                scpb_total_weight = scpb_total_weight.saturating_add(weight);
+                            }
+                            SatObjectiveEncoding::IncrementalTotalizer => {
+                                budget_gte.extend([(lit, weight)]);
+                            }
+                            SatObjectiveEncoding::BitTotalizer => {
+                                bit_totalizer.add_term(lit, weight);
+                            }
+                        }
+                    },
+                );
+            }
+        }
+
+        // The primary bound is encoded again over all terms whenever new
+        // ones were added, like the SCPB bound in `AddClauses` mode.
+        if let Some(bound) = settings.primary_bound {
+            if primary_encoded_terms < primary_terms.len() {
+                encode_scpb_leq(&mut solver, &primary_terms, bound.cost.max(0) as usize, None);
+                primary_encoded_terms = primary_terms.len();
+            }
         }
 
         if search == SatSearchMode::UbSearch {
@@ -2763,6 +3131,30 @@ fn add_fixed_precedence_row<L: satcoder::Lit>(
     }
 
     let (train_idx, visit_idx) = visits[visit_id];
+
+    // Entering at `in_t` or later means having entered the previous visit
+    // no earlier than its maximum travel time before.
+    let max_travel_time = visit_idx
+        .checked_sub(1)
+        .and_then(|prev_idx| problem.trains[train_idx].visits[prev_idx].max_travel_time);
+    if let Some(max_travel_time) = max_travel_time {
+        let prev_visit: VisitId = (usize::from(visit_id) - 1).into();
+        let req_t = in_t - max_travel_time;
+        let (earliest_prev, last_prev) = {
+            let occ = &occupations[prev_visit];
+            (occ.delays[0].1, occ.delays[occ.delays.len() - 1].1)
+        };
+        if req_t >= last_prev {
+            solver.add_clause(vec![!in_var]);
+        } else if req_t > earliest_prev {
+            let (req_var, is_new) = occupations[prev_visit].time_point(solver, req_t);
+            solver.add_clause(vec![!in_var, req_var]);
+            if is_new {
+                new_time_points.push((prev_visit, req_var, req_t));
+            }
+        }
+    }
+
     if visit_idx + 1 >= problem.trains[train_idx].visits.len() {
         return None;
     }
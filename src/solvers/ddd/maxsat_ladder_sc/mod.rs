@@ -20,4 +20,7 @@ mod settings;
 mod solve;
 
 pub use settings::MaxSatDddLadderScSettings;
-pub use solve::{solve, solve_debug, solve_debug_with_settings, solve_with_settings, IterationType, SolveStats};
+pub use solve::{
+    solve, solve_debug, solve_debug_with_settings, solve_scenarios, solve_with_settings,
+    IterationType, SolveStats,
+};
@@ -2,10 +2,13 @@
 //!
 //! `add_fixed_precedence_row` encodes one within-train travel-time
 //! implication `d^{i,r}(t) → d^{i,q}(t + l^{r}_i)` between two consecutive
-//! visits of the same train.
+//! visits of the same train. If the previous visit `p` has a maximum travel
+//! time `u^{p}_i`, it also encodes `d^{i,r}(t) → d^{i,p}(t - u^{p}_i)`.
 //!
 //! `propagate_precedence` walks the chain forward from a starting visit,
-//! eagerly emitting all reachable fixed-precedence rows in one pass.
+//! eagerly emitting all reachable fixed-precedence rows in one pass. At the
+//! last visit of a train it continues along the rolling-stock turnarounds
+//! (`add_turnaround_rows`) to the first visit of the trainset's next train.
 
 use std::collections::{HashSet, VecDeque};
 
@@ -36,6 +39,30 @@ pub(super) fn add_fixed_precedence_row<L: satcoder::Lit>(
 
     //check if (v, t) is already added -> ignore
     let (train_idx, visit_idx) = visits[visit_id];
+
+    // Entering at `in_t` or later means having entered the previous visit
+    // no earlier than its maximum travel time before.
+    let max_travel_time = visit_idx
+        .checked_sub(1)
+        .and_then(|prev_idx| problem.trains[train_idx].visits[prev_idx].max_travel_time);
+    if let Some(max_travel_time) = max_travel_time {
+        let prev_visit: VisitId = (usize::from(visit_id) - 1).into();
+        let req_t = in_t - max_travel_time;
+        let (earliest_prev, last_prev) = {
+            let occ = &occupations[prev_visit];
+            (occ.delays[0].1, occ.delays[occ.delays.len() - 1].1)
+        };
+        if req_t >= last_prev {
+            solver.add_clause(vec![!in_var]);
+        } else if req_t > earliest_prev {
+            let (req_var, is_new) = occupations[prev_visit].time_point(solver, req_t);
+            solver.add_clause(vec![!in_var, req_var]);
+            if is_new {
+                new_time_points.push((prev_visit, req_var, req_t));
+            }
+        }
+    }
+
     if visit_idx + 1 >= problem.trains[train_idx].visits.len() {
         return None;
     }
@@ -86,6 +113,52 @@ pub(super) fn add_fixed_precedence_row<L: satcoder::Lit>(
     Some((next_visit, req_var, req_t))
 }
 
+/// Add the turnaround rows `d^{A,last}(t) → d^{B,0}(t + l^{last}_A + turnaround)`
+/// for the trains `B` that the trainset of `A` runs next, and return the
+/// successor time points for further propagation.
+pub(super) fn add_turnaround_rows<L: satcoder::Lit>(
+    solver: &mut impl SatInstance<L>,
+    problem: &Problem,
+    visits: &TiVec<VisitId, (usize, usize)>,
+    occupations: &mut TiVec<VisitId, Occ<L>>,
+    new_time_points: &mut Vec<(VisitId, Bool<L>, i32)>,
+    visit_id: VisitId,
+    in_var: Bool<L>,
+    in_t: i32,
+) -> Vec<(VisitId, Bool<L>, i32)> {
+    let (train_idx, visit_idx) = visits[visit_id];
+    let mut next = Vec::new();
+    for turnaround in problem.turnarounds.iter().filter(|t| t.from == train_idx) {
+        let connection = problem.turnaround_connection(turnaround);
+        debug_assert_eq!(connection.from, (train_idx, visit_idx));
+        let first_visit: VisitId = problem.trains[..turnaround.to]
+            .iter()
+            .map(|train| train.visits.len())
+            .sum::<usize>()
+            .into();
+        let req_t = in_t + connection.min_transfer;
+        let (earliest, last) = {
+            let occ = &occupations[first_visit];
+            (occ.delays[0].1, occ.delays[occ.delays.len() - 1].1)
+        };
+        if req_t <= earliest {
+            continue;
+        }
+        if req_t >= last {
+            // The next train cannot start in time at all.
+            solver.add_clause(vec![!in_var]);
+            continue;
+        }
+        let (req_var, is_new) = occupations[first_visit].time_point(solver, req_t);
+        solver.add_clause(vec![!in_var, req_var]);
+        if is_new {
+            new_time_points.push((first_visit, req_var, req_t));
+        }
+        next.push((first_visit, req_var, req_t));
+    }
+    next
+}
+
 pub(super) fn propagate_precedence<L: satcoder::Lit>(
     solver: &mut impl SatInstance<L>,
     problem: &Problem,
@@ -101,6 +174,22 @@ pub(super) fn propagate_precedence<L: satcoder::Lit>(
     let mut queue = VecDeque::from([(start_visit, start_var, start_t)]);
 
     while let Some((visit_id, in_var, in_t)) = queue.pop_front() {
+        let (train_idx, visit_idx) = visits[visit_id];
+        if visit_idx + 1 == problem.trains[train_idx].visits.len() {
+            if added.insert((visit_id, in_t)) {
+                queue.extend(add_turnaround_rows(
+                    solver,
+                    problem,
+                    visits,
+                    occupations,
+                    new_time_points,
+                    visit_id,
+                    in_var,
+                    in_t,
+                ));
+            }
+            continue;
+        }
         if let Some(next) = add_fixed_precedence_row(
             solver,
             problem,
@@ -2,11 +2,12 @@
 //!
 //! `add_sc_amo` emits the SC AMO encoding (Sinz 2005). `add_pairwise_amo` is the legacy
 //! O(n²) pairwise encoding for comparison. `add_hybrid_amo` dispatches
-//! between the two based on clique size (`PAIRWISE_AMO_MAX_SIZE`).
+//! between the two based on clique size (`PAIRWISE_AMO_MAX_SIZE`). `add_at_most_k`
+//! generalises this to the capacity of cumulative resources.
 
 use std::collections::{HashMap, HashSet};
 
-use satcoder::{Bool, SatInstance};
+use satcoder::{constraints::Totalizer, Bool, SatInstance};
 use typed_index_collections::TiVec;
 
 use crate::problem::Problem;
@@ -69,6 +70,31 @@ pub(super) fn add_hybrid_amo<L: satcoder::Lit>(
     }
 }
 
+/// At-most-`k` over `lits`, used for the capacity of cumulative resources.
+/// `k == 1` goes through [`add_hybrid_amo`], larger bounds use a totalizer.
+pub(super) fn add_at_most_k<L: satcoder::Lit>(
+    solver: &mut impl SatInstance<L>,
+    lits: &[Bool<L>],
+    k: usize,
+    use_sc_amo: bool,
+) {
+    if lits.len() <= k {
+        return;
+    }
+    match k {
+        0 => {
+            for lit in lits {
+                solver.add_clause(vec![!*lit]);
+            }
+        }
+        1 => add_hybrid_amo(solver, lits, use_sc_amo),
+        _ => {
+            let tot = Totalizer::count(solver, lits.iter().copied(), k as u32);
+            solver.add_clause(vec![!tot.rhs()[k]]); // tot <= k
+        }
+    }
+}
+
 /// Monotone delay literal `visit_id.start ≥ t`.
 /// Returns `true.into()` if t ≤ earliest (always satisfied),
 /// `false.into()` if t ≥ infinity sentinel (never satisfied),
@@ -1,9 +1,18 @@
 //! Configuration for `maxsat_ladder_sc`.
+use crate::problem::{BufferObjective, CostBound};
+
 #[derive(Clone, Copy, Debug)]
 pub struct MaxSatDddLadderScSettings {
     pub use_precedence_graph: bool,
     pub use_sc_amo: bool,
     pub use_touched_clique_amo: bool,
+    /// Once the delay cost is optimal, keep it as a bound and maximise the
+    /// buffers between occupations of conflicting resources: the smallest
+    /// by adding it to every headway, or the total as the objective.
+    pub buffer_objective: Option<BufferObjective>,
+    /// Keep the cost under another cost type at most the bound as a hard
+    /// constraint, for lexicographic optimisation.
+    pub primary_bound: Option<CostBound>,
 
     // ── Alternative / experimental knobs (default OFF) ───────────────────
     /// Eagerly expand long travel-time precedence chains into per-step
@@ -23,6 +32,8 @@ impl Default for MaxSatDddLadderScSettings {
             use_eager_chain_expansion: false,
             use_sc_amo: true,
             use_touched_clique_amo: true,
+            buffer_objective: None,
+            primary_bound: None,
             seed_sc_from_earliest: false,
             prealloc_cost_thresholds: false,
         }
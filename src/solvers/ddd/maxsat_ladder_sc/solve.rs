@@ -8,7 +8,7 @@ use std::{
 use crate::{
     debug::{ResourceInterval, SolverAction},
     minimize_core,
-    problem::Problem,
+    problem::{BufferKind, Problem},
     trim_core,
 };
 use satcoder::{
@@ -88,7 +88,7 @@ pub struct SolveStats {
 // SC AMO helpers moved to `super::sc_amo`.
 // Precedence helpers moved to `super::precedence`.
 use super::precedence::{add_fixed_precedence_row, propagate_precedence};
-use super::sc_amo::{add_hybrid_amo, build_active_lit, get_delay_lit_at};
+use super::sc_amo::{add_at_most_k, add_hybrid_amo, build_active_lit, get_delay_lit_at};
 use super::settings::MaxSatDddLadderScSettings;
 
 enum Soft<L: satcoder::Lit> {
@@ -104,6 +104,133 @@ fn bits_needed(max_value: usize) -> usize {
     }
 }
 
+/// A literal implied by `lit` unless the train's `cancel` literal is true,
+/// for constraints that a cancelled train does not take part in.
+fn unless_cancelled<L: satcoder::Lit>(
+    solver: &mut impl SatInstance<L>,
+    lit: Bool<L>,
+    cancel: Option<Bool<L>>,
+) -> Bool<L> {
+    match cancel {
+        None => lit,
+        Some(cancel) => {
+            let kept = solver.new_var();
+            solver.add_clause(vec![!lit, cancel, kept]);
+            kept
+        }
+    }
+}
+
+/// The incumbent `(t_in, t_out)` of a visit.
+fn incumbent_interval<L: satcoder::Lit>(
+    problem: &Problem,
+    visits: &TiVec<VisitId, (usize, usize)>,
+    occupations: &TiVec<VisitId, Occ<L>>,
+    visit_id: VisitId,
+) -> (i32, i32) {
+    let (train_idx, visit_idx) = visits[visit_id];
+    let t_in = occupations[visit_id].incumbent_time();
+    let t_out = if visit_idx + 1 < problem.trains[train_idx].visits.len() {
+        occupations[VisitId::from(usize::from(visit_id) + 1)].incumbent_time()
+    } else {
+        t_in + problem.trains[train_idx].visits[visit_idx].travel_time
+    };
+    (t_in, t_out)
+}
+
+/// Adds a cost of `weight` for `lit` being false: as a soft constraint, or
+/// to the `group` of the objective register when minimising the worst case.
+/// A literal that is already soft gets the weight added to it. Soft
+/// constraints are also recorded in `cost_terms`.
+fn add_soft_cost<L: satcoder::Lit + Copy + 'static>(
+    solver: &mut impl SatInstance<L>,
+    soft_constraints: &mut HashMap<Bool<L>, (Soft<L>, usize, usize)>,
+    cost_terms: &mut Vec<(Bool<L>, usize)>,
+    worst_case: &mut Option<BinaryObjective<L>>,
+    group: usize,
+    lit: Bool<L>,
+    weight: usize,
+) {
+    match worst_case {
+        Some(objective) => objective.add_term(solver, group, !lit, weight),
+        None => {
+            let (_, w, original_w) = soft_constraints.entry(lit).or_insert((Soft::Primary, 0, 0));
+            *w += weight;
+            *original_w += weight;
+            cost_terms.push((lit, weight));
+        }
+    }
+}
+
+/// The order in which two visits pass in the incumbent: whether `first`
+/// leaves, and the headway passes, before `second` enters. `None` if they
+/// overlap.
+fn incumbent_order<L: satcoder::Lit>(
+    problem: &Problem,
+    visits: &TiVec<VisitId, (usize, usize)>,
+    occupations: &TiVec<VisitId, Occ<L>>,
+    first: VisitId,
+    second: VisitId,
+) -> Option<bool> {
+    let headway = problem.headway(visits[first], visits[second]);
+    let (a_in, a_out) = incumbent_interval(problem, visits, occupations, first);
+    let (b_in, b_out) = incumbent_interval(problem, visits, occupations, second);
+    match (b_in >= a_out + headway, a_in >= b_out + headway) {
+        (true, false) => Some(true),
+        (false, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// Adds the row "if `order` holds and `first` leaves at its incumbent time
+/// or later, `second` enters no earlier than that plus the headway", unless
+/// it was added before. Returns whether the row is new.
+#[allow(clippy::too_many_arguments)]
+fn add_order_row<L: satcoder::Lit>(
+    solver: &mut impl SatInstance<L>,
+    problem: &Problem,
+    visits: &TiVec<VisitId, (usize, usize)>,
+    occupations: &mut TiVec<VisitId, Occ<L>>,
+    new_time_points: &mut Vec<(VisitId, Bool<L>, i32)>,
+    fixed_prec_rows: &mut HashSet<(VisitId, i32)>,
+    use_eager_chain_expansion: bool,
+    order_rows: &mut HashSet<(VisitId, VisitId, i32)>,
+    cancel_lits: &[Option<Bool<L>>],
+    order: Bool<L>,
+    first: VisitId,
+    second: VisitId,
+) -> bool {
+    let (train_idx, visit_idx) = visits[first];
+    let (_, t_out) = incumbent_interval(problem, visits, occupations, first);
+    if !order_rows.insert((first, second, t_out)) {
+        return false;
+    }
+    // Leaving a last visit is entering it plus the travel time.
+    let out_visit = if visit_idx + 1 < problem.trains[train_idx].visits.len() {
+        VisitId::from(usize::from(first) + 1)
+    } else {
+        first
+    };
+    let out_lit = occupations[out_visit].delays[occupations[out_visit].incumbent_idx].0;
+    let headway = problem.headway(visits[first], visits[second]);
+    let second_lit = get_delay_lit_at(
+        solver,
+        problem,
+        visits,
+        occupations,
+        new_time_points,
+        fixed_prec_rows,
+        use_eager_chain_expansion,
+        second,
+        t_out + headway,
+    );
+    let mut clause = vec![!order, !out_lit, second_lit];
+    clause.extend(cancel_lits[train_idx]);
+    clause.extend(cancel_lits[visits[second].0]);
+    solver.add_clause(clause);
+    true
+}
+
 fn build_binary_register<L: satcoder::Lit + Copy + 'static>(reg_bits: &[Bool<L>]) -> Binary<L> {
     Binary::from_list(reg_bits.iter().copied())
 }
@@ -234,32 +361,50 @@ fn assert_binary_le<L: satcoder::Lit + Copy + 'static>(
     solver.add_clause(vec![le]);
 }
 
+/// A binary register whose value is the objective, as soft bits of weight
+/// `2^k`. The terms of each group are subtracted from their own copy of the
+/// register, which may not underflow, so the register is at least the
+/// largest group sum.
 struct BinaryObjective<L: satcoder::Lit> {
     reg_bits: Vec<Bool<L>>,
-    remaining: Option<Binary<L>>,
+    remaining: Vec<Option<Binary<L>>>,
 }
 
 impl<L: satcoder::Lit + Copy + 'static> BinaryObjective<L> {
-    fn new() -> Self {
+    fn new(n_groups: usize) -> Self {
         Self {
             reg_bits: Vec::new(),
-            remaining: None,
+            remaining: vec![None; n_groups],
         }
     }
 
-    fn add_term(&mut self, solver: &mut impl SatInstance<L>, lit: Bool<L>, weight: usize) {
+    /// A single group whose terms may add up to at most `bound`, without
+    /// a register in the objective.
+    fn bounded(bound: usize) -> Self {
+        Self {
+            reg_bits: Vec::new(),
+            remaining: vec![Some(Binary::constant(bound))],
+        }
+    }
+
+    fn add_term(
+        &mut self,
+        solver: &mut impl SatInstance<L>,
+        group: usize,
+        lit: Bool<L>,
+        weight: usize,
+    ) {
         if weight == 0 || lit == false.into() {
             return;
         }
 
         let term = build_weighted_binary_term(lit, weight);
-        let current_remaining = self
-            .remaining
+        let current_remaining = self.remaining[group]
             .clone()
             .expect("binary objective requires an upper bound before adding terms");
         let (next_remaining, underflow) = subtract_binary(solver, &current_remaining, &term);
         solver.add_clause(vec![!underflow]);
-        self.remaining = Some(next_remaining);
+        self.remaining[group] = Some(next_remaining);
     }
 
     fn ensure_capacity(
@@ -281,20 +426,33 @@ impl<L: satcoder::Lit + Copy + 'static> BinaryObjective<L> {
             }
         }
 
-        if old_len == self.reg_bits.len() {
-            if self.remaining.is_none() {
-                self.remaining = Some(build_binary_register(&self.reg_bits));
+        for remaining in self.remaining.iter_mut() {
+            if old_len == self.reg_bits.len() {
+                if remaining.is_none() {
+                    *remaining = Some(build_binary_register(&self.reg_bits));
+                }
+            } else if let Some(group_remaining) = remaining.take() {
+                let mut bits = group_remaining.into_list();
+                bits.extend(self.reg_bits[old_len..].iter().copied());
+                *remaining = Some(Binary::from_list(bits));
+            } else {
+                *remaining = Some(build_binary_register(&self.reg_bits));
             }
-        } else if let Some(remaining) = self.remaining.take() {
-            let mut bits = remaining.into_list();
-            bits.extend(self.reg_bits[old_len..].iter().copied());
-            self.remaining = Some(Binary::from_list(bits));
-        } else {
-            self.remaining = Some(build_binary_register(&self.reg_bits));
         }
     }
 }
 
+/// The state of the buffer phase, see
+/// [`MaxSatDddLadderScSettings::buffer_objective`].
+struct BufferPhase<L: satcoder::Lit> {
+    /// The schedule with the largest buffer so far.
+    best: Vec<Vec<i32>>,
+    /// For the smallest buffer, the buffer being tried, which is added to
+    /// every headway, and the literal that enables its conflict rows. The
+    /// total buffer is the objective of the phase instead.
+    target: Option<(i32, Bool<L>)>,
+}
+
 #[allow(dead_code)]
 fn compute_initial_heuristic_upper_bound<L: satcoder::Lit>(
     mk_env: &impl Fn() -> grb::Env,
@@ -313,8 +471,10 @@ fn compute_initial_heuristic_upper_bound<L: satcoder::Lit>(
             use_strong_branching,
             Some(&initial_solution),
         )? {
-            let ub_cost = problem.verify_solution(&ub_sol, delay_cost_type).unwrap();
-            return Ok(Some((ub_cost, ub_sol)));
+            // The heuristic ignores `latest` bounds, so its schedule may be rejected.
+            if let Some(ub_cost) = problem.verify_solution(&ub_sol, delay_cost_type) {
+                return Ok(Some((ub_cost, ub_sol)));
+            }
         }
     }
 
@@ -368,7 +528,11 @@ pub fn solve_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'static>(
 
 thread_local! { pub static  WATCH : std::cell::RefCell<Option<(usize,usize)>>  = RefCell::new(None);}
 
-use crate::{debug::DebugInfo, problem::DelayCostType, solvers::util::heuristic};
+use crate::{
+    debug::DebugInfo,
+    problem::DelayCostType,
+    solvers::util::{heuristic, scenarios::ScenarioExpansion},
+};
 
 use crate::solvers::{ddd::shared::costtree::CostTree, SolverError};
 pub fn solve_debug<L: satcoder::Lit + Copy + std::fmt::Debug>(
@@ -396,12 +560,65 @@ where
 }
 
 pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'static>(
+    mk_env: impl Fn() -> grb::Env + Send + 'static,
+    solver: impl SatInstance<L> + SatSolverWithCore<Lit = L> + std::fmt::Debug,
+    problem: &Problem,
+    timeout: f64,
+    delay_cost_type: DelayCostType,
+    settings: MaxSatDddLadderScSettings,
+    debug_out: impl Fn(DebugInfo),
+    output_stats: impl FnMut(String, serde_json::Value),
+) -> Result<(Vec<Vec<i32>>, SolveStats), SolverError> {
+    solve_expanded(
+        mk_env,
+        solver,
+        problem,
+        timeout,
+        delay_cost_type,
+        settings,
+        None,
+        debug_out,
+        output_stats,
+    )
+}
+
+/// Solves a scenario-expanded problem (see
+/// [`expand_scenarios`](crate::solvers::util::scenarios::expand_scenarios)),
+/// in which the copies of each pair of conflicting visits share an order
+/// literal and the copies of each train share their cancellation literal.
+/// The heuristic upper bounds and the local minimisation of the incumbent
+/// are not used, as they do not keep the copies in the same order.
+pub fn solve_scenarios<L: satcoder::Lit + Copy + std::fmt::Debug + 'static>(
+    mk_env: impl Fn() -> grb::Env + Send + 'static,
+    solver: impl SatInstance<L> + SatSolverWithCore<Lit = L> + std::fmt::Debug,
+    problem: &Problem,
+    timeout: f64,
+    delay_cost_type: DelayCostType,
+    expansion: ScenarioExpansion,
+    output_stats: impl FnMut(String, serde_json::Value),
+) -> Result<(Vec<Vec<i32>>, SolveStats), SolverError> {
+    solve_expanded(
+        mk_env,
+        solver,
+        problem,
+        timeout,
+        delay_cost_type,
+        MaxSatDddLadderScSettings::default(),
+        Some(expansion),
+        |_| {},
+        output_stats,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn solve_expanded<L: satcoder::Lit + Copy + std::fmt::Debug + 'static>(
     mk_env: impl Fn() -> grb::Env + Send + 'static,
     mut solver: impl SatInstance<L> + SatSolverWithCore<Lit = L> + std::fmt::Debug,
     problem: &Problem,
     timeout: f64,
     delay_cost_type: DelayCostType,
     settings: MaxSatDddLadderScSettings,
+    expansion: Option<ScenarioExpansion>,
     debug_out: impl Fn(DebugInfo),
     mut output_stats: impl FnMut(String, serde_json::Value),
 ) -> Result<(Vec<Vec<i32>>, SolveStats), SolverError> {
@@ -423,7 +640,6 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
     let mut resource_visits: Vec<Vec<VisitId>> = Vec::new();
     let mut occupations: TiVec<VisitId, Occ<_>> = TiVec::new();
     let mut touched_intervals = Vec::new();
-    let mut conflicts: HashMap<usize, Vec<usize>> = HashMap::new();
     let mut new_time_points = Vec::new();
     let effective_earliest = settings
         .use_precedence_graph
@@ -438,12 +654,10 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
     let mut n_timepoints = 0;
     let mut n_conflict_constraints = 0;
 
-    //Build conflict adjacency 
-    for (a, b) in problem.conflicts.iter() {
-        conflicts.entry(*a).or_default().push(*b);
-        if *a != *b {
-            conflicts.entry(*b).or_default().push(*a);
-        }
+    // The time ladders below end at each visit's `latest` bound, which
+    // requires the bound to be reachable at all.
+    if !problem.latest_bounds_reachable() {
+        return Err(SolverError::NoSolution);
     }
 
     //Build Occ 
@@ -453,12 +667,14 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
             let earliest = effective_earliest
                 .as_ref()
                 .map(|bounds| bounds[train_idx][visit_idx])
-                .unwrap_or(visit.earliest);
+                .unwrap_or(visit.earliest_start());
 
             occupations.push(Occ {
                 cost: vec![true.into()],
                 cost_tree: CostTree::new(),
-                delays: vec![(true.into(), earliest), (false.into(), i32::MAX)],
+                primary_tree: CostTree::new(),
+                earliness_tree: CostTree::new(),
+                delays: vec![(true.into(), earliest), (false.into(), visit.ladder_end())],
                 incumbent_idx: 0,
             });
             n_timepoints += 1;
@@ -497,14 +713,152 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
 
     let mut total_cost = 0;
     let mut soft_constraints = HashMap::new();
+    // The soft constraints of the costs, which are bounded by their optimum
+    // instead of minimised in the buffer phase.
+    let mut cost_terms = Vec::new();
     let mut debug_actions = Vec::new();
 
+    // A scenario-expanded problem has `n_scenarios` copies of `base_trains`
+    // trains with `base_visits` visits. When minimising the worst case, the
+    // costs of each copy are subtracted from a register of its own.
+    let n_scenarios = expansion.map_or(1, |e| e.n_scenarios);
+    let base_trains = problem.trains.len() / n_scenarios;
+    let base_visits = visits.len() / n_scenarios;
+    let mut worst_case = expansion.and_then(|e| e.worst_case_bound).map(|bound| {
+        let mut objective = BinaryObjective::new(n_scenarios);
+        objective.ensure_capacity(&mut solver, &mut soft_constraints, bound);
+        objective
+    });
+    // The costs under the cost type of the primary bound are subtracted from
+    // the bound. The costs that do not depend on the cost type (earliness,
+    // cancellations and broken connections) count in both.
+    let primary_cost_type = settings.primary_bound.map(|bound| bound.cost_type);
+    let mut primary_bound = settings
+        .primary_bound
+        .map(|bound| BinaryObjective::bounded(bound.cost.max(0) as usize));
+
+    // Trains that may be cancelled get a literal which frees all their
+    // occupations, connections and delay costs at the cancellation cost.
+    // The copies of a train share it. `cancelled` holds its value in the
+    // current model.
+    let mut cancel_lits: Vec<Option<Bool<L>>> = Vec::with_capacity(problem.trains.len());
+    for (train_idx, train) in problem.trains.iter().enumerate() {
+        let cancel = match train.cancellation_cost {
+            None => None,
+            Some(_) if train_idx >= base_trains => cancel_lits[train_idx % base_trains],
+            Some(_) => Some(SatInstance::new_var(&mut solver)),
+        };
+        if let (Some(cancel), Some(cost)) = (cancel, train.cancellation_cost) {
+            if cost > 0 {
+                if let Some(bound) = primary_bound.as_mut() {
+                    bound.add_term(&mut solver, 0, cancel, cost as usize);
+                }
+                add_soft_cost(
+                    &mut solver,
+                    &mut soft_constraints,
+                    &mut cost_terms,
+                    &mut worst_case,
+                    train_idx / base_trains,
+                    !cancel,
+                    cost as usize,
+                );
+            }
+        }
+        cancel_lits.push(cancel);
+    }
+    let mut cancelled = vec![false; problem.trains.len()];
+
     // Remember which cliques have already had their full AMO encoded
     // (keyed by sorted visit-set) to dedup AMO emission across iterations.
     //SEED PRECEDENCE ROWS
     let mut clique_amo_encoded: HashSet<Vec<VisitId>> = HashSet::new();
+    // Over-capacity sets of cumulative resources already forbidden, keyed by
+    // sorted visit-set and tau.
+    let mut capacity_rows: HashSet<(Vec<VisitId>, i32)> = HashSet::new();
+    // Connections (including turnarounds and fixed orders): the visits they
+    // link, the (connection, arrival time) rows already added, and the
+    // "kept" literal of each soft connection.
+    let connections = problem.precedences().collect::<Vec<_>>();
+    let visit_ids: HashMap<(usize, usize), VisitId> =
+        visits.iter_enumerated().map(|(id, tv)| (*tv, id)).collect();
+    let connection_visits: Vec<(VisitId, VisitId)> = connections
+        .iter()
+        .map(|c| (visit_ids[&c.from], visit_ids[&c.to]))
+        .collect();
+    let mut connection_rows: HashSet<(usize, i32)> = HashSet::new();
+    let mut connection_kept: HashMap<usize, Bool<L>> = HashMap::new();
+    let mut incoming_connections: HashMap<VisitId, Vec<usize>> = HashMap::new();
+    for (connection_idx, (_, to)) in connection_visits.iter().enumerate() {
+        incoming_connections.entry(*to).or_default().push(connection_idx);
+    }
+    // Fixed orders come last in `connections`. They are encoded eagerly:
+    // every time point of the visit that leaves first gets its row to the
+    // visit that follows, and the two visits are left out of the conflict
+    // check.
+    let mut order_rows_from: HashMap<VisitId, Vec<usize>> = HashMap::new();
+    let first_order_row = problem.connections.len() + problem.turnarounds.len();
+    for connection_idx in first_order_row..connections.len() {
+        let (from, _) = connection_visits[connection_idx];
+        order_rows_from.entry(from).or_default().push(connection_idx);
+    }
+    let ordered_visits: HashSet<(VisitId, VisitId)> = problem
+        .ordered_visits()
+        .flat_map(|(_, first, second)| {
+            let (first, second) = (visit_ids[&first], visit_ids[&second]);
+            [(first, second), (second, first)]
+        })
+        .collect();
+    // Visits with a maximum travel time, which are checked in every
+    // iteration as the violation may come from the next visit alone.
+    let bounded_visits: Vec<(VisitId, i32)> = visits
+        .iter_enumerated()
+        .filter_map(|(visit_id, (train_idx, visit_idx))| {
+            let train = &problem.trains[*train_idx];
+            let max_travel_time = train.visits[*visit_idx].max_travel_time?;
+            (*visit_idx + 1 < train.visits.len()).then_some((visit_id, max_travel_time))
+        })
+        .collect();
+    // Visits whose train pays for entering them before their aimed time.
+    let early_visits: Vec<(VisitId, i32)> = visits
+        .iter_enumerated()
+        .filter_map(|(visit_id, (train_idx, visit_idx))| {
+            let train = &problem.trains[*train_idx];
+            train.earliness_cost?;
+            Some((visit_id, train.visits[*visit_idx].aimed?))
+        })
+        .collect();
     // Rows already added for fixed-precedence encoding: (visit_id, time).
     let mut fixed_prec_rows: HashSet<(VisitId, i32)> = HashSet::new();
+    // The pairs of conflicting visits of the first copy of a
+    // scenario-expanded problem, the order literal of each pair that the
+    // copies have disagreed on with its value in the current model (true if
+    // the first visit of the pair goes first), and the order rows already
+    // added: (first, second, first's leaving time).
+    let scenario_pairs: Vec<(usize, usize)> = if expansion.is_some() {
+        (0..base_visits)
+            .flat_map(|a| {
+                let a_id = VisitId::from(a);
+                let (train_idx, visit_idx) = visits[a_id];
+                let resource_id = problem.trains[train_idx].visits[visit_idx].resource_id;
+                let conflicting = problem.conflicts.neighbours(resource_id);
+                let (visits, resource_visits, ordered_visits) =
+                    (&visits, &resource_visits, &ordered_visits);
+                conflicting
+                    .iter()
+                    .flat_map(move |r| resource_visits.get(*r).into_iter().flatten())
+                    .filter(move |b| {
+                        usize::from(**b) > a
+                            && visits[**b].0 != train_idx
+                            && !ordered_visits.contains(&(a_id, **b))
+                    })
+                    .map(move |b| (a, usize::from(*b)))
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let mut order_lits: HashMap<(usize, usize), (Bool<L>, bool)> = HashMap::new();
+    let mut order_rows: HashSet<(VisitId, VisitId, i32)> = HashSet::new();
 
     // Optional: seed fixed-precedence (travel-time) constraints from the earliest
     // time points to reduce the number of "travel-time conflict" iterations.
@@ -551,6 +905,10 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
     // result flows in via `heur_thread`'s channel during the main loop, where
     // we update `best_heur` and inject solution timepoints when it arrives.
     const USE_HEURISTIC: bool = true;
+    let use_heuristic = USE_HEURISTIC
+        && expansion.is_none()
+        && settings.buffer_objective.is_none()
+        && settings.primary_bound.is_none();
     let mut best_heur: Option<(i32, Vec<Vec<i32>>)> = None;
     let mut injected_heuristic_cost: Option<i32> = None;
 
@@ -558,7 +916,7 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
     // when the Gurobi-based heuristic thread isn't running (e.g. license
     // expired), so timeout cases can still report a finite `ub` and
     // downstream tooling can compute a real GAP.
-    {
+    if use_heuristic {
         let greedy_sol = crate::solvers::ddd::shared::greedy::greedy_schedule(problem);
         if let Some(cost) = problem.verify_solution(&greedy_sol, delay_cost_type) {
             best_heur = Some((cost, greedy_sol));
@@ -566,7 +924,7 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
     }
 
     //UB from Gurobi
-    let heur_thread = USE_HEURISTIC.then(|| {
+    let heur_thread = use_heuristic.then(|| {
         let (sol_in_tx, sol_in_rx) = std::sync::mpsc::channel();
         let (sol_out_tx, sol_out_rx) = std::sync::mpsc::channel();
         let problem = problem.clone();
@@ -574,10 +932,40 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
         (sol_in_tx, sol_out_rx)
     });
 
+    // Once the delay cost is optimal, the total cost is bounded by it. Then
+    // the smallest buffer is raised as long as there is a schedule for it,
+    // or the shortfall of the buffers from the cap is minimised. A pair of
+    // visits gets a literal for each buffer it is found below, which costs
+    // the shortfall from the cap when it is false, in a cost tree per pair.
+    let buffer_objective = settings.buffer_objective.filter(|_| expansion.is_none());
+    let mut buffer_phase: Option<BufferPhase<L>> = None;
+    let mut total_bound: Option<BinaryObjective<L>> = None;
+    let mut buffer_trees: HashMap<(VisitId, VisitId), CostTree<L>> = HashMap::new();
+    let mut buffer_levels: HashSet<((VisitId, VisitId), i32)> = HashSet::new();
+
     /// Main DDD loop
     loop {
         // Check timeout at the start of each iteration.
         if start_time.elapsed().as_secs_f64() > timeout {
+            // The delay cost is optimal already, only the buffer is not.
+            if let Some(phase) = buffer_phase.take() {
+                match phase.target {
+                    Some((target, _)) => println!("TIMEOUT with buffer {}", target - 1),
+                    None => println!("TIMEOUT in the buffer phase"),
+                }
+                do_output_stats(
+                    &mut output_stats,
+                    iteration,
+                    &iteration_types,
+                    &stats,
+                    &occupations,
+                    start_time,
+                    solver_time,
+                    total_cost,
+                    total_cost,
+                );
+                return Ok((phase.best, stats));
+            }
             let ub = best_heur.map(|(c, _)| c).unwrap_or(i32::MAX);
             println!("TIMEOUT LB={} UB={}", total_cost, ub);
 
@@ -647,6 +1035,7 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
             // Extract the current solution and check for travel-time and resource conflicts.
                let mut found_travel_time_conflict = false;
             let mut found_resource_conflict = false;
+            let mut found_earliness_gap = false;
 
             // travel time conflict check.
             for visit_id in touched_intervals.iter().copied() {
@@ -724,6 +1113,119 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
                 }
             }
 
+            // Maximum travel time check: leaving at `t_out` requires having
+            // entered no earlier than `t_out - max_travel_time`.
+            for (visit_id, max_travel_time) in bounded_visits.iter().copied() {
+                let next_visit: VisitId = (usize::from(visit_id) + 1).into();
+                let t_in = occupations[visit_id].incumbent_time();
+                let t_out = occupations[next_visit].incumbent_time();
+                if t_out - t_in <= max_travel_time {
+                    continue;
+                }
+                found_travel_time_conflict = true;
+                stats.n_travel += 1;
+
+                let out_occ = &occupations[next_visit];
+                let out_lit = out_occ.delays[out_occ.incumbent_idx].0;
+                let in_lit = get_delay_lit_at(
+                    &mut solver,
+                    problem,
+                    &visits,
+                    &mut occupations,
+                    &mut new_time_points,
+                    &mut fixed_prec_rows,
+                    settings.use_eager_chain_expansion,
+                    visit_id,
+                    t_out - max_travel_time,
+                );
+                SatInstance::add_clause(&mut solver, vec![!out_lit, in_lit]);
+            }
+
+            // Earliness check: the earliness cost of a time point is only
+            // counted below the next time point, so an early incumbent needs
+            // the time point right after it to be charged exactly.
+            for (visit_id, aimed) in early_visits.iter().copied() {
+                let occ = &occupations[visit_id];
+                let t = occ.incumbent_time();
+                if cancelled[visits[visit_id].0]
+                    || t >= aimed
+                    || occ.delays[occ.incumbent_idx + 1].1 == t + 1
+                {
+                    continue;
+                }
+                found_earliness_gap = true;
+                get_delay_lit_at(
+                    &mut solver,
+                    problem,
+                    &visits,
+                    &mut occupations,
+                    &mut new_time_points,
+                    &mut fixed_prec_rows,
+                    settings.use_eager_chain_expansion,
+                    visit_id,
+                    t + 1,
+                );
+            }
+
+            // Connection check: the departing visit must start `min_transfer`
+            // after the arriving visit. This is a precedence row between the
+            // two ladders, which a soft connection can give up by setting its
+            // "kept" literal to false at the cost of its penalty.
+            for (connection_idx, connection) in connections.iter().enumerate() {
+                if connection.penalty == Some(0) {
+                    continue;
+                }
+                let (from, to) = connection_visits[connection_idx];
+                let t_from = occupations[from].incumbent_time();
+                let t_required = t_from + connection.min_transfer;
+                // A row that is already there can only be violated if the
+                // connection is given up.
+                if cancelled[connection.from.0]
+                    || cancelled[connection.to.0]
+                    || occupations[to].incumbent_time() >= t_required
+                    || !connection_rows.insert((connection_idx, t_from))
+                {
+                    continue;
+                }
+                found_travel_time_conflict = true;
+                stats.n_travel += 1;
+
+                let from_lit = occupations[from].delays[occupations[from].incumbent_idx].0;
+                let to_lit = get_delay_lit_at(
+                    &mut solver,
+                    problem,
+                    &visits,
+                    &mut occupations,
+                    &mut new_time_points,
+                    &mut fixed_prec_rows,
+                    settings.use_eager_chain_expansion,
+                    to,
+                    t_required,
+                );
+                let mut clause = vec![!from_lit, to_lit];
+                clause.extend(cancel_lits[connection.from.0]);
+                clause.extend(cancel_lits[connection.to.0]);
+                if let Some(penalty) = connection.penalty {
+                    let kept = *connection_kept.entry(connection_idx).or_insert_with(|| {
+                        let kept = SatInstance::new_var(&mut solver);
+                        add_soft_cost(
+                            &mut solver,
+                            &mut soft_constraints,
+                            &mut cost_terms,
+                            &mut worst_case,
+                            connection.from.0 / base_trains,
+                            kept,
+                            penalty as usize,
+                        );
+                        if let Some(bound) = primary_bound.as_mut() {
+                            bound.add_term(&mut solver, 0, !kept, penalty as usize);
+                        }
+                        kept
+                    });
+                    clause.push(!kept);
+                }
+                SatInstance::add_clause(&mut solver, clause);
+            }
 
             //Resource conflict check: find all pairs of overlapping visits on conflicting resources, 
             //and add a clause to forbid the current overlap.
@@ -739,6 +1241,13 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
             // graph clique-cover pass.
             let mut touched_pair_cliques: HashMap<(usize, i32), HashSet<VisitId>> =
                 HashMap::new();
+            // For the total buffer, pairs up to the cap apart are checked.
+            let buffer_target = match (buffer_phase.as_ref(), buffer_objective) {
+                (Some(BufferPhase { target: Some((target, _)), .. }), _) => *target,
+                (Some(_), Some(objective)) => objective.cap,
+                _ => 0,
+            };
+            let total_phase = matches!(buffer_phase, Some(BufferPhase { target: None, .. }));
 
             touched_intervals.retain(|visit_id| {
                 let visit_id = *visit_id;
@@ -754,139 +1263,206 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
                 let visit = problem.trains[train_idx].visits[visit_idx];
                 let mut retain = false;
 
-                if let Some(conflicting_resources) = conflicts.get(&visit.resource_id) {
-                    for other_resource in conflicting_resources.iter().copied() {
-                        let t1_out = next_visit
-                            .map(|nx| occupations[nx].incumbent_time())
-                            .unwrap_or(t1_in + visit.travel_time);
+                let conflicting_resources = problem.conflicts.neighbours(visit.resource_id);
+                for other_resource in conflicting_resources.iter().copied() {
+                    let t1_out = next_visit
+                        .map(|nx| occupations[nx].incumbent_time())
+                        .unwrap_or(t1_in + visit.travel_time);
 
-                        for other_visit in resource_visits[other_resource].iter().copied() {
-                            if usize::from(visit_id) == usize::from(other_visit) {
-                                continue;
-                            }
+                    for other_visit in resource_visits[other_resource].iter().copied() {
+                        if usize::from(visit_id) == usize::from(other_visit) {
+                            continue;
+                        }
 
-                            let v2 = &occupations[other_visit];
-                            let t2_in = v2.incumbent_time();
-                            let (other_train_idx, other_visit_idx) = visits[other_visit];
+                        let v2 = &occupations[other_visit];
+                        let t2_in = v2.incumbent_time();
+                        let (other_train_idx, other_visit_idx) = visits[other_visit];
 
-                            if other_train_idx == train_idx {
-                                continue;
-                            }
+                        if other_train_idx == train_idx {
+                            continue;
+                        }
 
-                            let other_next_visit: Option<VisitId> = if other_visit_idx + 1
-                                < problem.trains[other_train_idx].visits.len()
-                            {
-                                Some((usize::from(other_visit) + 1).into())
-                            } else {
-                                None
-                            };
-
-                            let t2_out = other_next_visit
-                                .map(|v| occupations[v].incumbent_time())
-                                .unwrap_or_else(|| {
-                                    let other_v =
-                                        problem.trains[other_train_idx].visits[other_visit_idx];
-                                    t2_in + other_v.travel_time
-                                });
-
-                            if t1_out <= t2_in || t2_out <= t1_in {
-                                continue;
-                            }
+                        let other_next_visit: Option<VisitId> = if other_visit_idx + 1
+                            < problem.trains[other_train_idx].visits.len()
+                        {
+                            Some((usize::from(other_visit) + 1).into())
+                        } else {
+                            None
+                        };
+
+                        let t2_out = other_next_visit
+                            .map(|v| occupations[v].incumbent_time())
+                            .unwrap_or_else(|| {
+                                let other_v =
+                                    problem.trains[other_train_idx].visits[other_visit_idx];
+                                t2_in + other_v.travel_time
+                            });
+
+                        let base_headway = problem.headway(
+                            (train_idx, visit_idx),
+                            (other_train_idx, other_visit_idx),
+                        );
+                        let headway = base_headway + buffer_target;
 
-                            if !deconflicted_train_pairs.insert((train_idx, other_train_idx))
-                                || !deconflicted_train_pairs
-                                    .insert((other_train_idx, train_idx))
-                            {
-                                retain = true;
-                                continue;
-                            }
+                        // They are not overlapping so not in conflict.
+                        if t1_out + headway <= t2_in || t2_out + headway <= t1_in {
+                            continue;
+                        }
 
-                            found_resource_conflict = true;
-                            stats.n_conflict += 1;
+                        // A fixed order is kept by its rows instead.
+                        if ordered_visits.contains(&(visit_id, other_visit)) {
+                            retain = true;
+                            continue;
+                        }
 
-                            let (delay_t2, t2_is_new) =
-                                occupations[other_visit].time_point(&mut solver, t1_out);
-                            let (delay_t1, t1_is_new) =
-                                occupations[visit_id].time_point(&mut solver, t2_out);
+                        // Checked again once neither train is cancelled.
+                        if cancelled[train_idx] || cancelled[other_train_idx] {
+                            retain = true;
+                            continue;
+                        }
 
-                            if t1_is_new {
-                                new_time_points.push((visit_id, delay_t1, t2_out));
-                            }
-                            if t2_is_new {
-                                new_time_points.push((other_visit, delay_t2, t1_out));
-                            }
+                        // For the total buffer, a pair that does not overlap gets
+                        // a row for one more buffer than it has, unless it has
+                        // one already.
+                        let buffer = (t2_in - t1_out).max(t1_in - t2_out) - base_headway;
+                        let pair = if usize::from(visit_id) < usize::from(other_visit) {
+                            (visit_id, other_visit)
+                        } else {
+                            (other_visit, visit_id)
+                        };
+                        let has_buffer_lit = total_phase
+                            && buffer >= 0
+                            && buffer_levels.contains(&(pair, buffer + 1));
+                        if has_buffer_lit {
+                            retain = true;
+                            continue;
+                        }
+                        let headway = if total_phase {
+                            base_headway + (buffer + 1).max(0)
+                        } else {
+                            headway
+                        };
 
-                            // Eager SC-style precedence propagation: pre-emptively add a
-                            // fixed-precedence row at the new time point (delay_t1, delay_t2)
-                            // for the train's NEXT visit, propagating travel time forward.
-                            //
-                            // Gated on `use_eager_chain_expansion` so that this entire block
-                            // is a no-op when the flag is OFF — that yields semantics
-                            // equivalent to `maxsat_ladder.rs` (which only enforces travel
-                            // time lazily in the dedicated travel-time-conflict branch above).
-                            //
-                            // With the flag ON we keep the previous eager behaviour: better
-                            // unit propagation, but +50–70% more time points per benchmark
-                            // (see sc-vs-ladder analysis).
-                            if settings.use_eager_chain_expansion {
-                                let _ = add_fixed_precedence_row(
-                                    &mut solver,
-                                    problem,
-                                    &visits,
-                                    &mut occupations,
-                                    &mut new_time_points,
-                                    &mut fixed_prec_rows,
-                                    visit_id,
-                                    delay_t1,
-                                    t2_out,
-                                    settings.use_eager_chain_expansion,
-                                );
+                        // From here on, each occupation lasts until the resource is cleared
+                        // for the other train.
+                        let (t1_out, t2_out) = (t1_out + headway, t2_out + headway);
+
+                        if !deconflicted_train_pairs.insert((train_idx, other_train_idx))
+                            || !deconflicted_train_pairs
+                                .insert((other_train_idx, train_idx))
+                        {
+                            retain = true;
+                            continue;
+                        }
 
-                                let _ = add_fixed_precedence_row(
+                        found_resource_conflict = true;
+                        stats.n_conflict += 1;
+
+                        // The literal that enables the row, if it is for a buffer.
+                        let buffer_lit = match buffer_phase.as_ref() {
+                            Some(BufferPhase { target: Some((_, lit)), .. }) => Some(*lit),
+                            Some(_) if buffer >= 0 => {
+                                let lit = SatInstance::new_var(&mut solver);
+                                buffer_levels.insert((pair, buffer + 1));
+                                // `buffer_target` is the cap here.
+                                buffer_trees.entry(pair).or_default().add_cost(
                                     &mut solver,
-                                    problem,
-                                    &visits,
-                                    &mut occupations,
-                                    &mut new_time_points,
-                                    &mut fixed_prec_rows,
-                                    other_visit,
-                                    delay_t2,
-                                    t1_out,
-                                    settings.use_eager_chain_expansion,
+                                    !lit,
+                                    (buffer_target - buffer) as usize,
+                                    &mut |weight, cost_var| {
+                                        soft_constraints
+                                            .insert(!cost_var, (Soft::Primary, weight, weight));
+                                    },
                                 );
+                                Some(lit)
                             }
+                            _ => None,
+                        };
 
-                            let t1_out_lit = next_visit
-                                .map(|v| occupations[v].delays[occupations[v].incumbent_idx].0)
-                                .unwrap_or_else(|| true.into());
-                            let t2_out_lit = other_next_visit
-                                .map(|v| occupations[v].delays[occupations[v].incumbent_idx].0)
-                                .unwrap_or_else(|| true.into());
+                        let (delay_t2, t2_is_new) =
+                            occupations[other_visit].time_point(&mut solver, t1_out);
+                        let (delay_t1, t1_is_new) =
+                            occupations[visit_id].time_point(&mut solver, t2_out);
 
-                            n_conflict_constraints += 1;
-                            SatInstance::add_clause(
+                        if t1_is_new {
+                            new_time_points.push((visit_id, delay_t1, t2_out));
+                        }
+                        if t2_is_new {
+                            new_time_points.push((other_visit, delay_t2, t1_out));
+                        }
+
+                        // Eager SC-style precedence propagation: pre-emptively add a
+                        // fixed-precedence row at the new time point (delay_t1, delay_t2)
+                        // for the train's NEXT visit, propagating travel time forward.
+                        //
+                        // Gated on `use_eager_chain_expansion` so that this entire block
+                        // is a no-op when the flag is OFF — that yields semantics
+                        // equivalent to `maxsat_ladder.rs` (which only enforces travel
+                        // time lazily in the dedicated travel-time-conflict branch above).
+                        //
+                        // With the flag ON we keep the previous eager behaviour: better
+                        // unit propagation, but +50–70% more time points per benchmark
+                        // (see sc-vs-ladder analysis).
+                        if settings.use_eager_chain_expansion {
+                            let _ = add_fixed_precedence_row(
                                 &mut solver,
-                                vec![!t1_out_lit, !t2_out_lit, delay_t1, delay_t2],
+                                problem,
+                                &visits,
+                                &mut occupations,
+                                &mut new_time_points,
+                                &mut fixed_prec_rows,
+                                visit_id,
+                                delay_t1,
+                                t2_out,
+                                settings.use_eager_chain_expansion,
                             );
 
-                            // Touched-clique-AMO aggregation: record
-                            // both visits at the (resource, tau_plus_1)
-                            // of their overlap. Only meaningful for
-                            // self-conflicts (both on same resource);
-                            // cross-resource conflicts skipped.
-                            if settings.use_touched_clique_amo
-                                && visit.resource_id == other_resource
-                            {
-                                let tau_plus_1 = t1_out.min(t2_out);
-                                let entry = touched_pair_cliques
-                                    .entry((other_resource, tau_plus_1))
-                                    .or_default();
-                                entry.insert(visit_id);
-                                entry.insert(other_visit);
-                            }
+                            let _ = add_fixed_precedence_row(
+                                &mut solver,
+                                problem,
+                                &visits,
+                                &mut occupations,
+                                &mut new_time_points,
+                                &mut fixed_prec_rows,
+                                other_visit,
+                                delay_t2,
+                                t1_out,
+                                settings.use_eager_chain_expansion,
+                            );
+                        }
 
-                            retain = true;
+                        let t1_out_lit = next_visit
+                            .map(|v| occupations[v].delays[occupations[v].incumbent_idx].0)
+                            .unwrap_or_else(|| true.into());
+                        let t2_out_lit = other_next_visit
+                            .map(|v| occupations[v].delays[occupations[v].incumbent_idx].0)
+                            .unwrap_or_else(|| true.into());
+
+                        n_conflict_constraints += 1;
+                        let mut clause = vec![!t1_out_lit, !t2_out_lit, delay_t1, delay_t2];
+                        clause.extend(cancel_lits[train_idx]);
+                        clause.extend(cancel_lits[other_train_idx]);
+                        clause.extend(buffer_lit.map(|lit| !lit));
+                        SatInstance::add_clause(&mut solver, clause);
+
+                        // Touched-clique-AMO aggregation: record
+                        // both visits at the (resource, tau_plus_1)
+                        // of their overlap. Only meaningful for
+                        // self-conflicts (both on same resource);
+                        // cross-resource conflicts skipped.
+                        if settings.use_touched_clique_amo
+                            && visit.resource_id == other_resource
+                            && buffer_phase.is_none()
+                        {
+                            let tau_plus_1 = t1_out.min(t2_out);
+                            let entry = touched_pair_cliques
+                                .entry((other_resource, tau_plus_1))
+                                .or_default();
+                            entry.insert(visit_id);
+                            entry.insert(other_visit);
                         }
+
+                        retain = true;
                     }
                 }
                 retain
@@ -927,18 +1503,182 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
                             v,
                             tau_plus_1,
                         );
-                        active_lits.push(lit);
+                        active_lits.push(unless_cancelled(
+                            &mut solver,
+                            lit,
+                            cancel_lits[visits[v].0],
+                        ));
                     }
                     add_hybrid_amo(&mut solver, &active_lits, settings.use_sc_amo);
                     n_conflict_constraints += 1;
                 }
             }
 
+            // ───────── Scenario orders ─────────
+            // The copies of two conflicting visits must pass in the same
+            // order in every scenario. Once the copies disagree on a pair,
+            // it gets an order literal, and each copy gets the rows of the
+            // order that the literal chooses whenever it breaks them.
+            for &(a, b) in scenario_pairs.iter() {
+                let copies = (0..n_scenarios)
+                    .map(|s| {
+                        let offset = s * base_visits;
+                        (VisitId::from(a + offset), VisitId::from(b + offset))
+                    })
+                    .filter(|(x, y)| !cancelled[visits[*x].0] && !cancelled[visits[*y].0])
+                    .map(|(x, y)| (x, y, incumbent_order(problem, &visits, &occupations, x, y)))
+                    .collect::<Vec<_>>();
+                let rows = match order_lits.get(&(a, b)) {
+                    Some(&(order, true)) => copies
+                        .iter()
+                        .filter(|(_, _, a_first)| *a_first != Some(true))
+                        .map(|&(x, y, _)| (order, x, y))
+                        .collect::<Vec<_>>(),
+                    Some(&(order, false)) => copies
+                        .iter()
+                        .filter(|(_, _, a_first)| *a_first != Some(false))
+                        .map(|&(x, y, _)| (!order, y, x))
+                        .collect(),
+                    None if copies.iter().any(|c| c.2 == Some(true))
+                        && copies.iter().any(|c| c.2 == Some(false)) =>
+                    {
+                        let order = SatInstance::new_var(&mut solver);
+                        order_lits.insert((a, b), (order, true));
+                        copies
+                            .iter()
+                            .flat_map(|&(x, y, _)| [(order, x, y), (!order, y, x)])
+                            .collect()
+                    }
+                    None => continue,
+                };
+                for (order, first, second) in rows {
+                    if add_order_row(
+                        &mut solver,
+                        problem,
+                        &visits,
+                        &mut occupations,
+                        &mut new_time_points,
+                        &mut fixed_prec_rows,
+                        settings.use_eager_chain_expansion,
+                        &mut order_rows,
+                        &cancel_lits,
+                        order,
+                        first,
+                        second,
+                    ) {
+                        found_resource_conflict = true;
+                        stats.n_conflict += 1;
+                        n_conflict_constraints += 1;
+                    }
+                }
+            }
+
+            // ───────── Cumulative resources ─────────
+            // Occupations of resources with a capacity are not in the
+            // conflict graph. Wherever more than `capacity` of them are
+            // active at the same tau, at most `capacity` of the
+            // `active(v, tau)` literals may be true.
+            let mut active_lit_cache_capacity: HashMap<(VisitId, i32), Bool<L>> = HashMap::new();
+            for (&resource_id, &capacity) in problem.capacities.iter() {
+                let Some(on_resource) = resource_visits.get(resource_id) else {
+                    continue;
+                };
+                let intervals = on_resource
+                    .iter()
+                    .filter(|&&visit_id| !cancelled[visits[visit_id].0])
+                    .map(|&visit_id| {
+                        let (t_in, t_out) =
+                            incumbent_interval(problem, &visits, &occupations, visit_id);
+                        (t_in, t_out, visit_id)
+                    })
+                    .collect();
+                for (tau, mut members) in
+                    crate::solvers::ddd::shared::capacity::over_capacity(intervals, capacity)
+                {
+                    members.sort_by_key(|v| v.0);
+                    if !capacity_rows.insert((members.clone(), tau)) {
+                        continue;
+                    }
+                    found_resource_conflict = true;
+                    stats.n_conflict += 1;
+
+                    let mut active_lits = Vec::with_capacity(members.len());
+                    for &v in &members {
+                        let lit = build_active_lit(
+                            &mut solver,
+                            problem,
+                            &visits,
+                            &mut occupations,
+                            &mut new_time_points,
+                            &mut fixed_prec_rows,
+                            &mut active_lit_cache_capacity,
+                            settings.use_eager_chain_expansion,
+                            v,
+                            tau + 1,
+                        );
+                        active_lits.push(unless_cancelled(
+                            &mut solver,
+                            lit,
+                            cancel_lits[visits[v].0],
+                        ));
+                    }
+                    add_at_most_k(&mut solver, &active_lits, capacity, settings.use_sc_amo);
+                    n_conflict_constraints += 1;
+                }
+            }
+
+            // ───────── Blocked intervals ─────────
+            // A blocked interval is a fixed occupation: a visit that overlaps
+            // it must leave before the interval starts or enter after it ends.
+            if !problem.blocked.is_empty() {
+                for visit_id in visits.keys() {
+                    let (train_idx, visit_idx) = visits[visit_id];
+                    if cancelled[train_idx] {
+                        continue;
+                    }
+                    let visit = problem.trains[train_idx].visits[visit_idx];
+                    let (t_in, t_out) =
+                        incumbent_interval(problem, &visits, &occupations, visit_id);
+                    let Some((_, &blocked)) =
+                        problem.blocking_interval(visit.resource_id, t_in, t_out)
+                    else {
+                        continue;
+                    };
+                    found_resource_conflict = true;
+                    stats.n_conflict += 1;
+
+                    let (out_visit, out_t) =
+                        if visit_idx + 1 < problem.trains[train_idx].visits.len() {
+                            (VisitId::from(usize::from(visit_id) + 1), blocked.from + 1)
+                        } else {
+                            (visit_id, blocked.from - visit.travel_time + 1)
+                        };
+                    let mut lits = Vec::with_capacity(2);
+                    for (v, t) in [(visit_id, blocked.to), (out_visit, out_t)] {
+                        lits.push(get_delay_lit_at(
+                            &mut solver,
+                            problem,
+                            &visits,
+                            &mut occupations,
+                            &mut new_time_points,
+                            &mut fixed_prec_rows,
+                            settings.use_eager_chain_expansion,
+                            v,
+                            t,
+                        ));
+                    }
+                    // Enter after the interval, or leave before it.
+                    let mut clause = vec![lits[0], !lits[1]];
+                    clause.extend(cancel_lits[train_idx]);
+                    SatInstance::add_clause(&mut solver, clause);
+                    n_conflict_constraints += 1;
+                }
+            }
 
             // If UNSAT, add conflict-graph-based constraints, optimal check
             let iterationtype = if found_travel_time_conflict && found_resource_conflict {
                 IterationType::TravelAndResourceConflict
-            } else if found_travel_time_conflict {
+            } else if found_travel_time_conflict || found_earliness_gap {
                 IterationType::TravelTimeConflict
             } else if found_resource_conflict {
                 IterationType::ResourceConflict
@@ -948,16 +1688,79 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
 
             *iteration_types.entry(iterationtype).or_default() += 1;
 
-            if !(found_resource_conflict || found_travel_time_conflict) {
+            // ───────── Buffers ─────────
+            // A schedule with the optimal delay cost has a buffer of at least
+            // the one tried. Its rows then hold for good, and the next
+            // buffer is tried. The total buffer is the objective from the
+            // first such schedule on, which is optimal once it has no more
+            // pairs without the literal for their buffer.
+            let mut raised_buffer = false;
+            let conflict_free =
+                !(found_resource_conflict || found_travel_time_conflict || found_earliness_gap);
+            if let Some(objective) = buffer_objective.filter(|_| conflict_free) {
+                let mut schedule = extract_solution(problem, &occupations);
+                for (times, cancelled) in schedule.iter_mut().zip(cancelled.iter()) {
+                    if *cancelled {
+                        times.clear();
+                    }
+                }
+                let first_schedule = buffer_phase.is_none();
+                if first_schedule {
+                    total_bound = Some(BinaryObjective::bounded(total_cost.max(0) as usize));
+                    soft_constraints.clear();
+                }
+                let buffer = problem.buffer_stats(&schedule, objective.cap).value(objective.kind);
+                let target = buffer_phase.as_ref().and_then(|phase| phase.target);
+                match objective.kind {
+                    BufferKind::Min => {
+                        let buffer = buffer as i32;
+                        let reached = target.map_or(buffer, |(target, _)| target.max(buffer));
+                        if reached < objective.cap {
+                            if let Some((_, lit)) = target {
+                                SatInstance::add_clause(&mut solver, vec![lit]);
+                            }
+                            println!(
+                                "Buffer {} with cost {}, trying {}",
+                                reached,
+                                total_cost,
+                                reached + 1
+                            );
+                            buffer_phase = Some(BufferPhase {
+                                best: schedule,
+                                target: Some((reached + 1, SatInstance::new_var(&mut solver))),
+                            });
+                            touched_intervals = visits.keys().collect();
+                            raised_buffer = true;
+                        }
+                    }
+                    BufferKind::Total if first_schedule => {
+                        println!("Total buffer {} with cost {}", buffer, total_cost);
+                        buffer_phase = Some(BufferPhase {
+                            best: schedule,
+                            target: None,
+                        });
+                        touched_intervals = visits.keys().collect();
+                        raised_buffer = true;
+                    }
+                    BufferKind::Total => {}
+                }
+            }
+
+            if conflict_free && !raised_buffer {
                 // Incumbent times are feasible and optimal.
 
                 const USE_LP_MINIMIZE: bool = false;
 
-                let trains = if !USE_LP_MINIMIZE {
+                let mut trains = if !USE_LP_MINIMIZE {
                     extract_solution(problem, &occupations)
                 } else {
                     panic!()
                 };
+                for (times, cancelled) in trains.iter_mut().zip(cancelled.iter()) {
+                    if *cancelled {
+                        times.clear();
+                    }
+                }
 
                 println!(
                     "Finished with cost {} iterations {} solver {:?}",
@@ -1018,16 +1821,52 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
             }
         }
 
+        // Fixed order rows from the new time points, including the ones that
+        // these rows add.
+        let mut point_idx = 0;
+        while point_idx < new_time_points.len() {
+            let (visit, lit, t) = new_time_points[point_idx];
+            point_idx += 1;
+            for &connection_idx in order_rows_from.get(&visit).into_iter().flatten() {
+                if !connection_rows.insert((connection_idx, t)) {
+                    continue;
+                }
+                let connection = &connections[connection_idx];
+                let to_lit = get_delay_lit_at(
+                    &mut solver,
+                    problem,
+                    &visits,
+                    &mut occupations,
+                    &mut new_time_points,
+                    &mut fixed_prec_rows,
+                    settings.use_eager_chain_expansion,
+                    connection_visits[connection_idx].1,
+                    t + connection.min_transfer,
+                );
+                let mut clause = vec![!lit, to_lit];
+                clause.extend(cancel_lits[connection.from.0]);
+                clause.extend(cancel_lits[connection.to.0]);
+                SatInstance::add_clause(&mut solver, clause);
+            }
+        }
+
         // Add new time points and their costs to the solver.
+        let mut worst_case_terms = Vec::new();
+        let mut primary_terms = Vec::new();
         for (visit, new_timepoint_var, new_t) in new_time_points.drain(..) {
             n_timepoints += 1;
             let (train_idx, visit_idx) = visits[visit];
 
+            // The earliness cost is charged separately below, as it falls
+            // with time.
+            let train = &problem.trains[train_idx];
             let new_timepoint_cost =
-                problem.trains[train_idx].visit_delay_cost(delay_cost_type, visit_idx, new_t);
+                train.weight * train.unweighted_visit_delay_cost(delay_cost_type, visit_idx, new_t);
 
             if new_timepoint_cost > 0 {
-
+                // A cancelled train has no delay cost.
+                let new_timepoint_var =
+                    unless_cancelled(&mut solver, new_timepoint_var, cancel_lits[train_idx]);
 
                 const USE_COST_TREE: bool = true;
                 if !USE_COST_TREE {
@@ -1040,7 +1879,15 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
                         occupations[visit].cost.push(next_cost_var);
                         assert!(cost + 1 == occupations[visit].cost.len());
 
-                        soft_constraints.insert(!next_cost_var, (Soft::Primary, 1, 1));
+                        add_soft_cost(
+                            &mut solver,
+                            &mut soft_constraints,
+                            &mut cost_terms,
+                            &mut worst_case,
+                            train_idx / base_trains,
+                            !next_cost_var,
+                            1,
+                        );
                     }
 
                     SatInstance::add_clause(
@@ -1060,15 +1907,85 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
                         new_timepoint_var,
                         new_timepoint_cost,
                         &mut |weight, cost_var| {
-                            soft_constraints
-                                .insert(!cost_var, (Soft::Primary, weight, weight));
+                            if worst_case.is_some() {
+                                worst_case_terms.push((!cost_var, weight));
+                            } else {
+                                soft_constraints
+                                    .insert(!cost_var, (Soft::Primary, weight, weight));
+                                cost_terms.push((!cost_var, weight));
+                            }
                         },
                     );
                 }
             }
 
-            // set the cost for this new time point.
+            if let Some(primary_cost_type) = primary_cost_type {
+                let primary_cost = train.weight
+                    * train.unweighted_visit_delay_cost(primary_cost_type, visit_idx, new_t);
+                if primary_cost > 0 {
+                    let primary_var =
+                        unless_cancelled(&mut solver, new_timepoint_var, cancel_lits[train_idx]);
+                    occupations[visit].primary_tree.add_cost(
+                        &mut solver,
+                        primary_var,
+                        primary_cost,
+                        &mut |weight, cost_var| primary_terms.push((cost_var, weight)),
+                    );
+                }
+            }
+
+            // Being before this time point means entering at most at
+            // `new_t - 1`, which costs at least that much earliness.
+            let earliness_cost = train.visit_earliness_cost(visit_idx, new_t - 1);
+            if earliness_cost > 0 && new_t > occupations[visit].delays[0].1 {
+                let early_var =
+                    unless_cancelled(&mut solver, !new_timepoint_var, cancel_lits[train_idx]);
+                occupations[visit].earliness_tree.add_cost(
+                    &mut solver,
+                    early_var,
+                    earliness_cost,
+                    &mut |weight, cost_var| {
+                        if primary_cost_type.is_some() {
+                            primary_terms.push((cost_var, weight));
+                        }
+                        if worst_case.is_some() {
+                            worst_case_terms.push((!cost_var, weight));
+                        } else {
+                            soft_constraints.insert(!cost_var, (Soft::Primary, weight, weight));
+                            cost_terms.push((!cost_var, weight));
+                        }
+                    },
+                );
+            }
+
+            // The cost trees borrow the solver, so the terms of the worst
+            // case and of the primary bound are added afterwards.
+            if let Some(bound) = primary_bound.as_mut() {
+                for (cost_lit, weight) in primary_terms.drain(..) {
+                    bound.add_term(&mut solver, 0, cost_lit, weight);
+                }
+            }
+            for (cost_lit, weight) in worst_case_terms.drain(..) {
+                add_soft_cost(
+                    &mut solver,
+                    &mut soft_constraints,
+                    &mut cost_terms,
+                    &mut worst_case,
+                    train_idx / base_trains,
+                    cost_lit,
+                    weight,
+                );
+            }
+        }
 
+        // In the buffer phase, the total cost may not rise above its optimum.
+        if let Some(bound) = total_bound.as_mut() {
+            for (lit, weight) in cost_terms.drain(..) {
+                soft_constraints.remove(&lit);
+                bound.add_term(&mut solver, 0, !lit, weight);
+            }
+        } else if buffer_objective.is_none() {
+            cost_terms.clear();
         }
 
         //Solve
@@ -1079,6 +1996,9 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
             .map(|(k, (_, w, _))| (*k, *w))
             .collect::<Vec<_>>();
         assumptions.sort_by(|a, b| b.1.cmp(&a.1));
+        if let Some((_, lit)) = buffer_phase.as_ref().and_then(|phase| phase.target) {
+            assumptions.insert(0, (lit, 0));
+        }
 
         log::info!(
             "solving it{} with {} timepoints {} conflicts",
@@ -1139,9 +2059,16 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
 
                     }
 
+                    for (cancelled, cancel) in cancelled.iter_mut().zip(cancel_lits.iter()) {
+                        *cancelled = cancel.as_ref().map_or(false, |c| model.value(c));
+                    }
+                    for (order, value) in order_lits.values_mut() {
+                        *value = model.value(order);
+                    }
+
                     //Local Minimization: Optimize incumbent solution by trying to move each visit earlier as much as possible
                     const USE_LOCAL_MINIMIZE: bool = true;
-                    if USE_LOCAL_MINIMIZE {
+                    if USE_LOCAL_MINIMIZE && expansion.is_none() && buffer_phase.is_none() {
                         let mut last_mod = 0;
                         let mut i = 0;
                         let occs_len = occupations.len();
@@ -1188,15 +2115,77 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
 
                                 let travel_ok = t1_prev_earliest_out <= t1_in_new;
 
+                                // Connections that the model keeps must stay kept.
+                                let connections_ok = incoming_connections
+                                    .get(&visit_id)
+                                    .into_iter()
+                                    .flatten()
+                                    .all(|&connection_idx| {
+                                        let (from, _) = connection_visits[connection_idx];
+                                        cancelled[train_idx]
+                                            || cancelled[connections[connection_idx].from.0]
+                                            || t1_in_new
+                                            >= occupations[from].incumbent_time()
+                                                + connections[connection_idx].min_transfer
+                                            || connection_kept
+                                                .get(&connection_idx)
+                                                .map_or(false, |kept| !model.value(kept))
+                                    });
+
                                 let t1_out = next_visit
                                     .map(|nx| occupations[nx].incumbent_time())
                                     .unwrap_or(t1_in + visit.travel_time);
 
+                                // Rows already added for a cumulative resource are
+                                // skipped by the capacity check, so the incumbent must
+                                // not move back into an over-capacity set.
+                                let capacity_ok =
+                                    problem.capacity(visit.resource_id).map_or(true, |capacity| {
+                                        let intervals = resource_visits[visit.resource_id]
+                                            .iter()
+                                            .filter(|&&v| !cancelled[visits[v].0])
+                                            .map(|&v| {
+                                                let (t_in, t_out) = if v == visit_id {
+                                                    (t1_in_new, t1_out)
+                                                } else {
+                                                    incumbent_interval(
+                                                        problem,
+                                                        &visits,
+                                                        &occupations,
+                                                        v,
+                                                    )
+                                                };
+                                                (t_in, t_out, v)
+                                            })
+                                            .collect();
+                                        crate::solvers::ddd::shared::capacity::over_capacity(
+                                            intervals, capacity,
+                                        )
+                                        .is_empty()
+                                    });
+
+                                // Entering earlier makes the visit longer.
+                                let max_travel_ok = visit
+                                    .max_travel_time
+                                    .map_or(true, |max| t1_out - t1_in_new <= max);
+
+                                // Entering earlier must not cost earliness.
+                                let train = &problem.trains[train_idx];
+                                let earliness_ok = train.visit_earliness_cost(visit_idx, t1_in_new)
+                                    <= train.visit_earliness_cost(visit_idx, t1_in);
+
                                 let can_reduce = travel_ok
-                                    && conflicts
-                                        .get(&visit.resource_id)
+                                    && connections_ok
+                                    && capacity_ok
+                                    && max_travel_ok
+                                    && earliness_ok
+                                    && problem
+                                        .blocking_interval(visit.resource_id, t1_in_new, t1_out)
+                                        .is_none()
+                                    && problem
+                                        .conflicts
+                                        .neighbours(visit.resource_id)
                                         .iter()
-                                        .flat_map(|rs| rs.iter())
                                         .copied()
                                         .all(|other_resource| {
                                             resource_visits[other_resource]
@@ -1236,7 +2225,11 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
                                                                 .visits[other_visit_idx];
                                                             t2_in + other_visit.travel_time
                                                         });
-                                                    t1_out <= t2_in || t2_out <= t1_in_new
+                                                    let headway = problem.headway(
+                                                        (train_idx, visit_idx),
+                                                        (other_train_idx, other_visit_idx),
+                                                    );
+                                                    t1_out + headway <= t2_in || t2_out + headway <= t1_in_new
                                                 })
                                         });
 
@@ -1289,6 +2282,24 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
         if let Some(core) = core {
             let _p = hprof::enter("treat core");
 
+            // The only assumption while raising the smallest buffer is the
+            // buffer tried.
+            if let Some((target, _)) = buffer_phase.as_ref().and_then(|phase| phase.target) {
+                println!("Finished with cost {} buffer {}", total_cost, target - 1);
+                do_output_stats(
+                    &mut output_stats,
+                    iteration,
+                    &iteration_types,
+                    &stats,
+                    &occupations,
+                    start_time,
+                    solver_time,
+                    total_cost,
+                    total_cost,
+                );
+                return Ok((buffer_phase.unwrap().best, stats));
+            }
+
             if core.len() == 0 {
                 // SC-specific fallback: when the precedence graph is OFF but
                 // some other SC feature is ON, an empty core may indicate
@@ -1372,7 +2383,10 @@ pub fn solve_debug_with_settings<L: satcoder::Lit + Copy + std::fmt::Debug + 'st
                 }
             }
 
-            total_cost += min_weight as i32;
+            // The cores of the total buffer leave the cost at its optimum.
+            if buffer_phase.is_none() {
+                total_cost += min_weight as i32;
+            }
             println!("    LB={}", total_cost);
 
             if total_cost as i32 == best_heur.as_ref().map(|(c, _)| *c).unwrap_or(i32::MAX) {
@@ -1565,6 +2579,9 @@ fn inject_solution_timepoints_maxsat<L: satcoder::Lit>(
 pub(super) struct Occ<L: satcoder::Lit> {
     pub(super) cost: Vec<Bool<L>>,
     pub(super) cost_tree: CostTree<L>,
+    /// The costs under [`MaxSatDddLadderScSettings::primary_bound`].
+    pub(super) primary_tree: CostTree<L>,
+    pub(super) earliness_tree: CostTree<L>,
     pub(super) delays: Vec<(Bool<L>, i32)>,
     pub(super) incumbent_idx: usize,
 }
@@ -1580,7 +2597,10 @@ impl<L: satcoder::Lit> Occ<L> {
 
 
         assert!(idx > 0 || t == self.delays[0].1); // cannot insert before the earliest time.
-        assert!(idx < self.delays.len()); // cannot insert after infinity.
+        if idx == self.delays.len() {
+            // Beyond the visit's `latest` bound: the last time point is always false.
+            return (self.delays[idx - 1].0, false);
+        }
 
         assert!(idx == 0 || self.delays[idx - 1].1 < t);
         assert!(self.delays[idx].1 >= t);
@@ -42,7 +42,7 @@ thread_local! { pub static  WATCH : std::cell::RefCell<Option<(usize,usize)>>  =
 use crate::{debug::DebugInfo, problem::DelayCostType, solvers::util::heuristic};
 
 use crate::solvers::SolverError;
-use super::shared::costtree::CostTree;
+use super::shared::{capacity::over_capacity, costtree::CostTree};
 pub fn solve_debug<L: satcoder::Lit + Copy + std::fmt::Debug>(
     mk_env: impl Fn() -> grb::Env + Send + 'static,
     mut solver: impl SatInstance<L> + SatSolverWithCore<Lit = L> + std::fmt::Debug,
@@ -70,7 +70,6 @@ pub fn solve_debug<L: satcoder::Lit + Copy + std::fmt::Debug>(
     let mut resource_visits: Vec<Vec<VisitId>> = Vec::new();
     let mut occupations: TiVec<VisitId, Occ<_>> = TiVec::new();
     let mut touched_intervals = Vec::new();
-    let mut conflicts: HashMap<usize, Vec<usize>> = HashMap::new();
     let mut new_time_points = Vec::new();
 
     #[allow(unused)]
@@ -82,11 +81,10 @@ pub fn solve_debug<L: satcoder::Lit + Copy + std::fmt::Debug>(
     let mut n_timepoints = 0;
     let mut n_conflict_constraints = 0;
 
-    for (a, b) in problem.conflicts.iter() {
-        conflicts.entry(*a).or_default().push(*b);
-        if *a != *b {
-            conflicts.entry(*b).or_default().push(*a);
-        }
+    // The time ladders below end at each visit's `latest` bound, which
+    // requires the bound to be reachable at all.
+    if !problem.latest_bounds_reachable() {
+        return Err(SolverError::NoSolution);
     }
 
     for (train_idx, train) in problem.trains.iter().enumerate() {
@@ -96,7 +94,7 @@ pub fn solve_debug<L: satcoder::Lit + Copy + std::fmt::Debug>(
             occupations.push(Occ {
                 cost: vec![true.into()],
                 cost_tree: CostTree::new(),
-                delays: vec![(true.into(), visit.earliest), (false.into(), i32::MAX)],
+                delays: vec![(true.into(), visit.earliest), (false.into(), visit.ladder_end())],
                 incumbent_idx: 0,
             });
             n_timepoints += 1;
@@ -123,6 +121,9 @@ pub fn solve_debug<L: satcoder::Lit + Copy + std::fmt::Debug>(
 
     // let mut conflicts_added: HashSet<((VisitId, i32), (VisitId, i32))> = Default::default();
     let mut conflict_vars: HashMap<(VisitId, VisitId), Bool<L>> = Default::default();
+    // Over-capacity sets of cumulative resources already forbidden, keyed by
+    // sorted visit-set and tau.
+    let mut capacity_rows: HashSet<(Vec<VisitId>, i32)> = HashSet::new();
     // let mut priorities: Vec<(VisitId, VisitId)> = Vec::new();
 
     const USE_HEURISTIC: bool = true;
@@ -304,170 +305,178 @@ pub fn solve_debug<L: satcoder::Lit + Copy + std::fmt::Debug>(
 
                 let mut retain = false;
 
-                if let Some(conflicting_resources) = conflicts.get(&visit.resource_id) {
-                    for other_resource in conflicting_resources.iter().copied() {
-                        // println!(" other resource {:?}", other_resource);
-                        let t1_out = next_visit
-                            .map(|nx| occupations[nx].incumbent_time())
-                            .unwrap_or(t1_in + visit.travel_time);
+                let conflicting_resources = problem.conflicts.neighbours(visit.resource_id);
+                for other_resource in conflicting_resources.iter().copied() {
+                    // println!(" other resource {:?}", other_resource);
+                    let t1_out = next_visit
+                        .map(|nx| occupations[nx].incumbent_time())
+                        .unwrap_or(t1_in + visit.travel_time);
 
-                        // Waiting in stations, but not in tracks (where conflicts occur).
-                        // assert!(t1_in + travel_time == t1_out);
+                    // Waiting in stations, but not in tracks (where conflicts occur).
+                    // assert!(t1_in + travel_time == t1_out);
 
-                        for other_visit in resource_visits[other_resource].iter().copied() {
-                            if usize::from(visit_id) == usize::from(other_visit) {
-                                continue;
-                            }
+                    for other_visit in resource_visits[other_resource].iter().copied() {
+                        if usize::from(visit_id) == usize::from(other_visit) {
+                            continue;
+                        }
 
-                            let _v1 = &occupations[visit_id];
-                            let v2 = &occupations[other_visit];
-                            let t2_in = v2.incumbent_time();
-                            let (other_train_idx, other_visit_idx) = visits[other_visit];
+                        let _v1 = &occupations[visit_id];
+                        let v2 = &occupations[other_visit];
+                        let t2_in = v2.incumbent_time();
+                        let (other_train_idx, other_visit_idx) = visits[other_visit];
 
-                            // We have a train2 that is conflicting.
-                            if other_train_idx == train_idx {
-                                continue; // Assume for now that the train doesn't conflict with itself.
-                            }
+                        // We have a train2 that is conflicting.
+                        if other_train_idx == train_idx {
+                            continue; // Assume for now that the train doesn't conflict with itself.
+                        }
 
-                            let other_next_visit: Option<VisitId> = if other_visit_idx + 1
-                                < problem.trains[other_train_idx].visits.len()
-                            {
-                                Some((usize::from(other_visit) + 1).into())
-                            } else {
-                                None
-                            };
-
-                            let t2_out = other_next_visit
-                                .map(|v| occupations[v].incumbent_time())
-                                .unwrap_or_else(|| {
-                                    let other_visit =
-                                        problem.trains[other_train_idx].visits[other_visit_idx];
-                                    t2_in + other_visit.travel_time
-                                });
+                        let other_next_visit: Option<VisitId> = if other_visit_idx + 1
+                            < problem.trains[other_train_idx].visits.len()
+                        {
+                            Some((usize::from(other_visit) + 1).into())
+                        } else {
+                            None
+                        };
+
+                        let t2_out = other_next_visit
+                            .map(|v| occupations[v].incumbent_time())
+                            .unwrap_or_else(|| {
+                                let other_visit =
+                                    problem.trains[other_train_idx].visits[other_visit_idx];
+                                t2_in + other_visit.travel_time
+                            });
+
+                        // let t2_earliest_out = t2_in
+                        //     + problem.trains[other_train_idx].visits[other_visit_idx].2;
+                        // let t1_earliest_out =
+                        //     t1_in + problem.trains[train_idx].visits[visit_idx].2;
+
+                        let headway = problem.headway(
+                            (train_idx, visit_idx),
+                            (other_train_idx, other_visit_idx),
+                        );
 
-                            // let t2_earliest_out = t2_in
-                            //     + problem.trains[other_train_idx].visits[other_visit_idx].2;
-                            // let t1_earliest_out =
-                            //     t1_in + problem.trains[train_idx].visits[visit_idx].2;
+                        // They are not overlapping so not in conflict.
+                        if t1_out + headway <= t2_in || t2_out + headway <= t1_in {
+                            continue;
+                        }
 
-                            // They are not overlapping so not in conflict.
-                            if t1_out <= t2_in || t2_out <= t1_in {
-                                continue;
-                            }
+                        // From here on, each occupation lasts until the resource is cleared
+                        // for the other train.
+                        let (t1_out, t2_out) = (t1_out + headway, t2_out + headway);
 
-                            // let conflict_id_a = (visit_id, t1_out);
-                            // let conflict_id_b = (other_visit, t2_out);
+                        // let conflict_id_a = (visit_id, t1_out);
+                        // let conflict_id_b = (other_visit, t2_out);
 
-                            // if conflicts_added.contains(&(conflict_id_b, conflict_id_a)) {
-                            //     continue;
-                            // }
+                        // if conflicts_added.contains(&(conflict_id_b, conflict_id_a)) {
+                        //     continue;
+                        // }
 
-                            // println!("Inserting {:?}", (conflict_id_a, conflict_id_b));
-                            // assert!(conflicts_added.insert((conflict_id_a, conflict_id_b)));
+                        // println!("Inserting {:?}", (conflict_id_a, conflict_id_b));
+                        // assert!(conflicts_added.insert((conflict_id_a, conflict_id_b)));
 
-                            // let t2_out = t2_earliest_out;
-                            // let t1_out = t1_earliest_out;
-                            // if t1_out <= t2_in || t2_out <= t1_in {
-                            //     panic!("kejks");
-                            // }
+                        // let t2_out = t2_earliest_out;
+                        // let t1_out = t1_earliest_out;
+                        // if t1_out <= t2_in || t2_out <= t1_in {
+                        //     panic!("kejks");
+                        // }
 
-                            if !deconflicted_train_pairs.insert((train_idx, other_train_idx))
-                                || !deconflicted_train_pairs.insert((other_train_idx, train_idx))
-                            {
-                                retain = true;
-                                continue;
-                            }
+                        if !deconflicted_train_pairs.insert((train_idx, other_train_idx))
+                            || !deconflicted_train_pairs.insert((other_train_idx, train_idx))
+                        {
+                            retain = true;
+                            continue;
+                        }
 
-                            found_resource_conflict = true;
-                            stats.n_conflict += 1;
+                        found_resource_conflict = true;
+                        stats.n_conflict += 1;
 
-                            // println!(
-                            //         " - RESOURCE conflict between t{}-v{}-r{}-in{}-out{} t{}-v{}-r{}-in{}-out{}",
-                            //         train_idx,
-                            //         visit_idx,
-                            //         problem.trains[train_idx].visits[visit_idx].0,
-                            //         t1_in,
-                            //         t1_out,
-                            //         other_train_idx,
-                            //         other_visit_idx,
-                            //         problem.trains[other_train_idx].visits[other_visit_idx].0,
-                            //         t2_in,
-                            //         t2_out,
-                            //     );
-
-                            // We should not need to use these.
-                            #[allow(unused, clippy::let_unit_value)]
-                            let t1_in = ();
-                            #[allow(unused, clippy::let_unit_value)]
-                            let t2_in = ();
-
-                            // The constraint is:
-                            // We can delay T1_IN until T2_OUT?
-                            // .. OR we can delay T2_IN until T1_OUT
-                            let (delay_t2, t2_is_new) =
-                                occupations[other_visit].time_point(&mut solver, t1_out);
-                            let (delay_t1, t1_is_new) =
-                                occupations[visit_id].time_point(&mut solver, t2_out);
-
-                            if !t2_is_new && !t1_is_new {
-                                // println!("Did we solve this before?");
-                            }
+                        // println!(
+                        //         " - RESOURCE conflict between t{}-v{}-r{}-in{}-out{} t{}-v{}-r{}-in{}-out{}",
+                        //         train_idx,
+                        //         visit_idx,
+                        //         problem.trains[train_idx].visits[visit_idx].0,
+                        //         t1_in,
+                        //         t1_out,
+                        //         other_train_idx,
+                        //         other_visit_idx,
+                        //         problem.trains[other_train_idx].visits[other_visit_idx].0,
+                        //         t2_in,
+                        //         t2_out,
+                        //     );
+
+                        // We should not need to use these.
+                        #[allow(unused, clippy::let_unit_value)]
+                        let t1_in = ();
+                        #[allow(unused, clippy::let_unit_value)]
+                        let t2_in = ();
+
+                        // The constraint is:
+                        // We can delay T1_IN until T2_OUT?
+                        // .. OR we can delay T2_IN until T1_OUT
+                        let (delay_t2, t2_is_new) =
+                            occupations[other_visit].time_point(&mut solver, t1_out);
+                        let (delay_t1, t1_is_new) =
+                            occupations[visit_id].time_point(&mut solver, t2_out);
+
+                        if !t2_is_new && !t1_is_new {
+                            // println!("Did we solve this before?");
+                        }
 
-                            if t1_is_new {
-                                new_time_points.push((visit_id, delay_t1, t2_out));
-                            }
+                        if t1_is_new {
+                            new_time_points.push((visit_id, delay_t1, t2_out));
+                        }
 
-                            if t2_is_new {
-                                new_time_points.push((other_visit, delay_t2, t1_out));
-                            }
+                        if t2_is_new {
+                            new_time_points.push((other_visit, delay_t2, t1_out));
+                        }
 
-                            let v1 = &occupations[visit_id];
-                            let v2 = &occupations[other_visit];
-
-                            let _t1_in_lit = v1.delays[v1.incumbent_idx].0;
-                            let t1_out_lit = next_visit
-                                .map(|v| occupations[v].delays[occupations[v].incumbent_idx].0)
-                                .unwrap_or_else(|| true.into());
-                            let _t2_in_lit = v2.delays[v2.incumbent_idx].0;
-                            let t2_out_lit = other_next_visit
-                                .map(|v| occupations[v].delays[occupations[v].incumbent_idx].0)
-                                .unwrap_or_else(|| true.into());
-
-                            const USE_CHOICE_VAR: bool = false;
-                            n_conflict_constraints += 1;
-
-                            if USE_CHOICE_VAR {
-                                let (pa, pb) = (visit_id, other_visit);
-
-                                let choose =
-                                    conflict_vars.get(&(pa, pb)).copied().unwrap_or_else(|| {
-                                        let new_var = SatInstance::new_var(&mut solver);
-                                        conflict_vars.insert((pa, pb), new_var);
-                                        conflict_vars.insert((pb, pa), !new_var);
-                                        new_var
-                                    });
-
-                                SatInstance::add_clause(
-                                    &mut solver,
-                                    vec![!choose, !t1_out_lit, delay_t2],
-                                );
-                                SatInstance::add_clause(
-                                    &mut solver,
-                                    vec![choose, !t2_out_lit, delay_t1],
-                                );
-                            } else {
-                                SatInstance::add_clause(
-                                    &mut solver,
-                                    vec![
-                                        // !t1_in_lit,
-                                        !t1_out_lit,
-                                        // !t2_in_lit,
-                                        !t2_out_lit,
-                                        delay_t1,
-                                        delay_t2,
-                                    ],
-                                );
-                            }
+                        let v1 = &occupations[visit_id];
+                        let v2 = &occupations[other_visit];
+
+                        let _t1_in_lit = v1.delays[v1.incumbent_idx].0;
+                        let t1_out_lit = next_visit
+                            .map(|v| occupations[v].delays[occupations[v].incumbent_idx].0)
+                            .unwrap_or_else(|| true.into());
+                        let _t2_in_lit = v2.delays[v2.incumbent_idx].0;
+                        let t2_out_lit = other_next_visit
+                            .map(|v| occupations[v].delays[occupations[v].incumbent_idx].0)
+                            .unwrap_or_else(|| true.into());
+
+                        const USE_CHOICE_VAR: bool = false;
+                        n_conflict_constraints += 1;
+
+                        if USE_CHOICE_VAR {
+                            let (pa, pb) = (visit_id, other_visit);
+
+                            let choose =
+                                conflict_vars.get(&(pa, pb)).copied().unwrap_or_else(|| {
+                                    let new_var = SatInstance::new_var(&mut solver);
+                                    conflict_vars.insert((pa, pb), new_var);
+                                    conflict_vars.insert((pb, pa), !new_var);
+                                    new_var
+                                });
+
+                            SatInstance::add_clause(
+                                &mut solver,
+                                vec![!choose, !t1_out_lit, delay_t2],
+                            );
+                            SatInstance::add_clause(
+                                &mut solver,
+                                vec![choose, !t2_out_lit, delay_t1],
+                            );
+                        } else {
+                            SatInstance::add_clause(
+                                &mut solver,
+                                vec![
+                                    // !t1_in_lit,
+                                    !t1_out_lit,
+                                    // !t2_in_lit,
+                                    !t2_out_lit,
+                                    delay_t1,
+                                    delay_t2,
+                                ],
+                            );
                         }
                     }
                 }
@@ -475,6 +484,92 @@ pub fn solve_debug<L: satcoder::Lit + Copy + std::fmt::Debug>(
                 retain
             });
 
+            // Cumulative resources are not in the conflict graph: wherever more
+            // than `capacity` of their occupations overlap at the same tau, not all
+            // of them may stay active at tau, i.e. one of them enters after tau or
+            // leaves at or before tau.
+            for (&resource_id, &capacity) in problem.capacities.iter() {
+                let Some(on_resource) = resource_visits.get(resource_id) else {
+                    continue;
+                };
+                let intervals = on_resource
+                    .iter()
+                    .map(|&v| {
+                        let (t_in, t_out) = incumbent_interval(problem, &visits, &occupations, v);
+                        (t_in, t_out, v)
+                    })
+                    .collect();
+                for (tau, mut members) in over_capacity(intervals, capacity) {
+                    members.sort_by_key(|v| v.0);
+                    if !capacity_rows.insert((members.clone(), tau)) {
+                        continue;
+                    }
+                    found_resource_conflict = true;
+                    stats.n_conflict += 1;
+
+                    let mut clause = Vec::with_capacity(2 * members.len());
+                    for &v in &members {
+                        clause.push(delay_lit_at(
+                            &mut solver,
+                            &mut occupations,
+                            &mut new_time_points,
+                            v,
+                            tau + 1,
+                        ));
+                        let leave = leave_lit_at(
+                            &mut solver,
+                            problem,
+                            &visits,
+                            &mut occupations,
+                            &mut new_time_points,
+                            v,
+                            tau + 1,
+                        );
+                        clause.push(!leave);
+                    }
+                    SatInstance::add_clause(&mut solver, clause);
+                    n_conflict_constraints += 1;
+                }
+            }
+
+            // A blocked interval is a fixed occupation: a visit that overlaps it
+            // must leave before the interval starts or enter after it ends.
+            if !problem.blocked.is_empty() {
+                for visit_id in visits.keys() {
+                    let (train_idx, visit_idx) = visits[visit_id];
+                    let resource_id = problem.trains[train_idx].visits[visit_idx].resource_id;
+                    let (t_in, t_out) =
+                        incumbent_interval(problem, &visits, &occupations, visit_id);
+                    let Some((_, &blocked)) =
+                        problem.blocking_interval(resource_id, t_in, t_out)
+                    else {
+                        continue;
+                    };
+                    found_resource_conflict = true;
+                    stats.n_conflict += 1;
+
+                    let enter_after = delay_lit_at(
+                        &mut solver,
+                        &mut occupations,
+                        &mut new_time_points,
+                        visit_id,
+                        blocked.to,
+                    );
+                    let leave_after_start = leave_lit_at(
+                        &mut solver,
+                        problem,
+                        &visits,
+                        &mut occupations,
+                        &mut new_time_points,
+                        visit_id,
+                        blocked.from + 1,
+                    );
+                    // Enter after the interval, or leave before it.
+                    SatInstance::add_clause(&mut solver, vec![enter_after, !leave_after_start]);
+                    n_conflict_constraints += 1;
+                }
+            }
+
             // touched_intervals.clear();
             // assert!(touched_intervals.is_empty());
             // }
@@ -810,11 +905,29 @@ pub fn solve_debug<L: satcoder::Lit + Copy + std::fmt::Debug>(
                                     .map(|nx| occupations[nx].incumbent_time())
                                     .unwrap_or(t1_in + visit.travel_time);
 
+                                // Capacity rows already added are skipped by the capacity check,
+                                // so the incumbent must not move back into an over-capacity set.
+                                let capacity_ok = within_capacity(
+                                    problem,
+                                    &visits,
+                                    &resource_visits,
+                                    &occupations,
+                                    visit_id,
+                                    (t1_in_new, t1_out),
+                                );
+
+                                // Nor may it move back into a blocked interval.
+                                let blocked_ok = problem
+                                    .blocking_interval(visit.resource_id, t1_in_new, t1_out)
+                                    .is_none();
+
                                 let can_reduce = travel_ok
-                                    && conflicts
-                                        .get(&visit.resource_id)
+                                    && capacity_ok
+                                    && blocked_ok
+                                    && problem
+                                        .conflicts
+                                        .neighbours(visit.resource_id)
                                         .iter()
-                                        .flat_map(|rs| rs.iter())
                                         .copied()
                                         .all(|other_resource| {
                                             resource_visits[other_resource]
@@ -854,7 +967,11 @@ pub fn solve_debug<L: satcoder::Lit + Copy + std::fmt::Debug>(
                                                                 .visits[other_visit_idx];
                                                             t2_in + other_visit.travel_time
                                                         });
-                                                    t1_out <= t2_in || t2_out <= t1_in_new
+                                                    let headway = problem.headway(
+                                                        (train_idx, visit_idx),
+                                                        (other_train_idx, other_visit_idx),
+                                                    );
+                                                    t1_out + headway <= t2_in || t2_out + headway <= t1_in_new
                                                 })
                                         });
 
@@ -1064,3 +1181,91 @@ pub fn solve_debug<L: satcoder::Lit + Copy + std::fmt::Debug>(
         // println!("iteration {}", iteration);
     }
 }
+
+/// The incumbent `(t_in, t_out)` of a visit.
+fn incumbent_interval<L: satcoder::Lit>(
+    problem: &Problem,
+    visits: &TiVec<VisitId, (usize, usize)>,
+    occupations: &TiVec<VisitId, Occ<L>>,
+    visit_id: VisitId,
+) -> (i32, i32) {
+    let (train_idx, visit_idx) = visits[visit_id];
+    let t_in = occupations[visit_id].incumbent_time();
+    let t_out = if visit_idx + 1 < problem.trains[train_idx].visits.len() {
+        occupations[VisitId::from(usize::from(visit_id) + 1)].incumbent_time()
+    } else {
+        t_in + problem.trains[train_idx].visits[visit_idx].travel_time
+    };
+    (t_in, t_out)
+}
+
+/// Whether the cumulative resource of `visit_id`, if any, stays within its
+/// capacity when the visit occupies `interval` instead of its incumbent.
+fn within_capacity<L: satcoder::Lit>(
+    problem: &Problem,
+    visits: &TiVec<VisitId, (usize, usize)>,
+    resource_visits: &[Vec<VisitId>],
+    occupations: &TiVec<VisitId, Occ<L>>,
+    visit_id: VisitId,
+    interval: (i32, i32),
+) -> bool {
+    let (train_idx, visit_idx) = visits[visit_id];
+    let resource_id = problem.trains[train_idx].visits[visit_idx].resource_id;
+    problem.capacity(resource_id).map_or(true, |capacity| {
+        let intervals = resource_visits[resource_id]
+            .iter()
+            .map(|&v| {
+                let (t_in, t_out) = if v == visit_id {
+                    interval
+                } else {
+                    incumbent_interval(problem, visits, occupations, v)
+                };
+                (t_in, t_out, v)
+            })
+            .collect();
+        over_capacity(intervals, capacity).is_empty()
+    })
+}
+
+/// The literal for entering `visit_id` at `t` or later. A new time point
+/// is queued in `new_time_points` so that it gets its cost.
+fn delay_lit_at<L: satcoder::Lit>(
+    solver: &mut impl SatInstance<L>,
+    occupations: &mut TiVec<VisitId, Occ<L>>,
+    new_time_points: &mut Vec<(VisitId, Bool<L>, i32)>,
+    visit_id: VisitId,
+    t: i32,
+) -> Bool<L> {
+    let (always, earliest) = occupations[visit_id].delays[0];
+    if t <= earliest {
+        return always;
+    }
+    let (lit, is_new) = occupations[visit_id].time_point(solver, t);
+    if is_new {
+        new_time_points.push((visit_id, lit, t));
+    }
+    lit
+}
+
+/// The literal for leaving `visit_id` at `t` or later, i.e. entering the
+/// next visit at `t` or later, or entering a train's last visit at
+/// `t - travel_time` or later.
+fn leave_lit_at<L: satcoder::Lit>(
+    solver: &mut impl SatInstance<L>,
+    problem: &Problem,
+    visits: &TiVec<VisitId, (usize, usize)>,
+    occupations: &mut TiVec<VisitId, Occ<L>>,
+    new_time_points: &mut Vec<(VisitId, Bool<L>, i32)>,
+    visit_id: VisitId,
+    t: i32,
+) -> Bool<L> {
+    let (train_idx, visit_idx) = visits[visit_id];
+    let train = &problem.trains[train_idx];
+    if visit_idx + 1 < train.visits.len() {
+        let next_visit = VisitId::from(usize::from(visit_id) + 1);
+        delay_lit_at(solver, occupations, new_time_points, next_visit, t)
+    } else {
+        let t_in = t - train.visits[visit_idx].travel_time;
+        delay_lit_at(solver, occupations, new_time_points, visit_id, t_in)
+    }
+}
@@ -29,8 +29,8 @@ use rustsat::{
 };
 use rustsat_glucose::core::Glucose as RsGlucose;
 use satcoder::{
-    prelude::SymbolicModel, Bool, SatInstance, SatModel, SatResult, SatResultWithCore, SatSolver,
-    SatSolverWithCore,
+    constraints::Totalizer, prelude::SymbolicModel, Bool, SatInstance, SatModel, SatResult,
+    SatResultWithCore, SatSolver, SatSolverWithCore,
 };
 use typed_index_collections::TiVec;
 
@@ -1021,8 +1021,10 @@ fn compute_initial_heuristic_upper_bound<L: satcoder::Lit>(
             use_strong_branching,
             Some(&initial_solution),
         )? {
-            let ub_cost = problem.verify_solution(&ub_sol, delay_cost_type).unwrap();
-            return Ok(Some((ub_cost, ub_sol)));
+            // The heuristic ignores `latest` bounds, so its schedule may be rejected.
+            if let Some(ub_cost) = problem.verify_solution(&ub_sol, delay_cost_type) {
+                return Ok(Some((ub_cost, ub_sol)));
+            }
         }
     }
 
@@ -1124,6 +1126,31 @@ fn add_hybrid_amo<L: satcoder::Lit>(
     }
 }
 
+/// At-most-`k` over `lits` for the capacity of cumulative resources. `k == 1`
+/// goes through [`add_hybrid_amo`], larger bounds use a totalizer.
+fn add_at_most_k<L: satcoder::Lit>(
+    solver: &mut impl SatInstance<L>,
+    lits: &[Bool<L>],
+    k: usize,
+    use_sc_amo: bool,
+) {
+    if lits.len() <= k {
+        return;
+    }
+    match k {
+        0 => {
+            for lit in lits {
+                solver.add_clause(vec![!*lit]);
+            }
+        }
+        1 => add_hybrid_amo(solver, lits, use_sc_amo),
+        _ => {
+            let tot = Totalizer::count(solver, lits.iter().copied(), k as u32);
+            solver.add_clause(vec![!tot.rhs()[k]]); // tot <= k
+        }
+    }
+}
+
 /// Monotone delay literal `visit_id.start ≥ t`.
 /// Returns `true.into()` if t ≤ earliest (always satisfied),
 /// `false.into()` if t ≥ infinity sentinel (never satisfied),
@@ -1295,7 +1322,6 @@ fn solve_native_debug_with_mode(
     let mut resource_visits: Vec<Vec<VisitId>> = Vec::new();
     let mut occupations: TiVec<VisitId, Occ<NativeLit>> = TiVec::new();
     let mut touched_intervals = Vec::new();
-    let mut conflicts: HashMap<usize, Vec<usize>> = HashMap::new();
     let mut new_time_points: Vec<(VisitId, Bool<NativeLit>, i32)> = Vec::new();
     let effective_earliest = settings
         .use_precedence_graph
@@ -1306,11 +1332,10 @@ fn solve_native_debug_with_mode(
     let mut n_timepoints = 0usize;
     let mut n_conflict_constraints = 0usize;
 
-    for (a, b) in problem.conflicts.iter() {
-        conflicts.entry(*a).or_default().push(*b);
-        if *a != *b {
-            conflicts.entry(*b).or_default().push(*a);
-        }
+    // The time ladders below end at each visit's `latest` bound, which
+    // requires the bound to be reachable at all.
+    if !problem.latest_bounds_reachable() {
+        return Err(SolverError::NoSolution);
     }
 
     for (train_idx, train) in problem.trains.iter().enumerate() {
@@ -1325,7 +1350,7 @@ fn solve_native_debug_with_mode(
             occupations.push(Occ {
                 cost: vec![true.into()],
                 cost_tree: CostTree::new(),
-                delays: vec![(true.into(), earliest), (false.into(), i32::MAX)],
+                delays: vec![(true.into(), earliest), (false.into(), visit.ladder_end())],
                 incumbent_idx: 0,
             });
             n_timepoints += 1;
@@ -1374,6 +1399,9 @@ fn solve_native_debug_with_mode(
     let mut bit_totalizer = BitTotalizerObjective::default();
 
     let mut added_resource_clique_rows: HashSet<ResourceCliqueRowKey> = HashSet::new();
+    // Over-capacity sets of cumulative resources already forbidden, keyed by
+    // sorted visit-set and tau.
+    let mut capacity_rows: HashSet<(Vec<VisitId>, i32)> = HashSet::new();
     let mut fixed_prec_rows: HashSet<(VisitId, i32)> = HashSet::new();
 
     if settings.seed_precedence_from_earliest {
@@ -1430,7 +1458,6 @@ fn solve_native_debug_with_mode(
     if settings.seed_resource_conflicts {
         let mut seed_active_cache: HashMap<(VisitId, i32), Bool<NativeLit>> = HashMap::new();
         let mut n_seeded = 0usize;
-        let mut seen_resource_pairs: HashSet<(usize, usize)> = HashSet::new();
 
         // Helper: get earliest start + earliest end of a visit's occupation.
         let earliest_occupation = |visit_id: VisitId,
@@ -1509,54 +1536,42 @@ fn solve_native_debug_with_mode(
             };
 
         // Iterate over unique conflicting resource pairs.
-        let conflicts_snapshot: Vec<(usize, Vec<usize>)> = conflicts
-            .iter()
-            .map(|(k, v)| (*k, v.clone()))
-            .collect();
-        for (res_a, others) in &conflicts_snapshot {
-            for &res_b in others {
-                if *res_a > res_b {
-                    continue;
-                }
-                if !seen_resource_pairs.insert((*res_a, res_b)) {
-                    continue;
-                }
-                if *res_a >= resource_visits.len() || res_b >= resource_visits.len() {
-                    continue;
-                }
+        for (res_a, res_b) in problem.conflicts.iter() {
+            if res_a >= resource_visits.len() || res_b >= resource_visits.len() {
+                continue;
+            }
 
-                if *res_a == res_b {
-                    let visits_list = resource_visits[*res_a].clone();
-                    for i in 0..visits_list.len() {
-                        for j in (i + 1)..visits_list.len() {
-                            try_seed_pair(
-                                visits_list[i],
-                                visits_list[j],
-                                &mut solver,
-                                &mut occupations,
-                                &mut new_time_points,
-                                &mut fixed_prec_rows,
-                                &mut seed_active_cache,
-                                &mut n_seeded,
-                            );
-                        }
+            if res_a == res_b {
+                let visits_list = resource_visits[res_a].clone();
+                for i in 0..visits_list.len() {
+                    for j in (i + 1)..visits_list.len() {
+                        try_seed_pair(
+                            visits_list[i],
+                            visits_list[j],
+                            &mut solver,
+                            &mut occupations,
+                            &mut new_time_points,
+                            &mut fixed_prec_rows,
+                            &mut seed_active_cache,
+                            &mut n_seeded,
+                        );
                     }
-                } else {
-                    let visits_a = resource_visits[*res_a].clone();
-                    let visits_b = resource_visits[res_b].clone();
-                    for &v1 in &visits_a {
-                        for &v2 in &visits_b {
-                            try_seed_pair(
-                                v1,
-                                v2,
-                                &mut solver,
-                                &mut occupations,
-                                &mut new_time_points,
-                                &mut fixed_prec_rows,
-                                &mut seed_active_cache,
-                                &mut n_seeded,
-                            );
-                        }
+                }
+            } else {
+                let visits_a = resource_visits[res_a].clone();
+                let visits_b = resource_visits[res_b].clone();
+                for &v1 in &visits_a {
+                    for &v2 in &visits_b {
+                        try_seed_pair(
+                            v1,
+                            v2,
+                            &mut solver,
+                            &mut occupations,
+                            &mut new_time_points,
+                            &mut fixed_prec_rows,
+                            &mut seed_active_cache,
+                            &mut n_seeded,
+                        );
                     }
                 }
             }
@@ -1731,13 +1746,12 @@ fn solve_native_debug_with_mode(
             for &visit_id in &touched_intervals {
                 let (train_idx, visit_idx) = visits[visit_id];
                 let resource = problem.trains[train_idx].visits[visit_idx].resource_id;
-                if let Some(conflicting_resources) = conflicts.get(&resource) {
-                    for &other in conflicting_resources {
-                        if resource <= other {
-                            relevant_resource_pairs.insert((resource, other));
-                        } else {
-                            relevant_resource_pairs.insert((other, resource));
-                        }
+                let conflicting_resources = problem.conflicts.neighbours(resource);
+                for &other in conflicting_resources {
+                    if resource <= other {
+                        relevant_resource_pairs.insert((resource, other));
+                    } else {
+                        relevant_resource_pairs.insert((other, resource));
                     }
                 }
             }
@@ -1836,6 +1850,43 @@ fn solve_native_debug_with_mode(
                         resource_b,
                     ));
                 }
+
+                // Headways: the cliques above only cover occupations that
+                // overlap, so pairs that are too close are added as 2-member
+                // candidates with each occupation extended by the headway.
+                if !problem.headways.is_empty() {
+                    for (i, mi) in group_intervals.iter().enumerate() {
+                        for mj in group_intervals[i + 1..].iter() {
+                            if mi.train_idx == mj.train_idx
+                                || !(touched_set.contains(&mi.visit_id)
+                                    || touched_set.contains(&mj.visit_id))
+                            {
+                                continue;
+                            }
+                            let headway =
+                                problem.headway(visits[mi.visit_id], visits[mj.visit_id]);
+                            if headway <= 0
+                                || mi.end + headway <= mj.start
+                                || mj.end + headway <= mi.start
+                            {
+                                continue;
+                            }
+                            let members = vec![
+                                ActiveInterval {
+                                    end: mi.end + headway,
+                                    ..*mi
+                                },
+                                ActiveInterval {
+                                    end: mj.end + headway,
+                                    ..*mj
+                                },
+                            ];
+                            let tau = mi.start.max(mj.start);
+                            let severity = 2 * (mi.end.min(mj.end) + headway - tau).max(1) as i64;
+                            clique_candidates.push((severity, members, tau, resource_a, resource_b));
+                        }
+                    }
+                }
             }
 
             // Sort by severity descending — the most "dangerous" cliques (largest
@@ -2010,6 +2061,102 @@ fn solve_native_debug_with_mode(
             }
             touched_intervals = new_touched;
 
+            // Cumulative resources are not in the conflict graph: wherever
+            // more than `capacity` of their occupations are active at the
+            // same tau, at most `capacity` of them may stay active.
+            for (&resource_id, &capacity) in problem.capacities.iter() {
+                let Some(on_resource) = resource_visits.get(resource_id) else {
+                    continue;
+                };
+                let intervals = on_resource
+                    .iter()
+                    .map(|&visit_id| {
+                        let (train_idx, visit_idx) = visits[visit_id];
+                        let t_in = occupations[visit_id].incumbent_time();
+                        let t_out = if visit_idx + 1 < problem.trains[train_idx].visits.len() {
+                            occupations[VisitId::from(usize::from(visit_id) + 1)].incumbent_time()
+                        } else {
+                            t_in + problem.trains[train_idx].visits[visit_idx].travel_time
+                        };
+                        (t_in, t_out, visit_id)
+                    })
+                    .collect();
+                for (tau, mut members) in
+                    super::shared::capacity::over_capacity(intervals, capacity)
+                {
+                    members.sort_by_key(|v| v.0);
+                    if !capacity_rows.insert((members.clone(), tau)) {
+                        continue;
+                    }
+                    found_resource_conflict = true;
+                    stats.n_conflict += 1;
+
+                    let mut active_lits = Vec::with_capacity(members.len());
+                    for &v in &members {
+                        active_lits.push(build_active_lit(
+                            &mut solver,
+                            problem,
+                            &visits,
+                            &mut occupations,
+                            &mut new_time_points,
+                            &mut fixed_prec_rows,
+                            &mut active_lit_cache,
+                            prec,
+                            v,
+                            tau + 1,
+                        ));
+                    }
+                    add_at_most_k(&mut solver, &active_lits, capacity, settings.use_sc_amo);
+                    n_conflict_constraints += 1;
+                }
+            }
+
+            // A blocked interval is a fixed occupation: a visit that overlaps
+            // it must leave before the interval starts or enter after it ends.
+            if !problem.blocked.is_empty() {
+                for visit_id in visits.keys() {
+                    let (train_idx, visit_idx) = visits[visit_id];
+                    let visit = problem.trains[train_idx].visits[visit_idx];
+                    let has_next = visit_idx + 1 < problem.trains[train_idx].visits.len();
+                    let t_in = occupations[visit_id].incumbent_time();
+                    let t_out = if has_next {
+                        occupations[VisitId::from(usize::from(visit_id) + 1)].incumbent_time()
+                    } else {
+                        t_in + visit.travel_time
+                    };
+                    let Some((_, &blocked)) =
+                        problem.blocking_interval(visit.resource_id, t_in, t_out)
+                    else {
+                        continue;
+                    };
+                    found_resource_conflict = true;
+                    stats.n_conflict += 1;
+
+                    let (out_visit, out_t) = if has_next {
+                        (VisitId::from(usize::from(visit_id) + 1), blocked.from + 1)
+                    } else {
+                        (visit_id, blocked.from - visit.travel_time + 1)
+                    };
+                    let mut lits = Vec::with_capacity(2);
+                    for (v, t) in [(visit_id, blocked.to), (out_visit, out_t)] {
+                        lits.push(get_delay_lit_at(
+                            &mut solver,
+                            problem,
+                            &visits,
+                            &mut occupations,
+                            &mut new_time_points,
+                            &mut fixed_prec_rows,
+                            prec,
+                            v,
+                            t,
+                        ));
+                    }
+                    // Enter after the interval, or leave before it.
+                    solver.add_clause(vec![lits[0], !lits[1]]);
+                    n_conflict_constraints += 1;
+                }
+            }
+
             let iterationtype = if found_travel_time_conflict && found_resource_conflict {
                 IterationType::TravelAndResourceConflict
             } else if found_travel_time_conflict {
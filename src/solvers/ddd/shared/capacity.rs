@@ -0,0 +1,29 @@
+//! Capacity check for cumulative resources (see
+//! [`Problem::capacities`](crate::problem::Problem::capacities)).
+//!
+//! A resource with capacity `k` is over capacity when `k + 1` occupations
+//! are active at the same time. The DDD solvers forbid each such set by an
+//! at-most-`k` constraint over "active at `tau`" literals, where `tau` is
+//! the start of the occupation that pushed the count over the capacity.
+
+/// Sweeps the occupations `(t_in, t_out, visit)` of one cumulative resource
+/// and returns each time `tau` where more than `capacity` occupations are
+/// active (`t_in <= tau < t_out`), together with those occupations.
+pub fn over_capacity<V: Copy>(
+    mut intervals: Vec<(i32, i32, V)>,
+    capacity: usize,
+) -> Vec<(i32, Vec<V>)> {
+    intervals.retain(|&(t_in, t_out, _)| t_out > t_in);
+    intervals.sort_by_key(|&(t_in, t_out, _)| (t_in, t_out));
+
+    let mut sets = Vec::new();
+    let mut active: Vec<(i32, i32, V)> = Vec::new();
+    for interval @ (tau, _, _) in intervals {
+        active.retain(|&(_, t_out, _)| t_out > tau);
+        active.push(interval);
+        if active.len() > capacity {
+            sets.push((tau, active.iter().map(|&(_, _, v)| v).collect()));
+        }
+    }
+    sets
+}
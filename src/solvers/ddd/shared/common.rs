@@ -61,7 +61,10 @@ pub fn time_point(&mut self, solver: &mut impl SatInstance<L>, t: i32) -> (Bool<
         // println!("idx {} t {}   delays{:?}", idx, t, self.delays);
 
         assert!(idx > 0 || t == self.delays[0].1); // cannot insert before the earliest time.
-        assert!(idx < self.delays.len()); // cannot insert after infinity.
+        if idx == self.delays.len() {
+            // Beyond the visit's `latest` bound: the last time point is always false.
+            return (self.delays[idx - 1].0, false);
+        }
 
         assert!(idx == 0 || self.delays[idx - 1].1 < t);
         assert!(self.delays[idx].1 >= t);
@@ -38,9 +38,10 @@ fn propagate_est_forward(
 /// by linear index = stable within-train order), then sweep — each visit
 /// starts at `max(est, conflict-resource latest finish, predecessor
 /// finish)`. The resulting schedule respects:
-///   1. `visit.earliest` lower bounds,
+///   1. `visit.earliest_start()` lower bounds,
 ///   2. within-train chain `t[v+1] ≥ t[v] + travel[v]`,
-///   3. resource non-overlap on any pair `(r, r')` in `problem.conflicts`.
+///   3. resource non-overlap on any pair `(r, r')` in `problem.conflicts`,
+///      padded by the (direction-agnostic) maximum headway of the pair.
 /// → feasible by construction; cost is a valid upper bound on optimal.
 ///
 /// Used by solvers that lack a Gurobi-based heuristic UB to seed
@@ -61,7 +62,7 @@ pub fn greedy_schedule(problem: &Problem) -> Vec<Vec<i32>> {
         for visit in &train.visits {
             resource.push(visit.resource_id);
             travel.push(visit.travel_time);
-            est.push(visit.earliest);
+            est.push(visit.earliest_start());
         }
     }
     propagate_est_forward(&mut est, &travel, &problem.trains, &train_offset);
@@ -73,14 +74,6 @@ pub fn greedy_schedule(problem: &Problem) -> Vec<Vec<i32>> {
         }
     }
 
-    let mut conflicts_of: HashMap<usize, Vec<usize>> = HashMap::new();
-    for &(a, b) in &problem.conflicts {
-        conflicts_of.entry(a).or_default().push(b);
-        if a != b {
-            conflicts_of.entry(b).or_default().push(a);
-        }
-    }
-
     let mut order: Vec<usize> = (0..n).collect();
     order.sort_by_key(|&v| (est[v], v));
 
@@ -98,25 +91,23 @@ pub fn greedy_schedule(problem: &Problem) -> Vec<Vec<i32>> {
             0
         };
 
-        let resource_lb = conflicts_of
-            .get(&r)
-            .map(|rs| {
-                rs.iter()
-                    .map(|r2| last_finish.get(r2).copied().unwrap_or(0))
-                    .max()
-                    .unwrap_or(0)
-            })
+        let conflicting = problem.conflicts.neighbours(r);
+        let resource_lb = conflicting
+            .iter()
+            .map(|r2| last_finish.get(r2).copied().unwrap_or(0))
+            .max()
             .unwrap_or(0);
 
         let t_v = est[v].max(resource_lb).max(chain_lb);
         t_greedy[v] = t_v;
         let finish = t_v.saturating_add(travel[v]);
 
-        if let Some(rs) = conflicts_of.get(&r) {
-            for &r2 in rs {
+        if !conflicting.is_empty() {
+            for &r2 in conflicting {
+                let release = finish.saturating_add(problem.headways.max_headway(r, r2));
                 let cur = last_finish.entry(r2).or_insert(0);
-                if finish > *cur {
-                    *cur = finish;
+                if release > *cur {
+                    *cur = release;
                 }
             }
         } else {
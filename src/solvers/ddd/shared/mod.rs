@@ -1,5 +1,6 @@
 //! Shared utilities used by multiple DDD solver families.
 //!
+//! - `capacity` — over-capacity detection for cumulative resources.
 //! - `common` — shared types (Occ, VisitId, SolveStats helpers) used by `maxsat_rc2`.
 //! - `costtree` — cost-tree representation used across ladder solvers + maxsat_rc2.
 //! - `precedence` — within-train chain propagation (`chain_earliest`).
@@ -7,6 +8,7 @@
 //! - `greedy` — greedy feasible schedule for warm-start UB; used by
 //!              MaxSAT solvers to seed `best_heur`.
 
+pub mod capacity;
 pub mod common;
 pub mod costtree;
 pub mod greedy;
@@ -1,8 +1,9 @@
 //! Precedence preprocessing for TRP — Contribution 2 of the thesis.
 //!
 //! [`chain_earliest`] — within-train chain propagation
-//! `est[v+1] = max(visit.earliest, est[v] + travel[v])`. Sound for all
-//! objectives; used by `incremental_sat`, `puresat`, and
+//! `est[v+1] = max(visit.earliest_start(), est[v] + travel[v])`, continued along
+//! rolling-stock turnarounds from the end of one train to the start of the
+//! next. Sound for all objectives; used by `incremental_sat`, `puresat`, and
 //! `maxsat_ladder_sc`.
 
 use crate::problem::Problem;
@@ -10,19 +11,45 @@ use crate::problem::Problem;
 /// Simple within-train chain propagation of earliest start times.
 ///
 /// For each train, iterate visits in order and set
-/// `earliest[v] = max(visit.earliest, earliest[v-1] + travel[v-1])`.
+/// `earliest[v] = max(visit.earliest_start(), earliest[v-1] + travel[v-1])`.
+/// A train that is the next service of a trainset (see
+/// [`Problem::turnarounds`]) cannot start before the previous train's end
+/// plus the turnaround time, so the trains are swept again until the bounds
+/// are stable.
 pub fn chain_earliest(problem: &Problem) -> Vec<Vec<i32>> {
-    let mut effective = Vec::with_capacity(problem.trains.len());
-    for train in &problem.trains {
-        let mut train_bounds = Vec::with_capacity(train.visits.len());
-        let mut propagated_lb: Option<i32> = None;
-        for visit in &train.visits {
-            let lb = propagated_lb
-                .map_or(visit.earliest, |prev_lb: i32| prev_lb.max(visit.earliest));
-            train_bounds.push(lb);
-            propagated_lb = Some(lb.saturating_add(visit.travel_time));
+    let mut start_lb: Vec<Option<i32>> = vec![None; problem.trains.len()];
+    let mut effective = Vec::new();
+    // Each sweep fixes at least one more train along every circulation, so
+    // this terminates unless the turnarounds form a cycle.
+    for _ in 0..=problem.trains.len() {
+        effective = Vec::with_capacity(problem.trains.len());
+        for (train, start_lb) in problem.trains.iter().zip(start_lb.iter()) {
+            let mut train_bounds = Vec::with_capacity(train.visits.len());
+            let mut propagated_lb: Option<i32> = *start_lb;
+            for visit in &train.visits {
+                let earliest = visit.earliest_start();
+                let lb = propagated_lb.map_or(earliest, |prev_lb: i32| prev_lb.max(earliest));
+                train_bounds.push(lb);
+                propagated_lb = Some(lb.saturating_add(visit.travel_time));
+            }
+            effective.push(train_bounds);
+        }
+
+        let mut changed = false;
+        for turnaround in problem.turnarounds.iter() {
+            let from = &problem.trains[turnaround.from];
+            let end = effective[turnaround.from][from.visits.len() - 1]
+                .saturating_add(from.visits[from.visits.len() - 1].travel_time)
+                .saturating_add(turnaround.time);
+            if effective[turnaround.to][0] < end {
+                let lb = &mut start_lb[turnaround.to];
+                *lb = Some(lb.map_or(end, |lb| lb.max(end)));
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
         }
-        effective.push(train_bounds);
     }
     effective
 }
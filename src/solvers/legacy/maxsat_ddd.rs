@@ -39,7 +39,7 @@ pub fn solve_incremental<S: MaxSatSolver>(
         for (visit_idx, visit) in train.visits.iter().enumerate() {
             if problem
                 .conflicts
-                .contains(&(visit.resource_id, visit.resource_id))
+                .contains(visit.resource_id, visit.resource_id)
             {
                 resource_usage
                     .entry(visit.resource_id)
@@ -860,7 +860,7 @@ pub fn solve<S: MaxSatSolver>(
             occupations.push((train_idx, visit_idx));
             if problem
                 .conflicts
-                .contains(&(visit.resource_id, visit.resource_id))
+                .contains(visit.resource_id, visit.resource_id)
             {
                 resource_usage
                     .entry(visit.resource_id)
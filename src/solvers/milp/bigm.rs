@@ -5,7 +5,10 @@ use std::{
 
 use crate::solvers::SolverError;
 use crate::{
-    problem::{iter_infinite_staircase, DelayCostThresholds, DelayCostType, Problem},
+    problem::{
+        iter_infinite_staircase, BufferKind, BufferObjective, Connection, CostBound,
+        DelayCostFunction, DelayCostThresholds, DelayCostType, PiecewiseLinearCost, Problem,
+    },
     solvers::util::{minimize, value_trace::ValueTrace},
 };
 const M: f64 = 2.0 * 6.0 * 3600.0;
@@ -23,6 +26,43 @@ pub fn solve_bigm(
     train_names: &[String],
     resource_names: &[String],
     output_stats: impl FnMut(String, serde_json::Value),
+) -> Result<Vec<Vec<i32>>, SolverError> {
+    solve_bigm_with_objectives(
+        env,
+        mk_env,
+        problem,
+        delay_cost_type,
+        lazy,
+        timeout,
+        train_names,
+        resource_names,
+        None,
+        None,
+        output_stats,
+    )
+}
+
+/// Like [`solve_bigm`], with secondary objectives:
+///
+///  * With a `primary_bound`, the cost under its cost type is kept at most
+///    the bound by a constraint row, for lexicographic optimisation.
+///  * With a `buffer_objective`, once the delay cost is optimal, it is kept
+///    as a bound and the buffers are maximised. Conflicts whose buffer is
+///    too small are added as they are found. On a timeout in the second
+///    phase, the schedule of the first phase is returned.
+#[allow(clippy::too_many_arguments)]
+pub fn solve_bigm_with_objectives(
+    env: &grb::Env,
+    mk_env: impl Fn() -> grb::Env + Send + 'static,
+    problem: &Problem,
+    delay_cost_type: DelayCostType,
+    lazy: bool,
+    timeout: f64,
+    train_names: &[String],
+    resource_names: &[String],
+    primary_bound: Option<CostBound>,
+    buffer_objective: Option<BufferObjective>,
+    output_stats: impl FnMut(String, serde_json::Value),
 ) -> Result<Vec<Vec<i32>>, SolverError> {
     solve(
         env,
@@ -34,6 +74,8 @@ pub fn solve_bigm(
         train_names,
         resource_names,
         add_bigm_conflict_constraint,
+        primary_bound,
+        buffer_objective,
         output_stats,
     )
 }
@@ -61,6 +103,8 @@ pub fn solve_hull(
         train_names,
         resource_names,
         add_hull_conflict_constraint,
+        None,
+        None,
         output_stats,
     )
 }
@@ -76,11 +120,17 @@ fn solve(
     train_names: &[String],
     resource_names: &[String],
     add_conflict_constraint: ConflictHandler,
+    primary_bound: Option<CostBound>,
+    buffer_objective: Option<BufferObjective>,
     mut output_stats: impl FnMut(String, serde_json::Value),
 ) -> Result<Vec<Vec<i32>>, SolverError> {
     let _p = hprof::enter("bigm solver");
     use grb::prelude::*;
 
+    if !problem.latest_bounds_reachable() {
+        return Err(SolverError::NoSolution);
+    }
+
     let mut model = Model::with_env("model1", env).map_err(SolverError::GurobiError)?;
     let mut n_travel_constraints = 0;
     let mut n_resource_constraints = 0;
@@ -113,13 +163,69 @@ fn solve(
                 .map(|(visit_idx, visit)| {
                     add_ctsvar!(model,
                 name : &format!("tn{}_v{}_tk{}", train_names[train_idx], visit_idx, resource_names[visit.resource_id]), 
-                bounds: visit.earliest..)
+                bounds: visit.earliest_start()..)
                     .map_err(SolverError::GurobiError)
                 })
                 .collect::<Result<Vec<_>, _>>()
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    // Deadlines
+    for (train, train_t_vars) in problem.trains.iter().zip(t_vars.iter()) {
+        for (visit, t_var) in train.visits.iter().zip(train_t_vars.iter()) {
+            if let Some(latest) = visit.latest {
+                model
+                    .set_obj_attr(attr::UB, t_var, latest as f64)
+                    .map_err(SolverError::GurobiError)?;
+            }
+        }
+    }
+
+    // Cancelling a train costs its cancellation cost and frees it from
+    // conflicts, capacities and connections. Its delay costs are measured on
+    // `cost_t_vars`, which equal the times unless the train is cancelled.
+    let cancel_vars = problem
+        .trains
+        .iter()
+        .enumerate()
+        .map(|(train_idx, train)| {
+            train
+                .cancellation_cost
+                .map(|cost| {
+                    let name = format!("tn{}_cancelled", train_names[train_idx]);
+                    add_binvar!(model, name: &name, obj: cost)
+                })
+                .transpose()
+                .map_err(SolverError::GurobiError)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut cost_t_vars = t_vars.clone();
+    for (train_idx, cancel_var) in cancel_vars.iter().enumerate() {
+        let Some(cancel_var) = *cancel_var else {
+            continue;
+        };
+        for (visit_idx, cost_t_var) in cost_t_vars[train_idx].iter_mut().enumerate() {
+            let t_var = t_vars[train_idx][visit_idx];
+            let name = format!("tn{}_v{}_costtime", train_names[train_idx], visit_idx);
+            *cost_t_var =
+                add_ctsvar!(model, name: &name, bounds: ..).map_err(SolverError::GurobiError)?;
+            #[allow(clippy::useless_conversion)]
+            model
+                .add_constr(
+                    &format!("{}_lb", name),
+                    c!(*cost_t_var >= t_var - M * cancel_var),
+                )
+                .map_err(SolverError::GurobiError)?;
+            #[allow(clippy::useless_conversion)]
+            model
+                .add_constr(
+                    &format!("{}_ub", name),
+                    c!(*cost_t_var <= t_var + M * cancel_var),
+                )
+                .map_err(SolverError::GurobiError)?;
+        }
+    }
+
     // Travel time constraints
     for train_idx in 0..problem.trains.len() {
         for visit_idx in 0..problem.trains[train_idx].visits.len() - 1 {
@@ -135,6 +241,76 @@ fn solve(
         }
     }
 
+    // Running-time supplements: the time on a visit with a maximum travel
+    // time beyond its minimum travel time, measured on `cost_t_vars` so that
+    // a cancelled train has none.
+    for (train_idx, train) in problem.trains.iter().enumerate() {
+        let Some(cost) = train.running_time_cost else {
+            continue;
+        };
+        for visit_idx in 0..train.visits.len() - 1 {
+            let visit = &train.visits[visit_idx];
+            if visit.max_travel_time.is_none() {
+                continue;
+            }
+            let name = format!("tn{}_v{}_supplement", train_names[train_idx], visit_idx);
+            let supplement_var = add_ctsvar!(model, name: &name, bounds: 0.., obj: cost)
+                .map_err(SolverError::GurobiError)?;
+            let (t_in, t_out) = (
+                cost_t_vars[train_idx][visit_idx],
+                cost_t_vars[train_idx][visit_idx + 1],
+            );
+            #[allow(clippy::useless_conversion)]
+            model
+                .add_constr(
+                    &name,
+                    c!(supplement_var >= t_out - t_in - visit.travel_time),
+                )
+                .map_err(SolverError::GurobiError)?;
+        }
+    }
+
+    // Earliness: the time a visit is entered before its aimed time, also
+    // measured on `cost_t_vars`.
+    for (train_idx, train) in problem.trains.iter().enumerate() {
+        let Some(cost) = train.earliness_cost else {
+            continue;
+        };
+        for (visit_idx, visit) in train.visits.iter().enumerate() {
+            let Some(aimed) = visit.aimed.filter(|&aimed| aimed > visit.earliest_start()) else {
+                continue;
+            };
+            let name = format!("tn{}_v{}_early", train_names[train_idx], visit_idx);
+            let weight = cost as f64 * train.weight as f64;
+            let early_var = add_ctsvar!(model, name: &name, bounds: 0.., obj: weight)
+                .map_err(SolverError::GurobiError)?;
+            #[allow(clippy::useless_conversion)]
+            model
+                .add_constr(
+                    &name,
+                    c!(early_var >= aimed - cost_t_vars[train_idx][visit_idx]),
+                )
+                .map_err(SolverError::GurobiError)?;
+        }
+    }
+
+    // Connection, turnaround and fixed order constraints
+    for (connection_idx, connection) in problem.precedences().enumerate() {
+        add_connection_constraint(
+            connection_idx,
+            &connection,
+            &mut model,
+            &t_vars,
+            &cancel_vars,
+            train_names,
+        )?;
+        n_travel_constraints += 1;
+    }
+
+    // Blocked intervals are few, so they are always added up front.
+    n_resource_constraints +=
+        add_blocked_constraints(problem, &mut model, &t_vars, &cancel_vars, train_names)?;
+
     // List all conflicting visits
     let visit_conflicts = visit_conflicts(problem);
     let mut priority_vars = HashMap::new();
@@ -146,6 +322,7 @@ fn solve(
                 problem,
                 visit_pair,
                 t_vars: &t_vars,
+                cancel_vars: &cancel_vars,
                 train_names,
                 resource_names,
             };
@@ -156,11 +333,31 @@ fn solve(
         }
     }
 
+    // Cumulative resources get all their ordering constraints at once, up
+    // front or (lazily) the first time their capacity is exceeded.
+    let mut added_capacities = HashSet::new();
+    if !lazy_constraints {
+        for (&resource_id, &capacity) in problem.capacities.iter() {
+            n_resource_constraints += add_capacity_constraints(
+                &mut model,
+                problem,
+                resource_id,
+                capacity,
+                &t_vars,
+                &cancel_vars,
+                train_names,
+            )?;
+            added_capacities.insert(resource_id);
+        }
+    }
+
     let mut lazy_stepfunction: Option<HashMap<(usize, usize), Vec<()>>> = None;
 
     const USE_HEURISTIC: bool = true;
 
-    let heur_thread = USE_HEURISTIC.then(|| {
+    // The heuristic only knows about the delay cost.
+    let use_heuristic = USE_HEURISTIC && buffer_objective.is_none() && primary_bound.is_none();
+    let heur_thread = use_heuristic.then(|| {
         let (sol_in_tx, sol_in_rx) = std::sync::mpsc::channel();
         let (sol_out_tx, sol_out_rx) = std::sync::mpsc::channel();
         let problem = problem.clone();
@@ -174,132 +371,48 @@ fn solve(
         (sol_in_tx, sol_out_rx)
     });
 
-    // Objective
-    match delay_cost_type {
-        DelayCostType::FiniteSteps1_3Min
-        | DelayCostType::FiniteSteps1_5Min
-        | DelayCostType::FiniteSteps123
-        | DelayCostType::FiniteSteps12345
-        | DelayCostType::FiniteSteps139 => {
-            let delay_cost = match delay_cost_type {
-                DelayCostType::FiniteSteps1_3Min => DelayCostThresholds::f1_3min(),
-                DelayCostType::FiniteSteps1_5Min => DelayCostThresholds::f1_5min(),
-                DelayCostType::FiniteSteps123 => DelayCostThresholds::f123(),
-                DelayCostType::FiniteSteps12345 => DelayCostThresholds::f12345(),
-                DelayCostType::FiniteSteps139 => DelayCostThresholds::f139(),
-                _ => panic!(),
-            };
-            for (train_idx, train) in problem.trains.iter().enumerate() {
-                for visit_idx in 0..train.visits.len() {
-                    if let Some(aimed) = train.visits[visit_idx].aimed {
-                        let time_var = t_vars[train_idx][visit_idx];
-
-                        // create a variable for each delay threshold
-                        let thresholds = &delay_cost.thresholds;
-                        for threshold_idx in (0..thresholds.len()).rev() {
-                            let (_prev_threshold, prev_cost) =
-                                thresholds.get(threshold_idx + 1).unwrap_or(&(0, 0));
-                            let (threshold, cost) = thresholds[threshold_idx];
-                            let threshold = threshold;
-
-                            let cost_diff = cost - prev_cost;
-                            assert!(cost_diff > 0);
-
-                            let threshold_var_name = format!(
-                                "tn{}_v{}_tk{}_dly{}",
-                                train_names[train_idx],
-                                visit_idx,
-                                resource_names[train.visits[visit_idx].resource_id],
-                                threshold
-                            );
-
-                            // Add threshold_var to the objective with cost `diff_cost`.
-                            #[allow(clippy::unnecessary_cast)]
-                            let threshold_var =
-                                add_intvar!(model, name: &threshold_var_name, bounds: 0..1, obj: cost_diff)
-                                    .map_err(SolverError::GurobiError)?;
-
-                            // If last_t - aimed >= threshold+1 then threshold_var must be 1
-
-                            #[allow(clippy::useless_conversion)]
-                            model
-                                .add_constr(
-                                    &format!("has_{}", threshold_var_name),
-                                    c!(time_var - aimed <= threshold + M * threshold_var),
-                                )
-                                .map_err(SolverError::GurobiError)?;
-                        }
-                    }
-                }
-            }
-        }
-        DelayCostType::InfiniteSteps60
-        | DelayCostType::InfiniteSteps180
-        | DelayCostType::InfiniteSteps360 => {
-            // this is done lazily when a solution has been found
-            //lazy_stepfunction = Some(Default::default());
-            // The lazy_stepfunction uses big-M and can in princple have any shape, but since we know that
-            // the step funtion has a fixed spacing and step increase, we can just use a single integer variable with cost.
-
-            let interval = match delay_cost_type {
-                DelayCostType::InfiniteSteps60 => 60,
-                DelayCostType::InfiniteSteps180 => 180,
-                DelayCostType::InfiniteSteps360 => 360,
-                _ => panic!(),
-            };
-
-            for (train_idx, train) in problem.trains.iter().enumerate() {
-                for visit_idx in 0..train.visits.len() {
-                    if let Some(aimed) = train.visits[visit_idx].aimed {
-                        let time_var = t_vars[train_idx][visit_idx];
-                        let objective_var_name = format!(
-                            "tn{}_v{}_tk{}_dlysteps",
-                            train_names[train_idx],
-                            visit_idx,
-                            resource_names[train.visits[visit_idx].resource_id],
-                        );
-                        let objective_var =
-                            add_intvar!(model, name:&objective_var_name,bounds:0.., obj: 1.0)
-                                .map_err(SolverError::GurobiError)?;
-
-                        #[allow(clippy::useless_conversion)]
-                        model
-                            .add_constr(
-                                &format!("bound_{}", objective_var_name),
-                                c!(interval * objective_var >= time_var - aimed),
-                            )
-                            .map_err(SolverError::GurobiError)?;
-                    }
-                }
-            }
-        }
-        DelayCostType::Continuous => {
-            for (train_idx, train) in problem.trains.iter().enumerate() {
-                for visit_idx in 0..train.visits.len() {
-                    if let Some(aimed) = train.visits[visit_idx].aimed {
-                        let time_var = t_vars[train_idx][visit_idx];
-                        let objective_var_name = format!(
-                            "tn{}_v{}_tk{}_dly",
-                            train_names[train_idx],
-                            visit_idx,
-                            resource_names[train.visits[visit_idx].resource_id],
-                        );
-                        let objective_var =
-                            add_ctsvar!(model, name:&objective_var_name,bounds:0.., obj: 1.0)
-                                .map_err(SolverError::GurobiError)?;
-
-                        #[allow(clippy::useless_conversion)]
-                        model
-                            .add_constr(
-                                &format!("bound_{}", objective_var_name),
-                                c!(objective_var >= time_var - aimed),
-                            )
-                            .map_err(SolverError::GurobiError)?;
-                    }
-                }
-            }
-        }
+    // Objective. With a primary bound, the costs of its cost type are added
+    // first and bounded together with the costs above, which do not depend
+    // on the cost type. Only the costs of `delay_cost_type` stay in the
+    // objective.
+    if let Some(bound) = primary_bound {
+        model.update().map_err(SolverError::GurobiError)?;
+        let n_vars = model.get_vars().map_err(SolverError::GurobiError)?.len();
+        add_delay_cost(
+            &mut model,
+            problem,
+            bound.cost_type,
+            &cost_t_vars,
+            train_names,
+            resource_names,
+        )?;
+        model.update().map_err(SolverError::GurobiError)?;
+        let vars = model.get_vars().map_err(SolverError::GurobiError)?.to_vec();
+        let weights = model
+            .get_obj_attr_batch(attr::Obj, vars.iter().copied())
+            .map_err(SolverError::GurobiError)?;
+        let primary_cost = vars
+            .iter()
+            .zip(weights)
+            .filter(|(_, w)| *w != 0.0)
+            .map(|(var, w)| w * *var)
+            .grb_sum();
+        #[allow(clippy::useless_conversion)]
+        model
+            .add_constr("primary_cost_bound", c!(primary_cost <= bound.cost as f64 + 1e-6))
+            .map_err(SolverError::GurobiError)?;
+        model
+            .set_obj_attr_batch(attr::Obj, vars[n_vars..].iter().map(|var| (*var, 0.0)))
+            .map_err(SolverError::GurobiError)?;
     }
+    add_delay_cost(
+        &mut model,
+        problem,
+        delay_cost_type,
+        &cost_t_vars,
+        train_names,
+        resource_names,
+    )?;
 
     let mut refinement_iterations = 0usize;
     let mut solver_time = std::time::Duration::ZERO;
@@ -308,6 +421,7 @@ fn solve(
     let mut value_trace = ValueTrace::default();
     let mut logged_lb: Option<i32> = None;
     let mut logged_incumbent: Option<i32> = None;
+    let mut buffer_phase: Option<BufferPhase> = None;
     // println!("INSTANCE");
     loop {
         {
@@ -341,6 +455,25 @@ fn solve(
 
         let status = model.status().map_err(SolverError::GurobiError)?;
         if status == Status::TimeLimit {
+            // The delay cost is optimal already, only the buffers are not.
+            if let Some(phase) = buffer_phase {
+                println!("TIMEOUT in the buffer phase with cost {}", phase.cost);
+                value_trace.emit(&mut output_stats, Some(phase.cost));
+                do_output_stats(
+                    &mut output_stats,
+                    refinement_iterations,
+                    &added_conflicts,
+                    iteration,
+                    n_travel_constraints,
+                    n_resource_constraints,
+                    phase.cost,
+                    start_time,
+                    solver_time,
+                    phase.cost,
+                );
+                return Ok(phase.best);
+            }
+
             let ub = best_heur.as_ref().map(|(c, _)| *c).unwrap_or(i32::MAX);
             println!("TIMEOUT LB={} UB={}", global_lb, ub);
             value_trace.timeout(start_time, global_lb, best_heur.as_ref().map(|(c, _)| *c), Some(iteration));
@@ -387,8 +520,11 @@ fn solve(
             .get_attr(attr::ObjVal)
             .map_err(SolverError::GurobiError)?;
 
-        global_lb = cost.round() as i32;
-        if logged_lb != Some(global_lb) {
+        // In the buffer phase, the objective is the buffers.
+        if buffer_phase.is_none() {
+            global_lb = cost.round() as i32;
+        }
+        if buffer_phase.is_none() && logged_lb != Some(global_lb) {
             value_trace.lower_bound(
                 start_time,
                 global_lb,
@@ -438,9 +574,34 @@ fn solve(
             })
             .collect::<Vec<_>>();
 
+        let cancelled = cancel_vars
+            .iter()
+            .map(|cancel_var| {
+                cancel_var.map_or(Ok(false), |z| {
+                    model.get_obj_attr(attr::X, &z).map(|x| x > 0.5)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(SolverError::GurobiError)?;
+
         const USE_MINIMIZE: bool = true;
 
-        let solution = if USE_MINIMIZE {
+        // The minimization only keeps the conflict priorities, hard
+        // connections and maximum travel times, so it could break the
+        // capacity of cumulative resources, give up soft connections, run
+        // cancelled trains, move a visit into a blocked interval, add
+        // running-time supplements or run early.
+        let solution = if USE_MINIMIZE
+            && buffer_phase.is_none()
+            && problem.capacities.is_empty()
+            && problem.blocked.is_empty()
+            && problem
+                .trains
+                .iter()
+                .all(|t| t.running_time_cost.is_none() && t.earliness_cost.is_none())
+            && problem.connections.iter().all(|c| c.penalty.is_none())
+            && cancel_vars.iter().all(Option::is_none)
+        {
             minimize::minimize_solution(env, problem, priorities)?
         } else {
             let mut solution = Vec::new();
@@ -520,19 +681,60 @@ fn solve(
             }
         }
 
-        // Check the conflicts
+        // Check the conflicts. In the buffer phase, a conflict is also
+        // added when its buffer is below the one being maximised.
+        let buffer_threshold = match buffer_phase.as_ref() {
+            Some(phase) => phase.threshold(&model)?,
+            None => 0,
+        };
         let violated_conflicts = {
             let _p = hprof::enter("check conflicts");
 
             let mut cs: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
             for visit_pair @ ((t1, v1), (t2, v2)) in visit_conflicts.iter().copied() {
-                if !check_conflict(visit_pair, |t, v| solution[t][v])? {
+                if cancelled[t1] || cancelled[t2] {
+                    continue;
+                }
+                let violated = if buffer_phase.is_some() {
+                    !added_conflicts.contains(&visit_pair)
+                        && problem.buffer(&solution, visit_pair.0, visit_pair.1)
+                            < buffer_threshold
+                } else {
+                    !check_conflict(problem, visit_pair, |t, v| solution[t][v])?
+                };
+                if violated {
                     cs.entry((t1, t2)).or_default().push((v1, v2));
                 }
             }
             cs
         };
 
+        let violated_capacities = problem
+            .capacities
+            .iter()
+            .filter(|(resource_id, _)| !added_capacities.contains(*resource_id))
+            .filter(|&(&resource_id, &capacity)| {
+                let intervals = problem
+                    .trains
+                    .iter()
+                    .enumerate()
+                    .filter(|&(t, _)| !cancelled[t])
+                    .flat_map(|(t, train)| {
+                        train
+                            .visits
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, visit)| visit.resource_id == resource_id)
+                            .map(move |(v, _)| (t, v))
+                    })
+                    .map(|(t, v)| (solution[t][v], solution[t][v + 1], (t, v)))
+                    .collect();
+                !crate::solvers::ddd::shared::capacity::over_capacity(intervals, capacity)
+                    .is_empty()
+            })
+            .map(|(&resource_id, &capacity)| (resource_id, capacity))
+            .collect::<Vec<_>>();
+
         // Check the lazy objective
         let mut refined_objective = false;
         if let Some(lazy_stepfunction) = lazy_stepfunction.as_mut() {
@@ -546,7 +748,7 @@ fn solve(
             for (train_idx, train) in problem.trains.iter().enumerate() {
                 for visit_idx in 0..train.visits.len() {
                     if let Some(aimed) = train.visits[visit_idx].aimed {
-                        let time_var = t_vars[train_idx][visit_idx];
+                        let time_var = cost_t_vars[train_idx][visit_idx];
                         let curr_t = solution[train_idx][visit_idx];
                         // println!("t{} {} @ {}  delay {}", train_idx, visit_idx, curr_t, (curr_t - aimed).max(0));
 
@@ -554,7 +756,9 @@ fn solve(
                         // let mut added_this = false;
                         for (threshold, cost) in iter_infinite_staircase(interval).skip(steps.len())
                         {
-                            if cost > train.visit_delay_cost(delay_cost_type, visit_idx, curr_t) {
+                            if cost
+                                > train.unweighted_visit_delay_cost(delay_cost_type, visit_idx, curr_t)
+                            {
                                 // println!("  next cost {} exceeds {}", cost, train.visit_delay_cost(delay_cost_type, visit_idx, curr_t));
                                 break;
                             }
@@ -564,7 +768,7 @@ fn solve(
                             // added_this = true;
                             // println!("Adding t{} v{} thr{} cost{} on {}", train_idx, visit_idx, threshold, cost, train.visit_delay_cost(delay_cost_type, visit_idx, curr_t));
 
-                            let cost_diff = 1;
+                            let cost_diff = train.weight;
                             let threshold_var_name = format!(
                                 "tn{}_v{}_tk{}_dly{}",
                                 train_names[train_idx],
@@ -603,8 +807,21 @@ fn solve(
             }
         };
 
-        if !violated_conflicts.is_empty() || refined_objective {
+        if !violated_conflicts.is_empty() || !violated_capacities.is_empty() || refined_objective
+        {
             refinement_iterations += 1;
+            for (resource_id, capacity) in violated_capacities {
+                n_resource_constraints += add_capacity_constraints(
+                    &mut model,
+                    problem,
+                    resource_id,
+                    capacity,
+                    &t_vars,
+                    &cancel_vars,
+                    train_names,
+                )?;
+                added_capacities.insert(resource_id);
+            }
             for ((t1, t2), pairs) in violated_conflicts {
                 let (v1, v2) = *pairs.iter().min_by_key(|(v1, v2)| v1 + v2).unwrap();
                 let visit_pair = ((t1, v1), (t2, v2));
@@ -612,16 +829,47 @@ fn solve(
                     problem,
                     visit_pair,
                     t_vars: &t_vars,
+                    cancel_vars: &cancel_vars,
                     train_names,
                     resource_names,
                 };
                 let var = add_conflict_constraint(&mut model, &conflict)?;
+                if let Some(phase) = buffer_phase.as_ref() {
+                    phase.add_buffer_constraint(&mut model, &conflict, var)?;
+                }
                 assert!(priority_vars.insert(visit_pair, var).is_none());
                 n_resource_constraints += 1;
                 assert!(added_conflicts.insert(visit_pair));
             }
+        } else if let Some(objective) = buffer_objective.filter(|_| buffer_phase.is_none()) {
+            // The delay cost is optimal. It becomes a bound, and the buffers
+            // of the conflicts added so far the objective.
+            println!(
+                "Solved with cost {}, maximising the buffers after {} refinements",
+                cost, refinement_iterations
+            );
+            let mut best = solution;
+            for (times, cancelled) in best.iter_mut().zip(cancelled) {
+                if cancelled {
+                    times.clear();
+                }
+            }
+            let phase = BufferPhase::start(&mut model, objective, cost, best)?;
+            for (&visit_pair, &var) in priority_vars.iter() {
+                let conflict = ConflictInformation {
+                    problem,
+                    visit_pair,
+                    t_vars: &t_vars,
+                    cancel_vars: &cancel_vars,
+                    train_names,
+                    resource_names,
+                };
+                phase.add_buffer_constraint(&mut model, &conflict, var)?;
+            }
+            buffer_phase = Some(phase);
         } else {
             // success
+            let cost = buffer_phase.as_ref().map_or(cost, |phase| phase.cost as f64);
             println!(
                 "Solved with cost {} and {} conflict constraints after {} refinements",
                 cost,
@@ -685,6 +933,12 @@ fn solve(
                 iteration, n_travel_constraints, n_resource_constraints
             );
             println!("BIGM ITERATIONS {}", refinement_iterations);
+            let mut solution = solution;
+            for (times, cancelled) in solution.iter_mut().zip(cancelled) {
+                if cancelled {
+                    times.clear();
+                }
+            }
             return Ok(solution);
         }
     }
@@ -729,11 +983,343 @@ fn do_output_stats(
     output_stats("ub".to_string(), ub.into());
 }
 
-fn add_travel_constraint(
-    problem: &Problem,
-    (train_idx, visit_idx): (usize, usize),
+/// Adds the objective terms of `delay_cost_type` for the delay of every
+/// visit with an aimed time, measured on `cost_t_vars`.
+fn add_delay_cost(
     model: &mut grb::Model,
-    t_vars: &[Vec<grb::Var>],
+    problem: &Problem,
+    delay_cost_type: DelayCostType,
+    cost_t_vars: &[Vec<grb::Var>],
+    train_names: &[String],
+    resource_names: &[String],
+) -> Result<(), SolverError> {
+    use grb::prelude::*;
+
+    match delay_cost_type {
+        DelayCostType::FiniteSteps1_3Min
+        | DelayCostType::FiniteSteps1_5Min
+        | DelayCostType::FiniteSteps123
+        | DelayCostType::FiniteSteps12345
+        | DelayCostType::FiniteSteps139 => {
+            let delay_cost = match delay_cost_type {
+                DelayCostType::FiniteSteps1_3Min => DelayCostThresholds::f1_3min(),
+                DelayCostType::FiniteSteps1_5Min => DelayCostThresholds::f1_5min(),
+                DelayCostType::FiniteSteps123 => DelayCostThresholds::f123(),
+                DelayCostType::FiniteSteps12345 => DelayCostThresholds::f12345(),
+                DelayCostType::FiniteSteps139 => DelayCostThresholds::f139(),
+                _ => panic!(),
+            };
+            for (train_idx, train) in problem.trains.iter().enumerate() {
+                for visit_idx in 0..train.visits.len() {
+                    if let Some(aimed) = train.visits[visit_idx].aimed {
+                        let time_var = cost_t_vars[train_idx][visit_idx];
+
+                        // create a variable for each delay threshold
+                        let thresholds = &delay_cost.thresholds;
+                        for threshold_idx in (0..thresholds.len()).rev() {
+                            let (_prev_threshold, prev_cost) =
+                                thresholds.get(threshold_idx + 1).unwrap_or(&(0, 0));
+                            let (threshold, cost) = thresholds[threshold_idx];
+                            let threshold = threshold;
+
+                            let cost_diff = cost - prev_cost;
+                            assert!(cost_diff > 0);
+
+                            let threshold_var_name = format!(
+                                "tn{}_v{}_tk{}_dly{}",
+                                train_names[train_idx],
+                                visit_idx,
+                                resource_names[train.visits[visit_idx].resource_id],
+                                threshold
+                            );
+
+                            // Add threshold_var to the objective with cost `diff_cost`.
+                            #[allow(clippy::unnecessary_cast)]
+                            let threshold_var =
+                                add_intvar!(model, name: &threshold_var_name, bounds: 0..1, obj: cost_diff * train.weight)
+                                    .map_err(SolverError::GurobiError)?;
+
+                            // If last_t - aimed >= threshold+1 then threshold_var must be 1
+
+                            #[allow(clippy::useless_conversion)]
+                            model
+                                .add_constr(
+                                    &format!("has_{}", threshold_var_name),
+                                    c!(time_var - aimed <= threshold + M * threshold_var),
+                                )
+                                .map_err(SolverError::GurobiError)?;
+                        }
+                    }
+                }
+            }
+        }
+        DelayCostType::InfiniteSteps60
+        | DelayCostType::InfiniteSteps180
+        | DelayCostType::InfiniteSteps360 => {
+            // this is done lazily when a solution has been found
+            //lazy_stepfunction = Some(Default::default());
+            // The lazy_stepfunction uses big-M and can in princple have any shape, but since we know that
+            // the step funtion has a fixed spacing and step increase, we can just use a single integer variable with cost.
+
+            let interval = match delay_cost_type {
+                DelayCostType::InfiniteSteps60 => 60,
+                DelayCostType::InfiniteSteps180 => 180,
+                DelayCostType::InfiniteSteps360 => 360,
+                _ => panic!(),
+            };
+
+            for (train_idx, train) in problem.trains.iter().enumerate() {
+                for visit_idx in 0..train.visits.len() {
+                    if let Some(aimed) = train.visits[visit_idx].aimed {
+                        let time_var = cost_t_vars[train_idx][visit_idx];
+                        let objective_var_name = format!(
+                            "tn{}_v{}_tk{}_dlysteps",
+                            train_names[train_idx],
+                            visit_idx,
+                            resource_names[train.visits[visit_idx].resource_id],
+                        );
+                        let objective_var =
+                            add_intvar!(model, name:&objective_var_name,bounds:0.., obj: train.weight as f64)
+                                .map_err(SolverError::GurobiError)?;
+
+                        #[allow(clippy::useless_conversion)]
+                        model
+                            .add_constr(
+                                &format!("bound_{}", objective_var_name),
+                                c!(interval * objective_var >= time_var - aimed),
+                            )
+                            .map_err(SolverError::GurobiError)?;
+                    }
+                }
+            }
+        }
+        DelayCostType::Continuous => {
+            for (train_idx, train) in problem.trains.iter().enumerate() {
+                for visit_idx in 0..train.visits.len() {
+                    if let Some(aimed) = train.visits[visit_idx].aimed {
+                        let time_var = cost_t_vars[train_idx][visit_idx];
+                        let objective_var_name = format!(
+                            "tn{}_v{}_tk{}_dly",
+                            train_names[train_idx],
+                            visit_idx,
+                            resource_names[train.visits[visit_idx].resource_id],
+                        );
+                        let objective_var =
+                            add_ctsvar!(model, name:&objective_var_name,bounds:0.., obj: train.weight as f64)
+                                .map_err(SolverError::GurobiError)?;
+
+                        #[allow(clippy::useless_conversion)]
+                        model
+                            .add_constr(
+                                &format!("bound_{}", objective_var_name),
+                                c!(objective_var >= time_var - aimed),
+                            )
+                            .map_err(SolverError::GurobiError)?;
+                    }
+                }
+            }
+        }
+        DelayCostType::Custom(function) => {
+            for (train_idx, train) in problem.trains.iter().enumerate() {
+                for visit_idx in 0..train.visits.len() {
+                    if let Some(aimed) = train.visits[visit_idx].aimed {
+                        add_custom_delay_cost(
+                            model,
+                            function,
+                            train.weight,
+                            cost_t_vars[train_idx][visit_idx],
+                            aimed,
+                            &format!(
+                                "tn{}_v{}_tk{}",
+                                train_names[train_idx],
+                                visit_idx,
+                                resource_names[train.visits[visit_idx].resource_id],
+                            ),
+                        )?;
+                    }
+                }
+            }
+        }
+        DelayCostType::PiecewiseLinear => {
+            for (train_idx, train) in problem.trains.iter().enumerate() {
+                for visit_idx in 0..train.visits.len() {
+                    if let Some(function) = train.visit_cost(visit_idx) {
+                        add_piecewise_delay_cost(
+                            model,
+                            function,
+                            train.weight,
+                            cost_t_vars[train_idx][visit_idx],
+                            &format!(
+                                "tn{}_v{}_tk{}",
+                                train_names[train_idx],
+                                visit_idx,
+                                resource_names[train.visits[visit_idx].resource_id],
+                            ),
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Adds the objective terms of a user-defined cost function for one visit,
+/// scaled by the train's priority weight:
+/// a threshold variable per finite step, an integer step counter for the
+/// repeating staircase, and a continuous ramp variable per linear segment.
+fn add_custom_delay_cost(
+    model: &mut grb::Model,
+    function: &DelayCostFunction,
+    weight: usize,
+    time_var: grb::Var,
+    aimed: i32,
+    name: &str,
+) -> Result<(), SolverError> {
+    use grb::prelude::*;
+
+    let thresholds = &function.steps;
+    for threshold_idx in (0..thresholds.len()).rev() {
+        let (_prev_threshold, prev_cost) = thresholds.get(threshold_idx + 1).unwrap_or(&(0, 0));
+        let (threshold, cost) = thresholds[threshold_idx];
+        let cost_diff = cost - prev_cost;
+        assert!(cost_diff > 0);
+
+        let threshold_var_name = format!("{}_dly{}", name, threshold);
+        #[allow(clippy::unnecessary_cast)]
+        let threshold_var =
+            add_intvar!(model, name: &threshold_var_name, bounds: 0..1, obj: cost_diff * weight)
+                .map_err(SolverError::GurobiError)?;
+
+        #[allow(clippy::useless_conversion)]
+        model
+            .add_constr(
+                &format!("has_{}", threshold_var_name),
+                c!(time_var - aimed <= threshold + M * threshold_var),
+            )
+            .map_err(SolverError::GurobiError)?;
+    }
+
+    if let Some(repeat) = function.repeat {
+        let steps_var_name = format!("{}_dlyrepeat", name);
+        let steps_var = add_intvar!(model, name: &steps_var_name, bounds: 0.., obj: repeat.increment * weight)
+            .map_err(SolverError::GurobiError)?;
+
+        #[allow(clippy::useless_conversion)]
+        model
+            .add_constr(
+                &format!("bound_{}", steps_var_name),
+                c!(repeat.interval * steps_var >= time_var - aimed - repeat.start),
+            )
+            .map_err(SolverError::GurobiError)?;
+    }
+
+    for (segment_idx, segment) in function.linear.iter().enumerate() {
+        let ramp_var_name = format!("{}_dlylin{}", name, segment_idx);
+        let ramp_var = add_ctsvar!(model, name: &ramp_var_name, bounds: 0.., obj: segment.rate * weight)
+            .map_err(SolverError::GurobiError)?;
+
+        #[allow(clippy::useless_conversion)]
+        model
+            .add_constr(
+                &format!("bound_{}", ramp_var_name),
+                c!(ramp_var >= time_var - aimed - segment.from),
+            )
+            .map_err(SolverError::GurobiError)?;
+    }
+
+    Ok(())
+}
+
+/// Adds the objective term of a per-visit piecewise-linear penalty, scaled by
+/// the train's priority weight. Segment `k` gets a binary `y_k` which is set
+/// when the delay reaches the segment, and a continuous `z_k` measuring how
+/// far into the segment the delay goes. The penalty is
+/// `sum(jump_k * y_k + slope_k * z_k)`, rounded like
+/// [`PiecewiseLinearCost::eval`] by an integer cost variable.
+fn add_piecewise_delay_cost(
+    model: &mut grb::Model,
+    function: &PiecewiseLinearCost,
+    weight: usize,
+    time_var: grb::Var,
+    name: &str,
+) -> Result<(), SolverError> {
+    use grb::prelude::*;
+
+    let aimed = function.aimed;
+    let segments = &function.segments;
+    let reached_vars = segments
+        .iter()
+        .map(|segment| {
+            let reached_var_name = format!("{}_pwl{}", name, segment.from);
+            let reached_var =
+                add_binvar!(model, name: &reached_var_name).map_err(SolverError::GurobiError)?;
+
+            #[allow(clippy::useless_conversion)]
+            model
+                .add_constr(
+                    &format!("has_{}", reached_var_name),
+                    c!(time_var - aimed <= segment.from - 1 + M * reached_var),
+                )
+                .map_err(SolverError::GurobiError)?;
+            Ok(reached_var)
+        })
+        .collect::<Result<Vec<_>, SolverError>>()?;
+
+    let mut penalty = Expr::Constant(0.0);
+    let mut prev_end_value = 0.0;
+    for (segment_idx, segment) in segments.iter().enumerate() {
+        let is_last = segment_idx + 1 == segments.len();
+        let length = (segment.to - segment.from) as f64;
+        let ramp_var_name = format!("{}_pwlramp{}", name, segment.from);
+        let ramp_var = if is_last {
+            add_ctsvar!(model, name: &ramp_var_name, bounds: 0..)
+        } else {
+            add_ctsvar!(model, name: &ramp_var_name, bounds: 0..length)
+        }
+        .map_err(SolverError::GurobiError)?;
+
+        // Within the segment the ramp follows the delay, and a later segment
+        // can only be reached after this one is used up.
+        let next_reached = reached_vars.get(segment_idx + 1).copied();
+        let later = next_reached.map(|v| M * v).unwrap_or_else(|| Expr::Constant(0.0));
+        #[allow(clippy::useless_conversion)]
+        model
+            .add_constr(
+                &format!("bound_{}", ramp_var_name),
+                c!(ramp_var >= time_var - aimed - segment.from - later),
+            )
+            .map_err(SolverError::GurobiError)?;
+        if let Some(next_reached) = next_reached {
+            #[allow(clippy::useless_conversion)]
+            model
+                .add_constr(
+                    &format!("full_{}", ramp_var_name),
+                    c!(ramp_var >= length * next_reached),
+                )
+                .map_err(SolverError::GurobiError)?;
+        }
+
+        let jump = segment.from_value - prev_end_value;
+        penalty = penalty + jump * reached_vars[segment_idx] + segment.slope * ramp_var;
+        prev_end_value = segment.from_value + segment.slope * length;
+    }
+
+    let cost_var_name = format!("{}_pwlcost", name);
+    let cost_var = add_intvar!(model, name: &cost_var_name, bounds: 0.., obj: weight)
+        .map_err(SolverError::GurobiError)?;
+    model
+        .add_constr(&format!("bound_{}", cost_var_name), c!(cost_var >= penalty - 0.5))
+        .map_err(SolverError::GurobiError)?;
+
+    Ok(())
+}
+
+fn add_travel_constraint(
+    problem: &Problem,
+    (train_idx, visit_idx): (usize, usize),
+    model: &mut grb::Model,
+    t_vars: &[Vec<grb::Var>],
     train_names: &[String],
     resource_names: &[String],
 ) -> Result<(), SolverError> {
@@ -755,12 +1341,252 @@ fn add_travel_constraint(
             ),
         )
         .map_err(SolverError::GurobiError)?;
+    if let Some(max_travel_time) = visits[visit_idx].max_travel_time {
+        #[allow(clippy::useless_conversion)]
+        model
+            .add_constr(
+                &format!(
+                    "tn{}_v{}_tk{}_maxtravel",
+                    train_names[train_idx],
+                    visit_idx,
+                    resource_names[visits[visit_idx].resource_id]
+                ),
+                c!((t_vars[train_idx][visit_idx + 1]) - (t_vars[train_idx][visit_idx])
+                    <= max_travel_time),
+            )
+            .map_err(SolverError::GurobiError)?;
+    }
+    Ok(())
+}
+
+/// The departing visit of a connection starts `min_transfer` after the
+/// arriving visit. A soft connection can be broken by setting its binary
+/// variable, which costs its penalty. A connection to or from a cancelled
+/// train does not apply.
+fn add_connection_constraint(
+    connection_idx: usize,
+    connection: &Connection,
+    model: &mut grb::Model,
+    t_vars: &[Vec<grb::Var>],
+    cancel_vars: &[Option<grb::Var>],
+    train_names: &[String],
+) -> Result<(), SolverError> {
+    use grb::prelude::*;
+
+    let ((t1, v1), (t2, v2)) = (connection.from, connection.to);
+    let name = format!(
+        "conn{}_tn{}_v{}_tn{}_v{}",
+        connection_idx, train_names[t1], v1, train_names[t2], v2
+    );
+    let relaxation = match connection.penalty {
+        Some(penalty) => {
+            #[allow(clippy::unnecessary_cast)]
+            let broken_var = add_binvar!(model, name: &format!("{}_broken", name), obj: penalty)
+                .map_err(SolverError::GurobiError)?;
+            M * broken_var
+        }
+        None => Expr::Constant(0.0),
+    } + freed_if_cancelled(cancel_vars, t1)
+        + freed_if_cancelled(cancel_vars, t2);
+
+    #[allow(clippy::useless_conversion)]
+    model
+        .add_constr(
+            &name,
+            c!(t_vars[t2][v2] - t_vars[t1][v1] + relaxation >= connection.min_transfer),
+        )
+        .map_err(SolverError::GurobiError)?;
     Ok(())
 }
 
+/// A visit on a resource that is blocked, or in conflict with a blocked
+/// resource, either leaves before the blocked interval starts or enters
+/// after it ends, unless its train is cancelled. Returns the number of
+/// constraints added.
+fn add_blocked_constraints(
+    problem: &Problem,
+    model: &mut grb::Model,
+    t_vars: &[Vec<grb::Var>],
+    cancel_vars: &[Option<grb::Var>],
+    train_names: &[String],
+) -> Result<usize, SolverError> {
+    use grb::prelude::*;
+
+    let mut n_constraints = 0;
+    for (blocked_idx, blocked) in problem.blocked.iter().enumerate() {
+        for (train_idx, train) in problem.trains.iter().enumerate() {
+            for (visit_idx, visit) in train.visits.iter().enumerate() {
+                if visit.resource_id != blocked.resource_id
+                    && !problem.conflicts.contains(blocked.resource_id, visit.resource_id)
+                {
+                    continue;
+                }
+                let name =
+                    format!("blocked{}_tn{}_v{}", blocked_idx, train_names[train_idx], visit_idx);
+                let after_var = add_binvar!(model, name: &format!("{}_side", name))
+                    .map_err(SolverError::GurobiError)?;
+                let t_in = t_vars[train_idx][visit_idx];
+                let t_out = t_vars[train_idx][visit_idx + 1];
+
+                #[allow(clippy::useless_conversion)]
+                model
+                    .add_constr(
+                        &format!("{}_before", name),
+                        c!(t_out
+                            <= blocked.from
+                                + M * after_var
+                                + freed_if_cancelled(cancel_vars, train_idx)),
+                    )
+                    .map_err(SolverError::GurobiError)?;
+                #[allow(clippy::useless_conversion)]
+                model
+                    .add_constr(
+                        &format!("{}_after", name),
+                        c!(t_in
+                            >= blocked.to - M * (1 - after_var)
+                                - freed_if_cancelled(cancel_vars, train_idx)),
+                    )
+                    .map_err(SolverError::GurobiError)?;
+                n_constraints += 1;
+            }
+        }
+    }
+    Ok(n_constraints)
+}
+
+/// `M` if the train is cancelled, to disable a constraint on its times.
+fn freed_if_cancelled(cancel_vars: &[Option<grb::Var>], train_idx: usize) -> grb::expr::Expr {
+    match cancel_vars[train_idx] {
+        Some(cancel_var) => M * cancel_var,
+        None => grb::expr::Expr::Constant(0.0),
+    }
+}
+
+/// Limits the number of trains on a cumulative resource. For each pair of
+/// visits `u`, `v` on the resource, `before` orders their start times and
+/// `cleared` can only be set if `u` has left when `v` starts. Every visit
+/// `v` then sees at most `capacity - 1` other visits that started no later
+/// and have not left yet. A cancelled visit counts as cleared and is not
+/// limited. Returns the number of constraints added.
+fn add_capacity_constraints(
+    model: &mut grb::Model,
+    problem: &Problem,
+    resource_id: usize,
+    capacity: usize,
+    t_vars: &[Vec<grb::Var>],
+    cancel_vars: &[Option<grb::Var>],
+    train_names: &[String],
+) -> Result<i32, SolverError> {
+    use grb::prelude::*;
+
+    let visits = problem
+        .trains
+        .iter()
+        .enumerate()
+        .flat_map(|(t, train)| {
+            train
+                .visits
+                .iter()
+                .enumerate()
+                .filter(|(_, visit)| visit.resource_id == resource_id)
+                .map(move |(v, _)| (t, v))
+        })
+        .collect::<Vec<_>>();
+    let end = |(t, v): (usize, usize)| -> Expr {
+        if v + 1 < t_vars[t].len() {
+            t_vars[t][v + 1].into()
+        } else {
+            t_vars[t][v] + problem.trains[t].visits[v].travel_time as f64
+        }
+    };
+    let name = |(t1, v1): (usize, usize), (t2, v2): (usize, usize)| {
+        format!(
+            "cap{}_tn{}_v{}_tn{}_v{}",
+            resource_id, train_names[t1], v1, train_names[t2], v2
+        )
+    };
+
+    let mut n_constraints = 0;
+    let mut before = HashMap::new();
+    for (i, &(t1, v1)) in visits.iter().enumerate() {
+        for &(t2, v2) in visits[i + 1..].iter() {
+            if t1 == t2 {
+                continue;
+            }
+            let before_name = format!("{}_before", name((t1, v1), (t2, v2)));
+            #[allow(clippy::unnecessary_cast)]
+            let before_var =
+                add_binvar!(model, name: &before_name).map_err(SolverError::GurobiError)?;
+
+            #[allow(clippy::useless_conversion)]
+            model
+                .add_constr(
+                    &format!("{}_first", before_name),
+                    c!(t_vars[t1][v1] <= t_vars[t2][v2] + M * (1 - before_var)),
+                )
+                .map_err(SolverError::GurobiError)?;
+            #[allow(clippy::useless_conversion)]
+            model
+                .add_constr(
+                    &format!("{}_second", before_name),
+                    c!(t_vars[t2][v2] <= t_vars[t1][v1] + M * before_var),
+                )
+                .map_err(SolverError::GurobiError)?;
+            n_constraints += 2;
+
+            before.insert(((t1, v1), (t2, v2)), Expr::from(before_var));
+            before.insert(((t2, v2), (t1, v1)), 1 - before_var);
+        }
+    }
+
+    for &v in visits.iter() {
+        let mut occupied = Expr::Constant(0.0);
+        for &u in visits.iter() {
+            let Some(u_before_v) = before.get(&(u, v)) else {
+                continue;
+            };
+            let cleared_name = format!("{}_cleared", name(u, v));
+            #[allow(clippy::unnecessary_cast)]
+            let cleared_var =
+                add_binvar!(model, name: &cleared_name).map_err(SolverError::GurobiError)?;
+
+            #[allow(clippy::useless_conversion)]
+            model
+                .add_constr(
+                    &format!("{}_end", cleared_name),
+                    c!(end(u)
+                        <= t_vars[v.0][v.1]
+                            + M * (1 - cleared_var)
+                            + freed_if_cancelled(cancel_vars, u.0)),
+                )
+                .map_err(SolverError::GurobiError)?;
+            #[allow(clippy::useless_conversion)]
+            model
+                .add_constr(
+                    &format!("{}_order", cleared_name),
+                    c!(cleared_var <= u_before_v.clone()),
+                )
+                .map_err(SolverError::GurobiError)?;
+            n_constraints += 2;
+
+            occupied = occupied + u_before_v.clone() - cleared_var;
+        }
+
+        #[allow(clippy::useless_conversion)]
+        model
+            .add_constr(
+                &format!("cap{}_tn{}_v{}", resource_id, train_names[v.0], v.1),
+                c!(occupied <= capacity as f64 - 1.0 + freed_if_cancelled(cancel_vars, v.0)),
+            )
+            .map_err(SolverError::GurobiError)?;
+        n_constraints += 1;
+    }
+
+    Ok(n_constraints)
+}
+
 pub fn visit_conflicts(problem: &Problem) -> Vec<((usize, usize), (usize, usize))> {
     let mut conflicts = Vec::new();
-    let resource_conflicts = problem.conflicts.iter().copied().collect::<HashSet<_>>();
     for train_idx1 in 0..problem.trains.len() {
         for train_idx2 in (train_idx1 + 1)..problem.trains.len() {
             for visit_idx1 in 0..problem.trains[train_idx1].visits.len() {
@@ -768,12 +1594,7 @@ pub fn visit_conflicts(problem: &Problem) -> Vec<((usize, usize), (usize, usize)
                     let resource1 = problem.trains[train_idx1].visits[visit_idx1].resource_id;
                     let resource2 = problem.trains[train_idx2].visits[visit_idx2].resource_id;
 
-                    let is_conflict1 = resource_conflicts.contains(&(resource1, resource2));
-                    let is_conflict2 = resource_conflicts.contains(&(resource2, resource1));
-
-                    assert!(is_conflict1 == is_conflict2);
-
-                    if is_conflict1 || is_conflict2 {
+                    if problem.conflicts.contains(resource1, resource2) {
                         conflicts.push(((train_idx1, visit_idx1), (train_idx2, visit_idx2)));
                     }
                 }
@@ -784,6 +1605,7 @@ pub fn visit_conflicts(problem: &Problem) -> Vec<((usize, usize), (usize, usize)
 }
 
 pub fn check_conflict(
+    problem: &Problem,
     ((t1, v1), (t2, v2)): ((usize, usize), (usize, usize)),
     t_vars: impl Fn(usize, usize) -> i32,
 ) -> Result<bool, SolverError> {
@@ -796,7 +1618,7 @@ pub fn check_conflict(
 
     // let t1_first = t_t1v1next <= t_t2v2;
     // let t2_first = t_t2v2next <= t_t1v1;
-    let has_separation = separation >= 0;
+    let has_separation = separation >= problem.headway((t1, v1), (t2, v2));
     // println!("t1v1 {}-{}  t2v2 {}-{}  separation {} is_separated {}", t_t1v1, t_t1v1next, t_t2v2, t_t2v2next, separation, has_separation);
     // assert!((separation >= 1e-5) == has_separation);
 
@@ -813,12 +1635,131 @@ struct ConflictInformation<'a> {
     problem: &'a Problem,
     visit_pair: ((usize, usize), (usize, usize)),
     t_vars: &'a [Vec<grb::Var>],
+    cancel_vars: &'a [Option<grb::Var>],
     train_names: &'a [String],
 
     #[allow(dead_code)]
     resource_names: &'a [String],
 }
 
+/// The second phase of [`solve_bigm_with_objectives`], in which the delay cost
+/// is bounded by its optimum and the buffers are maximised.
+struct BufferPhase {
+    objective: BufferObjective,
+    /// The smallest buffer of the conflicts, for [`BufferKind::Min`].
+    min_var: Option<grb::Var>,
+    /// The optimal delay cost, and a schedule for it.
+    cost: i32,
+    best: Vec<Vec<i32>>,
+}
+
+impl BufferPhase {
+    /// Bounds the current objective by `cost` and replaces it with the
+    /// buffers, which are added with [`BufferPhase::add_buffer_constraint`].
+    fn start(
+        model: &mut grb::Model,
+        objective: BufferObjective,
+        cost: f64,
+        best: Vec<Vec<i32>>,
+    ) -> Result<BufferPhase, SolverError> {
+        use grb::prelude::*;
+
+        let vars = model.get_vars().map_err(SolverError::GurobiError)?.to_vec();
+        let weights = model
+            .get_obj_attr_batch(attr::Obj, vars.iter().copied())
+            .map_err(SolverError::GurobiError)?;
+        let delay_cost = vars
+            .iter()
+            .zip(weights)
+            .filter(|(_, w)| *w != 0.0)
+            .map(|(var, w)| w * *var)
+            .grb_sum();
+        #[allow(clippy::useless_conversion)]
+        model
+            .add_constr("delay_cost_bound", c!(delay_cost <= cost + 1e-6))
+            .map_err(SolverError::GurobiError)?;
+        model
+            .set_objective(0.0, Minimize)
+            .map_err(SolverError::GurobiError)?;
+
+        let min_var = match objective.kind {
+            BufferKind::Min => Some(
+                add_ctsvar!(model, name: "min_buffer", bounds: 0..objective.cap, obj: -1)
+                    .map_err(SolverError::GurobiError)?,
+            ),
+            BufferKind::Total => None,
+        };
+        Ok(BufferPhase {
+            objective,
+            min_var,
+            cost: cost.round() as i32,
+            best,
+        })
+    }
+
+    /// Conflicts that are not in the model yet need to be added if their
+    /// buffer is below this in a schedule of the model.
+    fn threshold(&self, model: &grb::Model) -> Result<i32, SolverError> {
+        match self.min_var {
+            Some(min_var) => Ok(model
+                .get_obj_attr(grb::attr::X, &min_var)
+                .map_err(SolverError::GurobiError)?
+                .round() as i32),
+            None => Ok(self.objective.cap),
+        }
+    }
+
+    /// Makes the conflict's buffer, given the order in `choice_var` as set
+    /// by the conflict handlers, count towards the objective.
+    fn add_buffer_constraint(
+        &self,
+        model: &mut grb::Model,
+        conflict: &ConflictInformation,
+        choice_var: grb::Var,
+    ) -> Result<(), SolverError> {
+        use grb::prelude::*;
+
+        let &ConflictInformation {
+            problem,
+            visit_pair: ((t1, v1), (t2, v2)),
+            t_vars,
+            cancel_vars,
+            resource_names: _,
+            train_names,
+        } = conflict;
+
+        let name = format!(
+            "buffer_tn{}_v{}_tn{}_v{}",
+            train_names[t1], v1, train_names[t2], v2
+        );
+        let buffer_var = match self.min_var {
+            Some(min_var) => min_var,
+            None => add_ctsvar!(model, name: &name, bounds: 0..self.objective.cap, obj: -1)
+                .map_err(SolverError::GurobiError)?,
+        };
+        let headway = problem.headway((t1, v1), (t2, v2));
+        let freed = freed_if_cancelled(cancel_vars, t1) + freed_if_cancelled(cancel_vars, t2);
+
+        #[allow(clippy::useless_conversion)]
+        model
+            .add_constr(
+                &format!("{}_first", name),
+                c!(headway + buffer_var + t_vars[t1][v1 + 1]
+                    <= t_vars[t2][v2] + M * (1 - choice_var) + freed.clone()),
+            )
+            .map_err(SolverError::GurobiError)?;
+        #[allow(clippy::useless_conversion)]
+        model
+            .add_constr(
+                &format!("{}_second", name),
+                c!(headway + buffer_var + t_vars[t2][v2 + 1]
+                    <= t_vars[t1][v1] + M * choice_var + freed),
+            )
+            .map_err(SolverError::GurobiError)?;
+        Ok(())
+    }
+}
+
 fn add_bigm_conflict_constraint(
     model: &mut grb::Model,
     conflict: &ConflictInformation,
@@ -826,14 +1767,16 @@ fn add_bigm_conflict_constraint(
     use grb::prelude::*;
 
     let &ConflictInformation {
-        problem: _,
+        problem,
         visit_pair: ((t1, v1), (t2, v2)),
         t_vars,
+        cancel_vars,
         resource_names: _,
         train_names,
     } = conflict;
 
     // println!("adding conflict {:?}", ((t1, v1), (t2, v2)));
+    let headway = problem.headway((t1, v1), (t2, v2));
 
     let choice_var_name = format!(
         "confl_tn{}_v{}_tn{}_v{}",
@@ -843,6 +1786,7 @@ fn add_bigm_conflict_constraint(
     #[allow(clippy::unnecessary_cast)]
     let choice_var =
         add_binvar!(model, name: &choice_var_name).map_err(SolverError::GurobiError)?;
+    let freed = freed_if_cancelled(cancel_vars, t1) + freed_if_cancelled(cancel_vars, t2);
 
     #[allow(clippy::useless_conversion)]
     model
@@ -850,7 +1794,8 @@ fn add_bigm_conflict_constraint(
             &format!("{}_first", choice_var_name),
             // t1 goes first: it reaches v1+1 before t2 reaches v2
             // if choice_var is 1, the constraint is disabled.
-            c!(t_vars[t1][v1 + 1] <= t_vars[t2][v2] + M * (1 - choice_var)),
+            c!(headway + t_vars[t1][v1 + 1]
+                <= t_vars[t2][v2] + M * (1 - choice_var) + freed.clone()),
         )
         .map_err(SolverError::GurobiError)?;
 
@@ -859,7 +1804,7 @@ fn add_bigm_conflict_constraint(
         .add_constr(
             &format!("{}_second", choice_var_name),
             // t2 goes first: it reaches v2+1 before t1 reaches v1
-            c!(t_vars[t2][v2 + 1] <= t_vars[t1][v1] + M * (choice_var)),
+            c!(headway + t_vars[t2][v2 + 1] <= t_vars[t1][v1] + M * (choice_var) + freed),
         )
         .map_err(SolverError::GurobiError)?;
     Ok(choice_var)
@@ -875,6 +1820,7 @@ fn add_hull_conflict_constraint(
         problem,
         visit_pair: ((t1, v1), (t2, v2)),
         t_vars,
+        cancel_vars: _,
         resource_names: _,
         train_names: _,
     } = conflict;
@@ -989,19 +1935,20 @@ fn add_hull_conflict_constraint(
     let t2s_lb = problem.trains[t2].visits[v2].earliest;
     let t1f_b = split_vars[1].1;
     let t1f_lb = problem.trains[t1].visits[v1 + 1].earliest;
+    let headway = problem.headway((t1, v1), (t2, v2));
 
     #[allow(clippy::useless_conversion)]
     model
         .add_constr(
             &format!("{}_a", choice_var_name),
-            c!((t2f_a + (1 - choice_var) * t2f_lb) - (t1s_a + (1 - choice_var) * t1s_lb) <= 0.0f64),
+            c!((t2f_a + (1 - choice_var) * (t2f_lb + headway)) - (t1s_a + (1 - choice_var) * t1s_lb) <= 0.0f64),
         )
         .map_err(SolverError::GurobiError)?;
     #[allow(clippy::useless_conversion)]
     model
         .add_constr(
             &format!("{}_b", choice_var_name),
-            c!((t1f_b + choice_var * t1f_lb) - (t2s_b + choice_var * t2s_lb) <= 0.0f64),
+            c!((t1f_b + choice_var * (t1f_lb + headway)) - (t2s_b + choice_var * t2s_lb) <= 0.0f64),
         )
         .map_err(SolverError::GurobiError)?;
 
@@ -35,6 +35,10 @@ pub fn solve_binarized_bigm(
     let _p = hprof::enter("binarized bigm solver");
     use grb::prelude::*;
 
+    if !problem.latest_bounds_reachable() {
+        return Err(SolverError::NoSolution);
+    }
+
     let mut model = Model::with_env("model1", env).map_err(SolverError::GurobiError)?;
     let mut n_travel_constraints = 0;
     let mut n_resource_constraints = 0;
@@ -62,7 +66,11 @@ pub fn solve_binarized_bigm(
         cost: impl Fn(i32) -> f64,
     ) -> TimeIndexedVar {
         let lb = round(lb, ti_interval);
-        let ub = round(ub, ti_interval);
+        // Do not round past `ub`, which may be a hard `latest` bound.
+        let ub = match round(ub, ti_interval) {
+            rounded if rounded > ub => rounded - ti_interval,
+            rounded => rounded,
+        };
 
         let mut vars = Vec::new();
         let mut expr = Expr::Constant(0.0);
@@ -115,7 +123,7 @@ pub fn solve_binarized_bigm(
                         train_names[train_idx], visit_idx, resource_names[visit.resource_id]
                     );
                     let lb = visit.earliest;
-                    let ub = lb + M as i32;
+                    let ub = visit.latest.map_or(lb + M as i32, |latest| latest.min(lb + M as i32));
                     mk_ti_var(&mut model, lb, ub, &name, ti_interval, |t| {
                         problem.trains[train_idx].visit_delay_cost(delay_cost_type, visit_idx, t)
                             as f64
@@ -298,7 +306,7 @@ pub fn solve_binarized_bigm(
 
             let mut cs: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
             for visit_pair @ ((t1, v1), (t2, v2)) in visit_conflicts.iter().copied() {
-                if !check_conflict(visit_pair, |t, v| solution[t][v])? {
+                if !check_conflict(problem, visit_pair, |t, v| solution[t][v])? {
                     cs.entry((t1, t2)).or_default().push((v1, v2));
                 }
             }
@@ -398,7 +406,6 @@ fn add_travel_constraint(
 
 pub fn visit_conflicts(problem: &Problem) -> Vec<((usize, usize), (usize, usize))> {
     let mut conflicts = Vec::new();
-    let resource_conflicts = problem.conflicts.iter().copied().collect::<HashSet<_>>();
     for train_idx1 in 0..problem.trains.len() {
         for train_idx2 in (train_idx1 + 1)..problem.trains.len() {
             for visit_idx1 in 0..problem.trains[train_idx1].visits.len() {
@@ -406,12 +413,7 @@ pub fn visit_conflicts(problem: &Problem) -> Vec<((usize, usize), (usize, usize)
                     let resource1 = problem.trains[train_idx1].visits[visit_idx1].resource_id;
                     let resource2 = problem.trains[train_idx2].visits[visit_idx2].resource_id;
 
-                    let is_conflict1 = resource_conflicts.contains(&(resource1, resource2));
-                    let is_conflict2 = resource_conflicts.contains(&(resource2, resource1));
-
-                    assert!(is_conflict1 == is_conflict2);
-
-                    if is_conflict1 || is_conflict2 {
+                    if problem.conflicts.contains(resource1, resource2) {
                         conflicts.push(((train_idx1, visit_idx1), (train_idx2, visit_idx2)));
                     }
                 }
@@ -422,6 +424,7 @@ pub fn visit_conflicts(problem: &Problem) -> Vec<((usize, usize), (usize, usize)
 }
 
 pub fn check_conflict(
+    problem: &Problem,
     ((t1, v1), (t2, v2)): ((usize, usize), (usize, usize)),
     t_vars: impl Fn(usize, usize) -> i32,
 ) -> Result<bool, SolverError> {
@@ -434,7 +437,7 @@ pub fn check_conflict(
 
     // let t1_first = t_t1v1next <= t_t2v2;
     // let t2_first = t_t2v2next <= t_t1v1;
-    let has_separation = separation >= 0;
+    let has_separation = separation >= problem.headway((t1, v1), (t2, v2));
     // println!("t1v1 {}-{}  t2v2 {}-{}  separation {} is_separated {}", t_t1v1, t_t1v1next, t_t2v2, t_t2v2next, separation, has_separation);
     // assert!((separation >= 1e-5) == has_separation);
 
@@ -464,7 +467,7 @@ fn add_bigm_conflict_constraint(
     use grb::prelude::*;
 
     let &ConflictInformation {
-        problem: _,
+        problem,
         visit_pair: ((t1, v1), (t2, v2)),
         t_vars,
         resource_names: _,
@@ -472,6 +475,7 @@ fn add_bigm_conflict_constraint(
     } = conflict;
 
     // println!("adding conflict {:?}", ((t1, v1), (t2, v2)));
+    let headway = problem.headway((t1, v1), (t2, v2)) as f64;
 
     let choice_var_name = format!(
         "confl_tn{}_v{}_tn{}_v{}",
@@ -488,7 +492,7 @@ fn add_bigm_conflict_constraint(
             &format!("{}_first", choice_var_name),
             // t1 goes first: it reaches v1+1 before t2 reaches v2
             // if choice_var is 1, the constraint is disabled.
-            c!(t_vars[t1][v1 + 1].expr.clone()
+            c!(t_vars[t1][v1 + 1].expr.clone() + headway
                 <= t_vars[t2][v2].expr.clone() + M * (1 - choice_var)),
         )
         .map_err(SolverError::GurobiError)?;
@@ -498,7 +502,8 @@ fn add_bigm_conflict_constraint(
         .add_constr(
             &format!("{}_second", choice_var_name),
             // t2 goes first: it reaches v2+1 before t1 reaches v1
-            c!(t_vars[t2][v2 + 1].expr.clone() <= t_vars[t1][v1].expr.clone() + M * (choice_var)),
+            c!(t_vars[t2][v2 + 1].expr.clone() + headway
+                <= t_vars[t1][v1].expr.clone() + M * (choice_var)),
         )
         .map_err(SolverError::GurobiError)?;
     Ok(choice_var)
@@ -1,5 +1,4 @@
 use grb::{add_binvar, c, expr::GurobiSum};
-use std::collections::HashMap;
 
 use crate::solvers::ddd::maxsat_ladder::SolveStats;
 use crate::solvers::SolverError;
@@ -30,14 +29,6 @@ pub fn solve_milp_fixed_ti(
     solver.set_param(grb::param::OutputFlag, 1).unwrap();
     solver.set_param(grb::param::LogToConsole, 1).unwrap();
 
-    let mut conflicting_resources: HashMap<usize, Vec<usize>> = HashMap::new();
-    for (a, b) in problem.conflicts.iter() {
-        conflicting_resources.entry(*a).or_default().push(*b);
-        if *a != *b {
-            conflicting_resources.entry(*b).or_default().push(*a);
-        }
-    }
-
     let mut resource_visits: Vec<Vec<(usize, usize)>> = Vec::new();
 
     let mut t_vars = Vec::new();
@@ -119,27 +110,26 @@ pub fn solve_milp_fixed_ti(
         println!("   c3 t{}", train1_idx);
         for (visit1_idx, visit) in train.visits.iter().enumerate() {
             // Find conflicting visits
-            if let Some(conflicting_resources) = conflicting_resources.get(&visit.resource_id) {
-                for other_resource in conflicting_resources.iter().copied() {
-                    for (train2_idx, visit2_idx) in resource_visits[other_resource].iter().copied()
-                    {
-                        if train2_idx == train1_idx {
-                            continue;
-                        }
+            let conflicting_resources = problem.conflicts.neighbours(visit.resource_id);
+            for other_resource in conflicting_resources.iter().copied() {
+                for (train2_idx, visit2_idx) in resource_visits[other_resource].iter().copied()
+                {
+                    if train2_idx == train1_idx {
+                        continue;
+                    }
 
-                        for (t1_in, var1) in t_vars[train1_idx][visit1_idx].iter() {
-                            for (t2_in, var2) in t_vars[train2_idx][visit2_idx].iter() {
-                                let t1_out = t1_in + visit.travel_time;
-                                let t2_out = t2_in
-                                    + problem.trains[train2_idx].visits[visit2_idx].travel_time;
+                    for (t1_in, var1) in t_vars[train1_idx][visit1_idx].iter() {
+                        for (t2_in, var2) in t_vars[train2_idx][visit2_idx].iter() {
+                            let t1_out = t1_in + visit.travel_time;
+                            let t2_out = t2_in
+                                + problem.trains[train2_idx].visits[visit2_idx].travel_time;
 
-                                let separation = (*t2_in - t1_out).max(*t1_in - t2_out);
-                                let has_separation = separation >= 0;
+                            let separation = (*t2_in - t1_out).max(*t1_in - t2_out);
+                            let has_separation = separation >= 0;
 
-                                if !has_separation {
-                                    // solver.add_clause(None, vec![-var1, -var2]);
-                                    solver.add_constr("confl", c!(*var1 + *var2 <= 1)).unwrap();
-                                }
+                            if !has_separation {
+                                // solver.add_clause(None, vec![-var1, -var2]);
+                                solver.add_constr("confl", c!(*var1 + *var2 <= 1)).unwrap();
                             }
                         }
                     }
@@ -233,7 +223,10 @@ pub fn solve(
                 .map(|visit| {
                     let mut time_vars = Vec::new();
                     let mut time = round(visit.earliest);
-                    while time < visit.earliest + big_m {
+                    let end = visit.latest.map_or(visit.earliest + big_m, |latest| {
+                        (latest + 1).min(visit.earliest + big_m)
+                    });
+                    while time < end {
                         time_vars.push(time);
                         time += discretization_interval;
                     }
@@ -63,7 +63,7 @@ pub fn solve(
             occupations.push((train_idx, visit_idx));
             if problem
                 .conflicts
-                .contains(&(visit.resource_id, visit.resource_id))
+                .contains(visit.resource_id, visit.resource_id)
             {
                 resource_usage
                     .entry(visit.resource_id)
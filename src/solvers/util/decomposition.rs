@@ -0,0 +1,162 @@
+//! Solving independent parts of an instance separately.
+//!
+//! Trains that never visit conflicting resources do not interact, so the
+//! components found by [`Problem::components`](crate::problem::Problem::components)
+//! can be solved one by one (or in parallel) and the schedules put back
+//! together. The cost of the merged schedule is the sum of the component
+//! costs.
+
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde_json::{json, Map, Value};
+
+use crate::problem::{DelayCostType, NamedProblem};
+use crate::solvers::SolverError;
+
+struct ComponentResult {
+    solution: Result<Vec<Vec<i32>>, SolverError>,
+    time_ms: f64,
+    stats: Map<String, Value>,
+}
+
+/// Solves each component of `problem` with `solve` and merges the results.
+/// Fails with the first component error. Per-component statistics are
+/// reported as `components` through `output_stats`, each with the map that
+/// `solve` collected its own statistics in as `solver_stats`.
+pub fn solve_decomposed(
+    problem: &NamedProblem,
+    delay_cost_type: DelayCostType,
+    mut solve: impl FnMut(
+        &NamedProblem,
+        &mut Map<String, Value>,
+    ) -> Result<Vec<Vec<i32>>, SolverError>,
+    output_stats: impl FnMut(String, Value),
+) -> Result<Vec<Vec<i32>>, SolverError> {
+    let components = problem.problem.components();
+    let results = components
+        .iter()
+        .map(|trains| {
+            let start = Instant::now();
+            let mut stats = Map::new();
+            let solution = solve(&problem.subproblem(trains), &mut stats);
+            ComponentResult {
+                solution,
+                time_ms: start.elapsed().as_secs_f64() * 1000.0,
+                stats,
+            }
+        })
+        .collect::<Vec<_>>();
+    merge(problem, delay_cost_type, &components, results, output_stats)
+}
+
+/// Like [`solve_decomposed`], but solves up to `threads` components at the
+/// same time. The largest components are started first.
+pub fn solve_decomposed_parallel(
+    problem: &NamedProblem,
+    delay_cost_type: DelayCostType,
+    threads: usize,
+    solve: impl Fn(&NamedProblem, &mut Map<String, Value>) -> Result<Vec<Vec<i32>>, SolverError>
+        + Sync,
+    output_stats: impl FnMut(String, Value),
+) -> Result<Vec<Vec<i32>>, SolverError> {
+    let components = problem.problem.components();
+    let mut queue = (0..components.len()).collect::<Vec<_>>();
+    queue.sort_by_key(|c| std::cmp::Reverse(components[*c].len()));
+    let queue = Mutex::new(queue.into_iter());
+
+    let results = Mutex::new((0..components.len()).map(|_| None).collect::<Vec<_>>());
+    std::thread::scope(|scope| {
+        for _ in 0..threads.max(1).min(components.len()) {
+            scope.spawn(|| loop {
+                let Some(component_idx) = queue.lock().unwrap().next() else {
+                    break;
+                };
+                let start = Instant::now();
+                let mut stats = Map::new();
+                let solution = solve(&problem.subproblem(&components[component_idx]), &mut stats);
+                results.lock().unwrap()[component_idx] = Some(ComponentResult {
+                    solution,
+                    time_ms: start.elapsed().as_secs_f64() * 1000.0,
+                    stats,
+                });
+            });
+        }
+    });
+
+    let results = results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(Option::unwrap)
+        .collect();
+    merge(problem, delay_cost_type, &components, results, output_stats)
+}
+
+fn merge(
+    problem: &NamedProblem,
+    delay_cost_type: DelayCostType,
+    components: &[Vec<usize>],
+    results: Vec<ComponentResult>,
+    mut output_stats: impl FnMut(String, Value),
+) -> Result<Vec<Vec<i32>>, SolverError> {
+    let mut solution = vec![Vec::new(); problem.problem.trains.len()];
+    let mut stats = Vec::new();
+    let mut error = None;
+    for (component_idx, (trains, result)) in components.iter().zip(results).enumerate() {
+        let n_visits = trains
+            .iter()
+            .map(|t| problem.problem.trains[*t].visits.len())
+            .sum::<usize>();
+        let (status, cost) = match result.solution {
+            Ok(component_solution) => {
+                let cost = problem
+                    .problem
+                    .subproblem(trains)
+                    .cost(&component_solution, delay_cost_type);
+                for (train_idx, times) in trains.iter().zip(component_solution) {
+                    solution[*train_idx] = times;
+                }
+                ("ok", Some(cost))
+            }
+            Err(e) => {
+                let status = match e {
+                    SolverError::NoSolution => "no_solution",
+                    SolverError::Timeout => "timeout",
+                    SolverError::OutOfMemory => "oom",
+                    SolverError::GurobiError(_) => "gurobi_error",
+                };
+                error.get_or_insert(e);
+                (status, None)
+            }
+        };
+        stats.push(json!({
+            "index": component_idx,
+            "trains": trains.len(),
+            "visits": n_visits,
+            "status": status,
+            "cost": cost,
+            "sol_time": result.time_ms,
+            "solver_stats": result.stats,
+        }));
+    }
+
+    println!(
+        "Solved {} independent components of {} trains",
+        components.len(),
+        problem.problem.trains.len()
+    );
+    output_stats("components".to_string(), Value::Array(stats));
+    if let Some(e) = error {
+        return Err(e);
+    }
+
+    if problem
+        .problem
+        .verify_solution(&solution, delay_cost_type)
+        .is_none()
+    {
+        return Err(SolverError::NoSolution);
+    }
+    Ok(solution)
+}
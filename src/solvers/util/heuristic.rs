@@ -28,8 +28,12 @@ pub fn spawn_heuristic_thread(
                     Some(&sol),
                 )
                 .unwrap();
-                if let Some(ub_sol) = ub_sol {
-                    let ub_cost = problem.verify_solution(&ub_sol, delay_cost_type).unwrap();
+                // The heuristic ignores `latest` bounds, so its schedule may be rejected.
+                let ub = ub_sol.and_then(|ub_sol| {
+                    let ub_cost = problem.verify_solution(&ub_sol, delay_cost_type)?;
+                    Some((ub_cost, ub_sol))
+                });
+                if let Some((ub_cost, ub_sol)) = ub {
                     if sol_out_tx.send((ub_cost, ub_sol)).is_ok() {
                         println!("HEUR.FEAS. {}", ub_cost);
                     }
@@ -55,8 +59,11 @@ pub fn spawn_heuristic_thread(
                     Some(&sol),
                 )
                 .unwrap();
-                if let Some(ub_sol) = ub_sol {
-                    let ub_cost = problem.verify_solution(&ub_sol, delay_cost_type).unwrap();
+                let ub = ub_sol.and_then(|ub_sol| {
+                    let ub_cost = problem.verify_solution(&ub_sol, delay_cost_type)?;
+                    Some((ub_cost, ub_sol))
+                });
+                if let Some((ub_cost, ub_sol)) = ub {
                     if sol_out_tx.send((ub_cost, ub_sol)).is_ok() {
                         println!("HEUR.FEAS. {}", ub_cost);
                     }
@@ -0,0 +1,69 @@
+//! Lexicographic optimisation of two delay cost types.
+//!
+//! The primary objective is solved first, giving the optimum `C1`. The
+//! solver is then run again on the secondary cost alone, with the primary
+//! cost kept at most `C1` as a hard constraint (a [`CostBound`]), so it
+//! minimises the secondary cost among the primary-optimal schedules. The
+//! solver must support [`CostBound`]s: `maxsat_ladder_sc`, `incremental_sat`
+//! and the Big-M MILP do.
+
+use serde_json::{Map, Value};
+
+use crate::problem::{CostBound, DelayCostType, Problem};
+use crate::solvers::SolverError;
+
+/// Minimises `secondary` among the schedules that are optimal for `primary`.
+/// `solve` is called once per phase with the cost type to optimise, the
+/// bound on the primary cost in the second phase, and a map to collect its
+/// statistics in.
+///
+/// Both objective values are reported as `primary_cost` and
+/// `secondary_cost`. The statistics of the first phase are reported under
+/// `primary_phase`, those of the second phase are passed on as they are.
+/// Fails if the schedule of the second phase does not keep the primary
+/// optimum.
+pub fn solve_lexicographic(
+    problem: &Problem,
+    primary: DelayCostType,
+    secondary: DelayCostType,
+    mut solve: impl FnMut(
+        DelayCostType,
+        Option<CostBound>,
+        &mut Map<String, Value>,
+    ) -> Result<Vec<Vec<i32>>, SolverError>,
+    mut output_stats: impl FnMut(String, Value),
+) -> Result<Vec<Vec<i32>>, SolverError> {
+    let mut primary_stats = Map::new();
+    let primary_solution = solve(primary, None, &mut primary_stats);
+    output_stats("primary_phase".to_string(), primary_stats.into());
+    let primary_solution = primary_solution?;
+    let bound = CostBound {
+        cost_type: primary,
+        cost: problem.cost(&primary_solution, primary),
+    };
+    println!("Lexicographic: primary optimum {}", bound.cost);
+
+    let mut secondary_stats = Map::new();
+    let solution = solve(secondary, Some(bound), &mut secondary_stats);
+    for (k, v) in secondary_stats {
+        output_stats(k, v);
+    }
+    let solution = solution?;
+
+    let primary_cost = problem.cost(&solution, primary);
+    let secondary_cost = problem.cost(&solution, secondary);
+    println!(
+        "Lexicographic: primary cost {} secondary cost {}",
+        primary_cost, secondary_cost
+    );
+    output_stats("primary_cost".to_string(), primary_cost.into());
+    output_stats("secondary_cost".to_string(), secondary_cost.into());
+    if primary_cost > bound.cost {
+        println!(
+            "Lexicographic: primary cost {} is above the optimum {}",
+            primary_cost, bound.cost
+        );
+        return Err(SolverError::NoSolution);
+    }
+    Ok(solution)
+}
@@ -35,7 +35,7 @@ pub fn minimize_solution(
                 .map(|(_visit_idx, visit)| {
                     add_ctsvar!(model,
                         // name : &format!("tn{}_v{}_tk{}", train_names[train_idx], visit_idx, resource_names[visit.resource_id]),
-                        bounds: visit.earliest..,
+                        bounds: visit.earliest_start()..,
                         obj: 1.0
                     )
                     .map_err(SolverError::GurobiError)
@@ -61,6 +61,15 @@ pub fn minimize_solution(
                         >= train.visits[visit_idx].travel_time),
                 )
                 .map_err(SolverError::GurobiError)?;
+            if let Some(max_travel_time) = train.visits[visit_idx].max_travel_time {
+                #[allow(clippy::useless_conversion)]
+                model
+                    .add_constr(
+                        "",
+                        c!((ts[visit_idx + 1]) - (ts[visit_idx]) <= max_travel_time),
+                    )
+                    .map_err(SolverError::GurobiError)?;
+            }
         }
     }
 
@@ -79,7 +88,19 @@ pub fn minimize_solution(
                 //     v2,
                 //     resource_names[problem.trains[t2].visits[v2].resource_id]
                 // ),
-                c!(t_vars[t1][v1 + 1] <= t_vars[t2][v2]),
+                c!(problem.headway((t1, v1), (t2, v2)) + t_vars[t1][v1 + 1] <= t_vars[t2][v2]),
+            )
+            .map_err(SolverError::GurobiError)?;
+    }
+
+    // Hard connections, turnarounds and fixed orders
+    for connection in problem.precedences().filter(|c| c.penalty.is_none()) {
+        let ((t1, v1), (t2, v2)) = (connection.from, connection.to);
+        #[allow(clippy::useless_conversion)]
+        model
+            .add_constr(
+                "",
+                c!(t_vars[t2][v2] - t_vars[t1][v1] >= connection.min_transfer),
             )
             .map_err(SolverError::GurobiError)?;
     }
@@ -4,9 +4,17 @@
 //! - `heuristic`         — improved heuristic, used as MaxSAT warm-start UB.
 //! - `minimize`          — UNSAT core minimisation.
 //! - `counting_solver`   — `SatSolver` wrapper recording vars/clauses.
+//! - `decomposition`     — solving independent train groups separately.
+//! - `lexicographic`     — optimising a secondary cost among primary optima.
+//! - `rolling_horizon`   — solving long instances in overlapping time windows.
+//! - `scenarios`         — robust rescheduling over delay scenarios.
 //! - `value_trace`       — per-iteration trace of cost values during a solve.
 pub mod counting_solver;
+pub mod decomposition;
 pub mod greedy;
 pub mod heuristic;
+pub mod lexicographic;
 pub mod minimize;
+pub mod rolling_horizon;
+pub mod scenarios;
 pub mod value_trace;
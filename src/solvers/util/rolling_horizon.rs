@@ -0,0 +1,191 @@
+//! Solving long instances in overlapping time windows.
+//!
+//! The instance is solved window by window, starting at the earliest time
+//! of any visit. A window of length `window` starting at `now` contains the
+//! visits of the remaining problem (see
+//! [`Problem::freeze_before`](crate::problem::Problem::freeze_before)) with an
+//! earliest time before its end. After a window is solved, the times before
+//! `now + window - overlap` are committed, and the next window starts there
+//! from the trains' positions at that time. When a window contains all the
+//! remaining visits, its schedule is final. The schedules of all windows are
+//! stitched together and verified against the whole instance.
+//!
+//! The result is feasible but not optimal in general: a window cannot see
+//! the trains that come after it, and committed times are never revised.
+
+use std::time::Instant;
+
+use serde_json::{json, Map, Value};
+
+use crate::problem::{DelayCostType, NamedProblem, Problem};
+use crate::solvers::SolverError;
+
+/// Solves `problem` in windows of length `window` that overlap by `overlap`,
+/// each with `solve`. Per-window statistics are reported as `windows`
+/// through `output_stats`, each with the map that `solve` collected its own
+/// statistics in as `solver_stats`.
+pub fn solve_rolling_horizon(
+    problem: &NamedProblem,
+    delay_cost_type: DelayCostType,
+    window: i32,
+    overlap: i32,
+    mut solve: impl FnMut(
+        &NamedProblem,
+        &mut Map<String, Value>,
+    ) -> Result<Vec<Vec<i32>>, SolverError>,
+    mut output_stats: impl FnMut(String, Value),
+) -> Result<Vec<Vec<i32>>, SolverError> {
+    assert!(0 <= overlap && overlap < window, "the overlap must be shorter than the window");
+
+    let mut realised = vec![Vec::new(); problem.problem.trains.len()];
+    let mut now = problem
+        .problem
+        .trains
+        .iter()
+        .flat_map(|t| t.visits.iter().map(|v| v.earliest))
+        .min()
+        .unwrap_or(0);
+    let mut stats = Vec::new();
+    let mut result = Ok(());
+    loop {
+        let frozen = problem.problem.freeze_before(now, &realised);
+        let end = now + window;
+        let (window_trains, window_problem, is_last) = slice_window(&frozen.problem, end);
+        if !window_trains.is_empty() {
+            let window_problem = NamedProblem {
+                problem: window_problem,
+                train_names: window_trains
+                    .iter()
+                    .map(|t| problem.train_names[frozen.trains[*t]].clone())
+                    .collect(),
+                resource_names: problem.resource_names.clone(),
+            };
+            let start = Instant::now();
+            let mut solver_stats = Map::new();
+            let solution = solve(&window_problem, &mut solver_stats);
+            let time_ms = start.elapsed().as_secs_f64() * 1000.0;
+            let n_visits = window_problem
+                .problem
+                .trains
+                .iter()
+                .map(|t| t.visits.len())
+                .sum::<usize>();
+            let (status, cost) = match &solution {
+                Ok(solution) => {
+                    let cost = window_problem.problem.cost(solution, delay_cost_type);
+                    ("ok", Some(cost))
+                }
+                Err(SolverError::NoSolution) => ("no_solution", None),
+                Err(SolverError::Timeout) => ("timeout", None),
+                Err(SolverError::OutOfMemory) => ("oom", None),
+                Err(SolverError::GurobiError(_)) => ("gurobi_error", None),
+            };
+            stats.push(json!({
+                "index": stats.len(),
+                "start": now,
+                "end": end,
+                "trains": window_trains.len(),
+                "visits": n_visits,
+                "status": status,
+                "cost": cost,
+                "sol_time": time_ms,
+                "solver_stats": solver_stats,
+            }));
+            let solution = match solution {
+                Ok(solution) => solution,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            };
+
+            // Trains outside the window keep what they had.
+            let mut frozen_solution = frozen
+                .trains
+                .iter()
+                .zip(frozen.dropped_visits.iter())
+                .map(|(t, dropped)| realised[*t].get(*dropped..).unwrap_or_default().to_vec())
+                .collect::<Vec<_>>();
+            for ((train_idx, window_train), mut times) in window_trains
+                .iter()
+                .zip(window_problem.problem.trains.iter())
+                .zip(solution)
+            {
+                // A train that was cut off leaves its last visit in the
+                // window no earlier than it can enter the next one, so that
+                // this time is not committed before the next window. The
+                // window kept the visit occupied for that long.
+                let n_visits = window_train.visits.len();
+                let train = &frozen.problem.trains[*train_idx];
+                if let Some(next_visit) = train.visits.get(n_visits).filter(|_| !times.is_empty()) {
+                    let t_in = times[n_visits - 1];
+                    let travel_time = train.visits[n_visits - 1].travel_time;
+                    times[n_visits] = (t_in + travel_time).max(next_visit.earliest);
+                }
+                frozen_solution[*train_idx] = times;
+            }
+            realised = frozen.expand_solution(&realised, &frozen_solution);
+        }
+        if is_last {
+            break;
+        }
+        now = end - overlap;
+    }
+
+    println!("Solved {} rolling-horizon windows of length {}", stats.len(), window);
+    output_stats("windows".to_string(), Value::Array(stats));
+    result?;
+
+    if problem
+        .problem
+        .verify_solution(&realised, delay_cost_type)
+        .is_none()
+    {
+        return Err(SolverError::NoSolution);
+    }
+    Ok(realised)
+}
+
+/// The trains of `problem` that have a visit with an earliest time before
+/// `end`, cut off at their first visit that does not. The last visit of a
+/// train that is cut off lasts at least until the earliest time of the
+/// next one. Connections to visits that are cut off and turnarounds from
+/// trains that are cut off are left out, and so are trains from the fixed
+/// orders whose visit is cut off. Also returns whether nothing was cut off.
+fn slice_window(problem: &Problem, end: i32) -> (Vec<usize>, Problem, bool) {
+    let trains = (0..problem.trains.len())
+        .filter(|t| problem.trains[*t].visits[0].earliest < end)
+        .collect::<Vec<_>>();
+    let mut window = problem.subproblem(&trains);
+    let mut is_last = trains.len() == problem.trains.len();
+    let mut cut_off = vec![false; trains.len()];
+    for (train, is_cut_off) in window.trains.iter_mut().zip(cut_off.iter_mut()) {
+        let n_visits = train.visits.iter().take_while(|v| v.earliest < end).count();
+        if n_visits < train.visits.len() {
+            let next_earliest = train.visits[n_visits].earliest;
+            let last = &mut train.visits[n_visits - 1];
+            last.travel_time = last.travel_time.max(next_earliest - last.earliest_start());
+            if let Some(max_travel_time) = last.max_travel_time.as_mut() {
+                *max_travel_time = (*max_travel_time).max(last.travel_time);
+            }
+            train.visits.truncate(n_visits);
+            train.visit_costs.truncate(n_visits);
+            *is_cut_off = true;
+            is_last = false;
+        }
+    }
+    let n_visits = window.trains.iter().map(|t| t.visits.len()).collect::<Vec<_>>();
+    window
+        .connections
+        .retain(|c| c.from.1 < n_visits[c.from.0] && c.to.1 < n_visits[c.to.0]);
+    window.turnarounds.retain(|t| !cut_off[t.from]);
+    for order in window.orders.iter_mut() {
+        let resource_id = order.resource_id;
+        order.trains.retain(|t| {
+            let visits = &window.trains[*t].visits;
+            visits.iter().any(|v| v.resource_id == resource_id)
+        });
+    }
+    window.orders.retain(|order| order.trains.len() >= 2);
+    (trains, window, is_last)
+}
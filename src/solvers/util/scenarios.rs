@@ -0,0 +1,307 @@
+//! Robust rescheduling over a set of delay scenarios.
+//!
+//! A [`DelayScenario`] shifts the `earliest` time of some visits, e.g. a
+//! forecast disruption of 3, 6 or 10 minutes. The scenarios are solved
+//! together as one scenario-expanded problem (see [`expand_scenarios`]): a
+//! disjoint copy of the instance per scenario, on resources of its own. The
+//! solver must make the copies pass every pair of conflicting visits in the
+//! same order and cancel the same trains, so the result is one train
+//! ordering with a schedule per scenario. `maxsat_ladder_sc` does this by
+//! sharing order literals between the copies.
+//!
+//! The ordering minimises either the weighted mean of the scenario costs
+//! or the largest scenario cost, see [`RobustObjective`].
+
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value};
+
+use crate::problem::{
+    BlockedInterval, Connection, DelayCostType, DelayScenario, Problem, TrainOrder, Turnaround,
+};
+use crate::solvers::SolverError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RobustObjective {
+    /// The weighted mean of the scenario costs.
+    Expected,
+    /// The largest scenario cost, regardless of the weights.
+    WorstCase,
+}
+
+impl RobustObjective {
+    pub fn from_name(value: &str) -> Option<RobustObjective> {
+        match value {
+            "expected" => Some(RobustObjective::Expected),
+            "worst" => Some(RobustObjective::WorstCase),
+            _ => None,
+        }
+    }
+}
+
+/// How a problem was expanded by [`expand_scenarios`], for the solver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScenarioExpansion {
+    /// The number of copies. Copy `s` has the trains
+    /// `s * n_trains..(s + 1) * n_trains` of the expanded problem.
+    pub n_scenarios: usize,
+    /// If set, the largest scenario cost is minimised instead of the sum,
+    /// given that it is at most this bound.
+    pub worst_case_bound: Option<usize>,
+}
+
+/// The scenario-expanded problem: a copy of `problem` per scenario, with
+/// the scenario's delays, in which the trains of copy `s` come after those
+/// of the copies before it and all resource ids are shifted past those of
+/// the copies before it. For the expected cost, the train weights and the
+/// cancellation, running-time and connection costs of each copy are
+/// multiplied by the scenario's weight.
+pub fn expand_scenarios(
+    problem: &Problem,
+    scenarios: &[DelayScenario],
+    objective: RobustObjective,
+) -> Problem {
+    let n_trains = problem.trains.len();
+    let n_resources = n_resources(problem);
+    let mut expanded = Problem {
+        name: problem.name.clone(),
+        trains: Vec::with_capacity(n_trains * scenarios.len()),
+        conflicts: Default::default(),
+        headways: Default::default(),
+        capacities: HashMap::new(),
+        connections: Vec::new(),
+        turnarounds: Vec::new(),
+        blocked: Vec::new(),
+        orders: Vec::new(),
+    };
+    expanded.headways.default = problem.headways.default;
+
+    for (s, scenario) in scenarios.iter().enumerate() {
+        let train = |t: usize| s * n_trains + t;
+        let resource = |r: usize| s * n_resources + r;
+        let weight = match objective {
+            RobustObjective::Expected => scenario.weight,
+            RobustObjective::WorstCase => 1,
+        };
+        let scale = |cost: i32| cost * weight as i32;
+
+        for mut copy in scenario.apply(problem).trains {
+            for visit in copy.visits.iter_mut() {
+                visit.resource_id = resource(visit.resource_id);
+            }
+            copy.weight *= weight;
+            copy.cancellation_cost = copy.cancellation_cost.map(scale);
+            copy.running_time_cost = copy.running_time_cost.map(scale);
+            expanded.trains.push(copy);
+        }
+        for (a, b) in problem.conflicts.iter() {
+            expanded.conflicts.insert(resource(a), resource(b));
+        }
+        for (r, headway) in problem.headways.resources.iter() {
+            expanded.headways.resources.insert(resource(*r), *headway);
+        }
+        for ((a, b), headway) in problem.headways.pairs.iter() {
+            expanded.headways.insert_pair(resource(*a), resource(*b), *headway);
+        }
+        for (r, capacity) in problem.capacities.iter() {
+            expanded.capacities.insert(resource(*r), *capacity);
+        }
+        expanded
+            .connections
+            .extend(problem.connections.iter().map(|c| Connection {
+                from: (train(c.from.0), c.from.1),
+                to: (train(c.to.0), c.to.1),
+                min_transfer: c.min_transfer,
+                penalty: c.penalty.map(scale),
+            }));
+        expanded
+            .turnarounds
+            .extend(problem.turnarounds.iter().map(|t| Turnaround {
+                from: train(t.from),
+                to: train(t.to),
+                time: t.time,
+            }));
+        expanded
+            .blocked
+            .extend(problem.blocked.iter().map(|b| BlockedInterval {
+                resource_id: resource(b.resource_id),
+                ..*b
+            }));
+        expanded
+            .orders
+            .extend(problem.orders.iter().map(|order| TrainOrder {
+                resource_id: resource(order.resource_id),
+                trains: order.trains.iter().map(|t| train(*t)).collect(),
+            }));
+    }
+    expanded
+}
+
+/// One more than the largest resource id used anywhere in `problem`.
+fn n_resources(problem: &Problem) -> usize {
+    let visits = problem
+        .trains
+        .iter()
+        .flat_map(|t| t.visits.iter().map(|v| v.resource_id));
+    let conflicts = problem.conflicts.iter().map(|(_, b)| b);
+    let headways = problem
+        .headways
+        .resources
+        .keys()
+        .copied()
+        .chain(problem.headways.pairs.keys().map(|(_, b)| *b));
+    let others = problem
+        .capacities
+        .keys()
+        .copied()
+        .chain(problem.blocked.iter().map(|b| b.resource_id))
+        .chain(problem.orders.iter().map(|o| o.resource_id));
+    visits
+        .chain(conflicts)
+        .chain(headways)
+        .chain(others)
+        .max()
+        .map_or(0, |r| r + 1)
+}
+
+/// Whether `first` leaves its visit, and the headway passes, before `second`
+/// enters its visit, in a schedule. `None` if they overlap.
+fn passes_first(
+    problem: &Problem,
+    schedule: &[Vec<i32>],
+    first: (usize, usize),
+    second: (usize, usize),
+) -> Option<bool> {
+    let headway = problem.headway(first, second);
+    let interval = |(t, v): (usize, usize)| (schedule[t][v], schedule[t][v + 1]);
+    let ((a_in, a_out), (b_in, b_out)) = (interval(first), interval(second));
+    match (b_in >= a_out + headway, a_in >= b_out + headway) {
+        (true, false) => Some(true),
+        (false, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether the schedules of all scenarios pass each pair of visits to
+/// conflicting resources in the same order. Cancelled trains are ignored.
+pub fn orders_agree(problem: &Problem, schedules: &[Vec<Vec<i32>>]) -> bool {
+    let visits = problem
+        .trains
+        .iter()
+        .enumerate()
+        .flat_map(|(t, train)| (0..train.visits.len()).map(move |v| (t, v)))
+        .collect::<Vec<_>>();
+    for (i, &a) in visits.iter().enumerate() {
+        for &b in visits[i + 1..].iter() {
+            let resource = |(t, v): (usize, usize)| problem.trains[t].visits[v].resource_id;
+            if a.0 == b.0 || !problem.conflicts.contains(resource(a), resource(b)) {
+                continue;
+            }
+            let mut orders = schedules
+                .iter()
+                .filter(|s| !s[a.0].is_empty() && !s[b.0].is_empty())
+                .filter_map(|s| passes_first(problem, s, a, b));
+            if let Some(order) = orders.next() {
+                if orders.any(|o| o != order) {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Finds one train ordering for all `scenarios` of `problem` that
+/// minimises `objective`, with `solve`, which is given the expanded problem
+/// and a map to collect its statistics in.
+///
+/// For the worst case, the scenarios are first solved for the sum of their
+/// costs, whose statistics are reported under `sum_phase`. The largest
+/// scenario cost of that ordering bounds the worst case for the second
+/// solve. Each scenario's schedule is verified against the scenario, and
+/// their costs are reported as `scenarios`, `expected_cost` and
+/// `worst_case_cost`. Returns the schedule of the first scenario.
+pub fn solve_robust(
+    problem: &Problem,
+    scenarios: &[DelayScenario],
+    objective: RobustObjective,
+    delay_cost_type: DelayCostType,
+    mut solve: impl FnMut(
+        &Problem,
+        ScenarioExpansion,
+        &mut Map<String, Value>,
+    ) -> Result<Vec<Vec<i32>>, SolverError>,
+    mut output_stats: impl FnMut(String, Value),
+) -> Result<Vec<Vec<i32>>, SolverError> {
+    assert!(!scenarios.is_empty(), "at least one scenario is needed");
+    let expanded = expand_scenarios(problem, scenarios, objective);
+    let scenario_costs = |solution: &[Vec<i32>]| -> Result<Vec<i32>, SolverError> {
+        let schedules = solution
+            .chunks(problem.trains.len())
+            .map(|c| c.to_vec())
+            .collect::<Vec<_>>();
+        if !orders_agree(problem, &schedules) {
+            return Err(SolverError::NoSolution);
+        }
+        let costs = scenarios
+            .iter()
+            .zip(schedules.iter())
+            .map(|(scenario, schedule)| {
+                scenario
+                    .apply(problem)
+                    .verify_solution(schedule, delay_cost_type)
+                    .ok_or(SolverError::NoSolution)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(costs)
+    };
+
+    let mut expansion = ScenarioExpansion {
+        n_scenarios: scenarios.len(),
+        worst_case_bound: None,
+    };
+    if objective == RobustObjective::WorstCase {
+        let mut sum_stats = Map::new();
+        let solution = solve(&expanded, expansion, &mut sum_stats);
+        output_stats("sum_phase".to_string(), sum_stats.into());
+        let bound = *scenario_costs(&solution?)?.iter().max().unwrap();
+        println!("Robust: worst case of the sum optimum is {}", bound);
+        expansion.worst_case_bound = Some(bound as usize);
+    }
+
+    let mut stats = Map::new();
+    let solution = solve(&expanded, expansion, &mut stats);
+    for (k, v) in stats {
+        output_stats(k, v);
+    }
+    let solution = solution?;
+    let costs = scenario_costs(&solution)?;
+
+    let total_weight = scenarios.iter().map(|s| s.weight).sum::<usize>();
+    let expected_cost = scenarios
+        .iter()
+        .zip(costs.iter())
+        .map(|(s, c)| s.weight as f64 * *c as f64)
+        .sum::<f64>()
+        / total_weight.max(1) as f64;
+    let worst_case_cost = *costs.iter().max().unwrap();
+    println!(
+        "Robust: {} scenarios, expected cost {:.2}, worst case cost {}",
+        scenarios.len(),
+        expected_cost,
+        worst_case_cost
+    );
+    output_stats(
+        "scenarios".to_string(),
+        scenarios
+            .iter()
+            .zip(costs.iter())
+            .map(|(s, c)| json!({ "name": s.name, "weight": s.weight, "cost": c }))
+            .collect::<Vec<_>>()
+            .into(),
+    );
+    output_stats("expected_cost".to_string(), expected_cost.into());
+    output_stats("worst_case_cost".to_string(), worst_case_cost.into());
+
+    Ok(solution[..problem.trains.len()].to_vec())
+}